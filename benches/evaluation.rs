@@ -0,0 +1,88 @@
+//! Compare the different ways of working out the total value and weight of a set of
+//! choices, on the 25,000 items in `knapsacks/BigProblem1.txt`:
+//!
+//! - `value then weight`: calling `Knapsack::value` and then `Knapsack::weight`, which is
+//!   what `CliffScorer` used to do, going through the `Bitstring` twice.
+//! - `fused`: `Knapsack::value_and_weight`, which gets both in one pass.
+//! - `packed`: `Knapsack::packed_value_and_weight` on choices that are already packed
+//!   into `u64` words, which only visits the chosen items.
+//! - `pack then packed`: packing a `Bitstring` first, and then doing the same.
+//!
+//! Each of these is run on random choices where each item is chosen with probability
+//! 0.5 (like the initial population of a run) and 0.02 (more like the populations later
+//! in a run, once most of the items have been dropped to fit in the knapsack).
+//!
+//! Run this with `cargo bench`.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use ec_linear::genome::bitstring::Bitstring;
+use knapsack_from_scratch::{knapsack::Knapsack, packed::PackedBits};
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64;
+
+const NUM_GENOMES: usize = 100;
+
+fn evaluation(c: &mut Criterion) {
+    let knapsack = Knapsack::from_file_path("knapsacks/BigProblem1.txt")
+        .expect("Failed to read knapsacks/BigProblem1.txt");
+    let mut rng = Pcg64::seed_from_u64(0);
+
+    let mut group = c.benchmark_group("BigProblem1");
+    for density in [0.5, 0.02] {
+        let genomes = (0..NUM_GENOMES)
+            .map(|_| {
+                (0..knapsack.num_items())
+                    .map(|_| rng.random_bool(density))
+                    .collect::<Bitstring>()
+            })
+            .collect::<Vec<_>>();
+        let packed = genomes.iter().map(PackedBits::from).collect::<Vec<_>>();
+
+        group.bench_with_input(
+            BenchmarkId::new("value then weight", density),
+            &genomes,
+            |b, genomes| {
+                b.iter(|| {
+                    for genome in genomes {
+                        black_box((knapsack.value(genome), knapsack.weight(genome)));
+                    }
+                });
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("fused", density),
+            &genomes,
+            |b, genomes| {
+                b.iter(|| {
+                    for genome in genomes {
+                        black_box(knapsack.value_and_weight(genome));
+                    }
+                });
+            },
+        );
+        group.bench_with_input(BenchmarkId::new("packed", density), &packed, |b, packed| {
+            b.iter(|| {
+                for genome in packed {
+                    black_box(knapsack.packed_value_and_weight(genome));
+                }
+            });
+        });
+        group.bench_with_input(
+            BenchmarkId::new("pack then packed", density),
+            &genomes,
+            |b, genomes| {
+                b.iter(|| {
+                    for genome in genomes {
+                        black_box(knapsack.packed_value_and_weight(&PackedBits::from(genome)));
+                    }
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, evaluation);
+criterion_main!(benches);
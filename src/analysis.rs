@@ -0,0 +1,171 @@
+use std::fmt::{self, Display};
+
+use crate::{
+    bounds::{greedy, lp_bound},
+    knapsack::Knapsack,
+    statistics::quantile,
+};
+
+/// Statistics about a 0/1 knapsack instance, to see what kind of instance it is (and
+/// whether it's even interesting) before spending time running on it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstanceAnalysis {
+    pub num_items: usize,
+    pub capacity: u64,
+    pub total_value: u64,
+    pub total_weight: u64,
+    /// The number of items that are heavier than the capacity on their own, so they can
+    /// never be in a legal solution.
+    pub too_heavy: usize,
+    /// The Pearson correlation between the items' values and weights, or `None` if
+    /// there are fewer than two items or all the values or weights are the same.
+    pub correlation: Option<f64>,
+    /// The minimum, lower quartile, median, upper quartile, and maximum of the items'
+    /// value/weight ratios, leaving out items with no weight, or `None` if all the
+    /// items have no weight.
+    pub ratios: Option<[f64; 5]>,
+    /// The value of the greedy solution (see [`greedy`]), which is a lower bound on the
+    /// best value.
+    pub greedy_value: u64,
+    /// The linear programming bound (see [`lp_bound`]), which is an upper bound on the
+    /// best value.
+    pub lp_bound: u64,
+    pub best_known: Option<u64>,
+}
+
+impl InstanceAnalysis {
+    /// Work out the statistics for `knapsack`.
+    #[must_use]
+    pub fn of(knapsack: &Knapsack) -> Self {
+        let items = knapsack.items();
+        let values = items
+            .iter()
+            .map(|item| item.value() as f64)
+            .collect::<Vec<_>>();
+        let weights = items
+            .iter()
+            .map(|item| item.weight() as f64)
+            .collect::<Vec<_>>();
+        let mut ratios = items
+            .iter()
+            .filter(|item| item.weight() > 0)
+            .map(|item| item.value() as f64 / item.weight() as f64)
+            .collect::<Vec<_>>();
+        ratios.sort_by(f64::total_cmp);
+        let ratios =
+            (!ratios.is_empty()).then(|| [0.0, 0.25, 0.5, 0.75, 1.0].map(|q| quantile(&ratios, q)));
+
+        Self {
+            num_items: items.len(),
+            capacity: knapsack.capacity(),
+            total_value: items.iter().map(|item| item.value()).sum(),
+            total_weight: knapsack.total_weight(),
+            too_heavy: items
+                .iter()
+                .filter(|item| item.weight() > knapsack.capacity())
+                .count(),
+            correlation: correlation(&values, &weights),
+            ratios,
+            greedy_value: knapsack.value(&greedy(knapsack)),
+            lp_bound: lp_bound(knapsack),
+            best_known: knapsack.best_known(),
+        }
+    }
+
+    /// Whether every item fits in the knapsack at once, so the best solution is just to
+    /// take all of them.
+    #[must_use]
+    pub const fn is_trivial(&self) -> bool {
+        self.total_weight <= self.capacity
+    }
+}
+
+impl Display for InstanceAnalysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Items: {}", self.num_items)?;
+        writeln!(f, "Capacity: {}", self.capacity)?;
+        writeln!(
+            f,
+            "Total weight: {} ({:.3} times the capacity)",
+            self.total_weight,
+            self.total_weight as f64 / self.capacity as f64
+        )?;
+        writeln!(f, "Total value: {}", self.total_value)?;
+        writeln!(f, "Items heavier than the capacity: {}", self.too_heavy)?;
+        match self.correlation {
+            Some(correlation) => writeln!(f, "Value/weight correlation: {correlation:.4}")?,
+            None => writeln!(f, "Value/weight correlation: undefined")?,
+        }
+        if let Some([min, lower, median, upper, max]) = self.ratios {
+            writeln!(
+                f,
+                "Value/weight ratios: min {min:.4}, lower quartile {lower:.4}, median {median:.4}, upper quartile {upper:.4}, max {max:.4}"
+            )?;
+        }
+        writeln!(f, "Greedy value (lower bound): {}", self.greedy_value)?;
+        writeln!(f, "LP bound (upper bound): {}", self.lp_bound)?;
+        if let Some(best_known) = self.best_known {
+            writeln!(f, "Best known value: {best_known}")?;
+        }
+        if self.is_trivial() {
+            writeln!(
+                f,
+                "Warning: every item fits in the knapsack at once, so the best solution is to take them all"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The Pearson correlation between `xs` and `ys`, or `None` if there are fewer than two
+/// of them or either has no variance.
+fn correlation(xs: &[f64], ys: &[f64]) -> Option<f64> {
+    if xs.len() < 2 {
+        return None;
+    }
+    let mean = |numbers: &[f64]| numbers.iter().sum::<f64>() / numbers.len() as f64;
+    let (mean_x, mean_y) = (mean(xs), mean(ys));
+    let (mut covariance, mut variance_x, mut variance_y) = (0.0, 0.0, 0.0);
+    for (x, y) in xs.iter().zip(ys) {
+        covariance += (x - mean_x) * (y - mean_y);
+        variance_x += (x - mean_x).powi(2);
+        variance_y += (y - mean_y).powi(2);
+    }
+    (variance_x > 0.0 && variance_y > 0.0).then(|| covariance / (variance_x * variance_y).sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{correlation, InstanceAnalysis};
+    use crate::{item::Item, knapsack::Knapsack};
+
+    #[test]
+    fn analyse_tiny() {
+        let items = vec![Item::new(1, 3, 8), Item::new(2, 2, 8), Item::new(3, 9, 1)];
+        let analysis = InstanceAnalysis::of(&Knapsack::new(items, 10));
+        assert_eq!(analysis.num_items, 3);
+        assert_eq!(analysis.total_weight, 17);
+        assert_eq!(analysis.too_heavy, 0);
+        assert_eq!(analysis.greedy_value, 12);
+        // Items 3 and 1, and 1/8 of item 2.
+        assert_eq!(analysis.lp_bound, 12);
+        assert!(!analysis.is_trivial());
+        assert_eq!(analysis.ratios.map(|ratios| ratios[4]), Some(9.0));
+    }
+
+    #[test]
+    fn everything_fitting_is_trivial() {
+        let items = vec![Item::new(1, 3, 8), Item::new(2, 2, 8)];
+        let analysis = InstanceAnalysis::of(&Knapsack::new(items, 10_000_000_000));
+        assert!(analysis.is_trivial());
+        assert!(analysis.to_string().contains("Warning"));
+    }
+
+    #[test]
+    fn correlations() {
+        assert_eq!(correlation(&[1.0, 2.0, 3.0], &[2.0, 4.0, 6.0]), Some(1.0));
+        assert_eq!(correlation(&[1.0, 2.0, 3.0], &[3.0, 2.0, 1.0]), Some(-1.0));
+        assert_eq!(correlation(&[1.0, 2.0], &[5.0, 5.0]), None);
+        assert_eq!(correlation(&[1.0], &[1.0]), None);
+    }
+}
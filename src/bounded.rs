@@ -0,0 +1,441 @@
+use std::{path::Path, sync::Arc};
+
+use anyhow::{anyhow, Context};
+use ec_core::individual::scorer::Scorer;
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    cliff_score::CliffScore,
+    evolution::Variation,
+    instance_lines::{read_instance_file, InstanceLines},
+    item::Item,
+    knapsack::Knapsack,
+    overflow::checked_total,
+};
+
+/// The largest dynamic programming table (number of cells) [`BoundedKnapsack::solve_exactly`]
+/// will build. This keeps it to "small" instances, where it can be used to check the
+/// results of evolutionary runs.
+const MAX_DP_CELLS: u64 = 200_000_000;
+
+/// Representation of a bounded knapsack problem, where we can take up to
+/// `max_copies` copies of each item instead of just 0 or 1.
+///
+/// Solutions are integer vectors (`Vec<u64>`) with one entry per item, saying how many
+/// copies of that item to include. The unbounded knapsack problem, where there's no
+/// limit on the number of copies, is the special case where the bound for each item
+/// is the number of copies that fit in the (otherwise empty) knapsack; see
+/// [`BoundedKnapsack::unbounded`].
+#[derive(Debug)]
+pub struct BoundedKnapsack {
+    items: Vec<Item>,
+    /// The maximum number of copies of each item, in the same order as `items`.
+    max_copies: Vec<u64>,
+    capacity: u64,
+}
+
+impl BoundedKnapsack {
+    /// Construct a bounded knapsack instance from a collection of items, the
+    /// maximum number of copies of each item, and a capacity.
+    ///
+    /// # Errors
+    ///
+    /// This fails if there isn't exactly one bound per item, or if taking the maximum
+    /// number of copies of every item would give a total value or weight too big for a `u64`
+    /// (in which case [`BoundedKnapsack::value`] or [`BoundedKnapsack::weight`] could overflow).
+    pub fn new(items: Vec<Item>, max_copies: Vec<u64>, capacity: u64) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            items.len() == max_copies.len(),
+            "There are {} items but {} bounds on the number of copies",
+            items.len(),
+            max_copies.len()
+        );
+        let with_all_copies = |amount: fn(&Item) -> u64| {
+            items
+                .iter()
+                .zip(&max_copies)
+                .map(move |(item, &copies)| u128::from(amount(item)) * u128::from(copies))
+        };
+        checked_total(with_all_copies(Item::value), "value (with every copy)")?;
+        checked_total(with_all_copies(Item::weight), "weight (with every copy)")?;
+        Ok(Self {
+            items,
+            max_copies,
+            capacity,
+        })
+    }
+
+    /// Construct an unbounded knapsack instance from a (0/1) `knapsack`, where any number
+    /// of copies of each item can be chosen. The bound for each item is then just how many
+    /// copies of it would fit in the knapsack on their own.
+    ///
+    /// # Errors
+    ///
+    /// This fails if an item has no weight (and some value), since we could then take
+    /// infinitely many copies of it.
+    pub fn unbounded(knapsack: &Knapsack) -> anyhow::Result<Self> {
+        let max_copies = knapsack
+            .items()
+            .iter()
+            .map(|item| match item.weight() {
+                0 if item.value() > 0 => Err(anyhow!(
+                    "Item {} has no weight, so an unbounded knapsack could hold infinitely many copies of it",
+                    item.id()
+                )),
+                0 => Ok(0),
+                weight => Ok(knapsack.capacity() / weight),
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Self::new(knapsack.items().to_vec(), max_copies, knapsack.capacity())
+    }
+
+    /// Get the number of items in this knapsack instance.
+    #[must_use]
+    pub fn num_items(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Get the capacity of the knapsack, i.e., the maximum total weight it can hold.
+    #[must_use]
+    pub const fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    /// Get the value of a current set of choices for this knapsack. `counts` says how
+    /// many copies of each `Item` to include.
+    #[must_use]
+    pub fn value(&self, counts: &[u64]) -> u64 {
+        self.items
+            .iter()
+            .zip(counts)
+            .map(|(item, count)| item.value() * count)
+            .sum()
+    }
+
+    /// Get the total weight of a current set of choices for this knapsack. `counts` says how
+    /// many copies of each `Item` to include.
+    #[must_use]
+    pub fn weight(&self, counts: &[u64]) -> u64 {
+        self.items
+            .iter()
+            .zip(counts)
+            .map(|(item, count)| item.weight() * count)
+            .sum()
+    }
+
+    /// Are all the counts within the bounds on the number of copies of each item?
+    #[must_use]
+    pub fn within_bounds(&self, counts: &[u64]) -> bool {
+        counts.len() == self.items.len()
+            && counts
+                .iter()
+                .zip(&self.max_copies)
+                .all(|(count, max)| count <= max)
+    }
+
+    /// Parse a bounded knapsack instance from a text file.
+    ///
+    /// This is the same format as described in [`Knapsack::from_file_path`], except that
+    /// each item line has a fourth integer: the maximum number of copies of that item.
+    ///
+    /// ```text
+    /// 3
+    /// 1 3 8 2
+    /// 2 2 8 1
+    /// 3 9 1 5
+    /// 10
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This can fail if:
+    ///    - We fail to read the file, or
+    ///    - The file contents have the wrong format
+    pub fn from_file_path(file_path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let (contents, source) = read_instance_file(file_path.as_ref())?;
+        let mut lines = InstanceLines::new(&contents, &source);
+        let num_items = lines.parse_next::<usize>("the number of items")?;
+
+        let mut items = Vec::new();
+        let mut max_copies = Vec::new();
+        for n in 1..=num_items {
+            let fields = lines.next_item(n, num_items, &["id", "value", "weight", "copies"])?;
+            items.push(Item::new(fields[0], fields[1], fields[2]));
+            max_copies.push(fields[3]);
+        }
+
+        let capacity = lines.parse_next("the capacity")?;
+        lines.finish("the capacity")?;
+
+        Self::new(items, max_copies, capacity)
+            .with_context(|| format!("The knapsack in {source} can't be used"))
+    }
+
+    /// Find an optimal solution using dynamic programming, returning its value and the
+    /// number of copies of each item. This is only practical for small capacities, and is
+    /// meant for checking the results of evolutionary runs on small instances.
+    ///
+    /// Each item is split into "bundles" of 1, 2, 4, ... copies (so any count up to its bound
+    /// can be made from a subset of its bundles), and the bundles are then treated as a
+    /// regular 0/1 knapsack.
+    ///
+    /// # Errors
+    ///
+    /// This fails if the dynamic programming table would be too big.
+    pub fn solve_exactly(&self) -> anyhow::Result<(u64, Vec<u64>)> {
+        // Bundles of copies of an item, as `(item index, copies, weight, value)`.
+        let mut bundles = Vec::new();
+        for (index, (item, &max)) in self.items.iter().zip(&self.max_copies).enumerate() {
+            let mut remaining = max;
+            let mut size = 1;
+            while remaining > 0 {
+                let copies = size.min(remaining);
+                let weight = item.weight().checked_mul(copies);
+                // Bundles that could never fit can't be part of a solution.
+                if let Some(weight) = weight.filter(|&weight| weight <= self.capacity) {
+                    bundles.push((index, copies, weight, item.value() * copies));
+                }
+                remaining -= copies;
+                size = size.saturating_mul(2);
+            }
+        }
+
+        let num_cells = (bundles.len() as u64).saturating_mul(self.capacity.saturating_add(1));
+        anyhow::ensure!(
+            num_cells <= MAX_DP_CELLS,
+            "This instance is too big to solve exactly: it would need {num_cells} table entries"
+        );
+        let capacity = usize::try_from(self.capacity)?;
+
+        // `best[c]` is the best value we can get with a total weight of at most `c`, and
+        // `taken[b][c]` records whether bundle `b` was used to get `best[c]` at that point.
+        let mut best = vec![0u64; capacity + 1];
+        let mut taken = vec![vec![false; capacity + 1]; bundles.len()];
+        for (b, &(_, _, weight, value)) in bundles.iter().enumerate() {
+            let weight = usize::try_from(weight)?;
+            for c in (weight..=capacity).rev() {
+                let with_bundle = best[c - weight] + value;
+                if with_bundle > best[c] {
+                    best[c] = with_bundle;
+                    taken[b][c] = true;
+                }
+            }
+        }
+
+        // Walk back through the bundles to recover which ones we took.
+        let mut counts = vec![0; self.items.len()];
+        let mut c = capacity;
+        for (b, &(index, copies, weight, _)) in bundles.iter().enumerate().rev() {
+            if taken[b][c] {
+                counts[index] += copies;
+                c -= usize::try_from(weight)?;
+            }
+        }
+        Ok((best[capacity], counts))
+    }
+}
+
+/// A scorer for bounded knapsacks that returns `CliffScore::Overloaded` if the
+/// choices exceed the capacity (or the bound on the number of copies of an item),
+/// and the total value otherwise.
+pub struct BoundedCliffScorer {
+    knapsack: Arc<BoundedKnapsack>,
+}
+
+impl BoundedCliffScorer {
+    #[must_use]
+    pub fn new(knapsack: impl Into<Arc<BoundedKnapsack>>) -> Self {
+        Self {
+            knapsack: knapsack.into(),
+        }
+    }
+}
+
+impl Scorer<Vec<u64>> for BoundedCliffScorer {
+    type Score = CliffScore;
+
+    fn score(&self, genome: &Vec<u64>) -> Self::Score {
+        if !self.knapsack.within_bounds(genome)
+            || self.knapsack.weight(genome) > self.knapsack.capacity()
+        {
+            CliffScore::Overloaded
+        } else {
+            CliffScore::Score(self.knapsack.value(genome))
+        }
+    }
+}
+
+/// The variation for integer genomes on bounded knapsacks.
+///
+/// - Random genomes are built by going through the items in a random order, and adding a
+///   random number of copies of each that still fits. Choosing counts uniformly up to each
+///   bound would almost always overload the knapsack, which leaves a cliff scorer with
+///   nothing to work with.
+/// - Children are made by uniform crossover, then replacing each count with probability
+///   `1 / num_items` by a new count chosen uniformly between 0 and that item's bound.
+pub struct BoundedVariation {
+    knapsack: Arc<BoundedKnapsack>,
+}
+
+impl BoundedVariation {
+    #[must_use]
+    pub fn new(knapsack: impl Into<Arc<BoundedKnapsack>>) -> Self {
+        Self {
+            knapsack: knapsack.into(),
+        }
+    }
+}
+
+impl Variation<Vec<u64>> for BoundedVariation {
+    fn random<R: Rng + ?Sized>(&self, rng: &mut R) -> Vec<u64> {
+        let knapsack = &self.knapsack;
+        let mut order = (0..knapsack.num_items()).collect::<Vec<_>>();
+        order.shuffle(rng);
+        let mut counts = vec![0; knapsack.num_items()];
+        let mut remaining = knapsack.capacity();
+        for index in order {
+            let item = &knapsack.items[index];
+            let fits = remaining
+                .checked_div(item.weight())
+                .unwrap_or(u64::MAX)
+                .min(knapsack.max_copies[index]);
+            let count = rng.random_range(0..=fits);
+            counts[index] = count;
+            remaining -= count * item.weight();
+        }
+        counts
+    }
+
+    fn make_child<R: Rng + ?Sized>(
+        &self,
+        first_parent: &Vec<u64>,
+        second_parent: &Vec<u64>,
+        rng: &mut R,
+    ) -> Vec<u64> {
+        let mutation_rate = 1.0 / self.knapsack.num_items().max(1) as f64;
+        first_parent
+            .iter()
+            .zip(second_parent)
+            .zip(&self.knapsack.max_copies)
+            .map(|((&first, &second), &max)| {
+                if rng.random_bool(mutation_rate) {
+                    rng.random_range(0..=max)
+                } else if rng.random_bool(0.5) {
+                    first
+                } else {
+                    second
+                }
+            })
+            .collect()
+    }
+}
+
+#[expect(clippy::unwrap_used, reason = ".unwrap() is reasonable in tests")]
+#[cfg(test)]
+mod tests {
+    use ec_core::individual::scorer::Scorer;
+    use rand::SeedableRng;
+    use rand_pcg::Pcg64;
+    use test_case::test_case;
+
+    use super::{BoundedCliffScorer, BoundedKnapsack, BoundedVariation};
+    use crate::{cliff_score::CliffScore, evolution::Variation, item::Item, knapsack::Knapsack};
+
+    fn knapsack(capacity: u64) -> BoundedKnapsack {
+        BoundedKnapsack::new(
+            vec![Item::new(1, 5, 8), Item::new(2, 9, 6), Item::new(3, 2, 7)],
+            vec![2, 3, 1],
+            capacity,
+        )
+        .unwrap()
+    }
+
+    /// Find the best value by trying every possible combination of counts.
+    fn brute_force(knapsack: &BoundedKnapsack) -> u64 {
+        let mut best = 0;
+        let mut counts = vec![0; knapsack.num_items()];
+        loop {
+            if knapsack.weight(&counts) <= knapsack.capacity() {
+                best = best.max(knapsack.value(&counts));
+            }
+            // Step to the next combination of counts, like an odometer.
+            let Some(index) = (0..counts.len()).find(|&i| counts[i] < knapsack.max_copies[i])
+            else {
+                return best;
+            };
+            counts[index] += 1;
+            counts[..index].fill(0);
+        }
+    }
+
+    #[test_case([0, 0, 0], 0; "choose no items")]
+    #[test_case([0, 2, 0], 18; "choose two copies of one item")]
+    #[test_case([2, 0, 1], 12; "choose copies of two items")]
+    fn test_values(counts: [u64; 3], expected_value: u64) {
+        assert_eq!(knapsack(100).value(&counts), expected_value);
+    }
+
+    #[test_case([0, 0, 0], 0; "choose no items")]
+    #[test_case([0, 2, 0], 12; "choose two copies of one item")]
+    #[test_case([2, 0, 1], 23; "choose copies of two items")]
+    fn test_weights(counts: [u64; 3], expected_weight: u64) {
+        assert_eq!(knapsack(100).weight(&counts), expected_weight);
+    }
+
+    #[test_case(vec![0, 3, 0], CliffScore::Score(27); "fits")]
+    #[test_case(vec![2, 1, 0], CliffScore::Overloaded; "too heavy")]
+    #[test_case(vec![0, 0, 2], CliffScore::Overloaded; "too many copies")]
+    fn test_scores(counts: Vec<u64>, expected_score: CliffScore) {
+        let scorer = BoundedCliffScorer::new(knapsack(20));
+        assert_eq!(scorer.score(&counts), expected_score);
+    }
+
+    #[test]
+    fn unbounded_bounds() {
+        let knapsack = Knapsack::new(vec![Item::new(1, 5, 8), Item::new(2, 9, 6)], 20);
+        let unbounded = BoundedKnapsack::unbounded(&knapsack).unwrap();
+        assert_eq!(unbounded.max_copies, vec![2, 3]);
+    }
+
+    #[test]
+    fn unbounded_weightless_item() {
+        let knapsack = Knapsack::new(vec![Item::new(1, 5, 0)], 20);
+        assert!(BoundedKnapsack::unbounded(&knapsack).is_err());
+    }
+
+    #[test]
+    fn unbounded_values_that_could_overflow() {
+        // Up to 2^40 copies of an item worth 2^30 each could be worth 2^70 in total.
+        let knapsack = Knapsack::new(vec![Item::new(1, 1 << 30, 1)], 1 << 40);
+        assert!(BoundedKnapsack::unbounded(&knapsack).is_err());
+    }
+
+    #[test_case(0; "nothing fits")]
+    #[test_case(14; "some things fit")]
+    #[test_case(20; "a bit more fits")]
+    #[test_case(100; "everything fits")]
+    fn solve_exactly_matches_brute_force(capacity: u64) {
+        let knapsack = knapsack(capacity);
+        let (value, counts) = knapsack.solve_exactly().unwrap();
+        assert_eq!(value, brute_force(&knapsack));
+        assert_eq!(knapsack.value(&counts), value);
+        assert!(knapsack.within_bounds(&counts));
+        assert!(knapsack.weight(&counts) <= capacity);
+    }
+
+    #[test]
+    fn variation_stays_in_bounds() {
+        let knapsack = std::sync::Arc::new(knapsack(20));
+        let variation = BoundedVariation::new(std::sync::Arc::clone(&knapsack));
+        let mut rng = Pcg64::seed_from_u64(3);
+        for _ in 0..100 {
+            let first = variation.random(&mut rng);
+            let second = variation.random(&mut rng);
+            // Random genomes should always fit.
+            assert!(knapsack.within_bounds(&first));
+            assert!(knapsack.weight(&first) <= knapsack.capacity());
+            let child = variation.make_child(&first, &second, &mut rng);
+            assert!(knapsack.within_bounds(&child));
+        }
+    }
+}
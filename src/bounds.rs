@@ -0,0 +1,103 @@
+use std::cmp::Ordering;
+
+use ec_linear::genome::bitstring::Bitstring;
+
+use crate::{item::Item, knapsack::Knapsack};
+
+/// Compare the value/weight ratios of two items, by cross-multiplying so there's no
+/// rounding (and items with no weight have the highest ratio).
+fn compare_ratios(a: &Item, b: &Item) -> Ordering {
+    (u128::from(a.value()) * u128::from(b.weight()))
+        .cmp(&(u128::from(b.value()) * u128::from(a.weight())))
+}
+
+/// The indices of the items that fit in the knapsack on their own, from the highest to
+/// the lowest value/weight ratio. Items heavier than the capacity can't be in any legal
+/// solution, so they're left out.
+#[must_use]
+pub fn by_ratio(knapsack: &Knapsack) -> Vec<usize> {
+    let items = knapsack.items();
+    let mut order = (0..items.len())
+        .filter(|&index| items[index].weight() <= knapsack.capacity())
+        .collect::<Vec<_>>();
+    order.sort_by(|&a, &b| compare_ratios(&items[b], &items[a]));
+    order
+}
+
+/// The greedy solution: go through the items from the highest to the lowest value/weight
+/// ratio, taking each one that still fits. This is always a legal solution, so its value
+/// is a lower bound on the best value.
+#[must_use]
+pub fn greedy(knapsack: &Knapsack) -> Bitstring {
+    let mut choices = vec![false; knapsack.num_items()];
+    let mut remaining = knapsack.capacity();
+    for index in by_ratio(knapsack) {
+        let weight = knapsack.items()[index].weight();
+        if weight <= remaining {
+            remaining -= weight;
+            choices[index] = true;
+        }
+    }
+    Bitstring::from_iter(choices)
+}
+
+/// The Dantzig bound, i.e., the best value of the linear programming relaxation where
+/// we can take any fraction of an item, rounded down. We take whole items from the
+/// highest to the lowest value/weight ratio until the next one (the _critical_ item)
+/// doesn't fit, and then the fraction of it that does. No legal solution can be worth
+/// more than this.
+#[must_use]
+pub fn lp_bound(knapsack: &Knapsack) -> u64 {
+    let mut value = 0;
+    let mut remaining = knapsack.capacity();
+    for index in by_ratio(knapsack) {
+        let item = &knapsack.items()[index];
+        if item.weight() > remaining {
+            // `remaining < weight`, so the fraction of the critical item's value is less
+            // than its value, and fits in a `u64`.
+            let fraction =
+                u128::from(item.value()) * u128::from(remaining) / u128::from(item.weight());
+            return value + fraction as u64;
+        }
+        remaining -= item.weight();
+        value += item.value();
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{by_ratio, greedy, lp_bound};
+    use crate::{item::Item, knapsack::Knapsack};
+
+    fn knapsack() -> Knapsack {
+        // Ratios 2.5, 3, 1, and 1 (but too heavy).
+        let items = vec![
+            Item::new(1, 10, 4),
+            Item::new(2, 30, 10),
+            Item::new(3, 5, 5),
+            Item::new(4, 20, 20),
+        ];
+        Knapsack::new(items, 12)
+    }
+
+    #[test]
+    fn items_by_ratio_skip_heavy_items() {
+        assert_eq!(by_ratio(&knapsack()), [1, 0, 2]);
+    }
+
+    #[test]
+    fn greedy_takes_what_fits() {
+        let knapsack = knapsack();
+        let choices = greedy(&knapsack);
+        assert_eq!(knapsack.value_and_weight(&choices), (30, 10));
+    }
+
+    #[test]
+    fn lp_bound_takes_part_of_the_critical_item() {
+        // All of item 2, and 2/4 of item 1.
+        assert_eq!(lp_bound(&knapsack()), 35);
+        let everything_fits = Knapsack::new(vec![Item::new(1, 3, 1), Item::new(2, 4, 2)], 10);
+        assert_eq!(lp_bound(&everything_fits), 7);
+    }
+}
@@ -0,0 +1,192 @@
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use ec_core::individual::ec::EcIndividual;
+use ec_linear::genome::bitstring::Bitstring;
+use rand_pcg::Pcg64;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cli::ProblemKind, cliff_score::CliffScore, formats::InstanceFormat,
+    results::bitstring_as_string,
+};
+
+/// The name of the checkpoint file inside the checkpoint directory. There's only
+/// ever one (the latest) checkpoint; each new one replaces the previous one.
+const CHECKPOINT_FILE_NAME: &str = "checkpoint.json";
+
+/// The settings that a run was started with. A run can only be resumed with
+/// the same settings it was started with, since otherwise the saved genomes could
+/// mean something else entirely (or even have the wrong length).
+///
+/// The problem, instance, format, and reduction weren't saved in the first
+/// checkpoints; those load with the defaults for them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunSettings {
+    pub knapsack: PathBuf,
+    #[serde(default)]
+    pub problem: ProblemKind,
+    /// Which instance in the file, for files that contain several.
+    #[serde(default)]
+    pub instance: usize,
+    #[serde(default)]
+    pub format: Option<InstanceFormat>,
+    /// Whether the instance was reduced (with `--reduce`) before the run.
+    #[serde(default)]
+    pub reduce: bool,
+    pub tournament_size: usize,
+    pub population_size: usize,
+    pub max_generations: usize,
+}
+
+/// An individual as it's stored in a checkpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedIndividual {
+    #[serde(with = "bitstring_as_string")]
+    genome: Bitstring,
+    score: CliffScore,
+}
+
+impl From<&EcIndividual<Bitstring, CliffScore>> for SavedIndividual {
+    fn from(individual: &EcIndividual<Bitstring, CliffScore>) -> Self {
+        Self {
+            genome: individual.genome.clone(),
+            score: individual.test_results,
+        }
+    }
+}
+
+impl From<SavedIndividual> for EcIndividual<Bitstring, CliffScore> {
+    fn from(saved: SavedIndividual) -> Self {
+        Self::new(saved.genome, saved.score)
+    }
+}
+
+/// Everything needed to continue a run exactly where it left off.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub settings: RunSettings,
+    /// The last generation that was completed (and reported on) before the checkpoint.
+    pub generation: usize,
+    pub population: Vec<SavedIndividual>,
+    pub best_in_run: Option<SavedIndividual>,
+    /// The state of the random number generator after `generation` was created.
+    pub rng: Pcg64,
+}
+
+impl Checkpoint {
+    /// Write this checkpoint to the checkpoint file in `dir`, replacing any previous
+    /// checkpoint there. The checkpoint is written to a temporary file first and then
+    /// renamed, so a crash while checkpointing leaves the previous checkpoint intact.
+    ///
+    /// # Errors
+    ///
+    /// This fails if the directory can't be created or the checkpoint can't be written.
+    pub fn save(&self, dir: impl AsRef<Path>) -> anyhow::Result<()> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create the checkpoint directory {dir:?}"))?;
+        let temp_path = dir.join(format!("{CHECKPOINT_FILE_NAME}.tmp"));
+        let mut writer = BufWriter::new(
+            File::create(&temp_path)
+                .with_context(|| format!("Failed to create the checkpoint file {temp_path:?}"))?,
+        );
+        serde_json::to_writer(&mut writer, self)?;
+        writer.flush()?;
+        drop(writer);
+        fs::rename(&temp_path, dir.join(CHECKPOINT_FILE_NAME))?;
+        Ok(())
+    }
+
+    /// Load the latest checkpoint from `dir`.
+    ///
+    /// # Errors
+    ///
+    /// This fails if there's no checkpoint in `dir`, or it can't be parsed.
+    pub fn load(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = dir.as_ref().join(CHECKPOINT_FILE_NAME);
+        let file = File::open(&path)
+            .with_context(|| format!("Failed to open the checkpoint file {path:?}"))?;
+        serde_json::from_reader(BufReader::new(file))
+            .with_context(|| format!("Failed to parse the checkpoint file {path:?}"))
+    }
+
+    /// The population saved in this checkpoint.
+    #[must_use]
+    pub fn population(&self) -> Vec<EcIndividual<Bitstring, CliffScore>> {
+        self.population.iter().cloned().map(Into::into).collect()
+    }
+
+    /// The best individual in the run up to this checkpoint, if there is one.
+    #[must_use]
+    pub fn best_in_run(&self) -> Option<EcIndividual<Bitstring, CliffScore>> {
+        self.best_in_run.clone().map(Into::into)
+    }
+}
+
+#[expect(clippy::unwrap_used, reason = ".unwrap() is reasonable in tests")]
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use ec_core::individual::ec::EcIndividual;
+    use ec_linear::genome::bitstring::Bitstring;
+    use rand::{Rng, SeedableRng};
+    use rand_pcg::Pcg64;
+
+    use super::{Checkpoint, RunSettings, SavedIndividual};
+    use crate::{cli::ProblemKind, cliff_score::CliffScore};
+
+    #[test]
+    fn save_and_load() {
+        let dir = env::temp_dir().join(format!("knapsack-checkpoint-{}", std::process::id()));
+        let individual = EcIndividual::new(
+            Bitstring::from_iter([true, false, true]),
+            CliffScore::Score(12),
+        );
+        let mut rng = Pcg64::seed_from_u64(5);
+        let _: u64 = rng.random();
+        let checkpoint = Checkpoint {
+            settings: RunSettings {
+                knapsack: "knapsacks/tiny.txt".into(),
+                problem: ProblemKind::Knapsack,
+                instance: 0,
+                format: None,
+                reduce: false,
+                tournament_size: 2,
+                population_size: 1,
+                max_generations: 10,
+            },
+            generation: 3,
+            population: vec![SavedIndividual::from(&individual)],
+            best_in_run: Some(SavedIndividual::from(&individual)),
+            rng: rng.clone(),
+        };
+
+        checkpoint.save(&dir).unwrap();
+        let mut loaded = Checkpoint::load(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(loaded, checkpoint);
+        assert_eq!(loaded.population(), vec![individual.clone()]);
+        assert_eq!(loaded.best_in_run(), Some(individual));
+        // The restored random number generator should carry on exactly where the saved one was.
+        assert_eq!(loaded.rng.random::<u64>(), rng.random::<u64>());
+    }
+
+    #[test]
+    fn older_settings_load_with_defaults() {
+        let settings: RunSettings = serde_json::from_str(
+            r#"{"knapsack":"knapsacks/tiny.txt","tournament_size":2,"population_size":1,"max_generations":10}"#,
+        )
+        .unwrap();
+        assert_eq!(settings.problem, ProblemKind::Knapsack);
+        assert_eq!(settings.instance, 0);
+        assert_eq!(settings.format, None);
+        assert!(!settings.reduce);
+    }
+}
@@ -0,0 +1,397 @@
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    comparison::RunGroup, evolution::Crossover, experiment::MAX_SEED, formats::InstanceFormat,
+    generator::Generator, multiple_choice::ClassConstraint, transform::Transformation,
+};
+
+/// Evolve solutions to a knapsack problem.
+#[derive(Debug, Parser)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    /// Something to do other than evolving a solution.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// The knapsack instance to run on, or `-` to read it from standard input.
+    #[arg(long, default_value = "knapsacks/SmallProblem4.txt")]
+    pub knapsack: PathBuf,
+
+    /// The format of the instance file, for the problems that use 0/1 knapsack
+    /// instances (`knapsack`, `unbounded`, and `multi-objective`). If this isn't given,
+    /// it's worked out from the file's extension or contents.
+    #[arg(long, value_enum)]
+    pub format: Option<InstanceFormat>,
+
+    /// What kind of knapsack problem the instance file describes.
+    #[arg(long, value_enum, default_value_t = ProblemKind::Knapsack)]
+    pub problem: ProblemKind,
+
+    /// The search algorithm to use. Everything other than the genetic algorithm only
+    /// works with 0/1 knapsacks (`--problem knapsack`).
+    #[arg(long, value_enum, default_value_t = Algorithm::Ga)]
+    pub algorithm: Algorithm,
+
+    /// Which instance to run on, counting from 0, for files that contain
+    /// several instances (like the OR-Library `mknap` files, or Pisinger's files).
+    #[arg(long, default_value_t = 0)]
+    pub instance: usize,
+
+    /// For multiple-choice problems, how many items must be chosen from each class.
+    #[arg(long, value_enum, default_value_t = ClassConstraint::ExactlyOne)]
+    pub class_constraint: ClassConstraint,
+
+    /// For multi-objective problems, write the final Pareto front to this CSV file.
+    #[arg(long)]
+    pub pareto_front: Option<PathBuf>,
+
+    /// Write per-generation statistics, one JSON object per line, to this file.
+    #[arg(long)]
+    pub results: Option<PathBuf>,
+
+    /// Include the per-locus bit frequencies in the results file. This adds one number
+    /// per item per generation, so it makes the results file much bigger.
+    #[arg(long, requires = "results")]
+    pub locus_frequencies: bool,
+
+    /// Periodically save the state of the run to this directory, so it can be resumed with
+    /// `--resume` if it's interrupted. This uses our own (seedable) implementation of the
+    /// same genetic algorithm instead of `course_helpers::ec_run::Run`.
+    #[arg(long)]
+    pub checkpoint_dir: Option<PathBuf>,
+
+    /// How many generations to run between checkpoints.
+    #[arg(long, default_value_t = 25, value_parser = clap::value_parser!(u64).range(1..))]
+    pub checkpoint_every: u64,
+
+    /// Continue the run from the latest checkpoint in `--checkpoint-dir`. This needs the
+    /// same knapsack as the original run; the results file (if any) is picked up where
+    /// the checkpoint left off.
+    #[arg(long, requires = "checkpoint_dir")]
+    pub resume: bool,
+
+    /// The seed for the random number generator when using our own implementation of the
+    /// genetic algorithm (with `--checkpoint-dir`, or for bounded, unbounded, multiple, and
+    /// multi-objective problems), or one of the other algorithms. If this isn't given, a
+    /// random seed is chosen (and printed out). It's an error to give a seed for a run that
+    /// can't use it.
+    #[arg(long, conflicts_with = "resume")]
+    pub seed: Option<u64>,
+
+    /// For bounded and unbounded problems, also find the optimal solution with dynamic
+    /// programming at the end of the run, to check how close the run got. This is only
+    /// practical for small capacities. For multiple knapsacks, check that the best
+    /// assignment in the run really is legal.
+    #[arg(long)]
+    pub verify: bool,
+
+    /// For 0/1 knapsacks (`--problem knapsack`), work out the choices for as many items as
+    /// we can before the run (dropping items that can never fit, and fixing items with
+    /// the linear programming bound), and only search over the rest. Scores during the
+    /// run are for the reduced instance; the best in the run is mapped back to the
+    /// original instance at the end.
+    #[arg(long)]
+    pub reduce: bool,
+}
+
+/// The things we can do other than evolving a solution.
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Convert a 0/1 knapsack instance from one file format to another.
+    Convert(ConvertArgs),
+    /// Print statistics about a 0/1 knapsack instance, e.g., its bounds and whether
+    /// it's trivial.
+    Analyze(AnalyzeArgs),
+    /// Generate a 0/1 knapsack instance in one of Pisinger's classes.
+    Generate(GenerateArgs),
+    /// Derive a new 0/1 knapsack instance from an existing one, e.g., by changing its
+    /// capacity or keeping some of its items.
+    Transform(TransformArgs),
+    /// Compare the best values found by two or more groups of runs, using their
+    /// structured results (`--results`).
+    Compare(CompareArgs),
+    /// Turn the printed output of old runs (like `Outputs/ts_2_pID_Sm1/output_1.txt`)
+    /// into structured results files, so they can be compared and plotted.
+    Import(ImportArgs),
+    /// Export the convergence curves (quartiles of the best score in each generation)
+    /// of groups of runs as CSV, and optionally plot them as SVG.
+    Convergence(ConvergenceArgs),
+    /// Write a Markdown table for each instance in an experiment directory, with the
+    /// best, mean, and median value found by each configuration and its gap to a bound.
+    Report(ReportArgs),
+    /// Run many independent replicates of the genetic algorithm on a 0/1 knapsack at
+    /// once, each with its own seed and results file.
+    Replicates(ReplicatesArgs),
+    /// Run an experiment described in a TOML file: every combination of operators and
+    /// parameters on every instance, each for a number of replicates.
+    Experiment(ExperimentArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct ConvertArgs {
+    /// The instance to convert, or `-` to read it from standard input.
+    pub input: PathBuf,
+
+    /// Where to write the converted instance, or `-` for standard output.
+    pub output: PathBuf,
+
+    /// The format of the input. If this isn't given, it's worked out from the
+    /// input's extension or contents.
+    #[arg(long, value_enum)]
+    pub from: Option<InstanceFormat>,
+
+    /// The format to convert to. If this isn't given, it's worked out from the
+    /// output's extension, and is `jooken` if that doesn't help.
+    #[arg(long, value_enum)]
+    pub to: Option<InstanceFormat>,
+
+    /// Which instance to convert, counting from 0, for files that contain several.
+    #[arg(long, default_value_t = 0)]
+    pub instance: usize,
+}
+
+#[derive(Debug, Args)]
+pub struct AnalyzeArgs {
+    /// The instance to analyze, or `-` to read it from standard input.
+    pub input: PathBuf,
+
+    /// The format of the instance. If this isn't given, it's worked out from the
+    /// extension or contents.
+    #[arg(long, value_enum)]
+    pub format: Option<InstanceFormat>,
+
+    /// Which instance to analyze, counting from 0, for files that contain several.
+    #[arg(long, default_value_t = 0)]
+    pub instance: usize,
+}
+
+#[derive(Debug, Args)]
+pub struct GenerateArgs {
+    #[command(flatten)]
+    pub generator: Generator,
+
+    /// The seed for the random number generator. If this isn't given, a random seed
+    /// is chosen (and printed out).
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Where to write the instance, or `-` for standard output.
+    #[arg(long, default_value = "-")]
+    pub output: PathBuf,
+
+    /// The format to write the instance in. If this isn't given, it's worked out from
+    /// the output's extension, and is `jooken` if that doesn't help.
+    #[arg(long, value_enum)]
+    pub format: Option<InstanceFormat>,
+}
+
+#[derive(Debug, Args)]
+pub struct TransformArgs {
+    /// The instance to start from, or `-` to read it from standard input.
+    pub input: PathBuf,
+
+    /// Where to write the new instance (in the Jooken format), or `-` for standard output.
+    pub output: PathBuf,
+
+    /// The format of the input. If this isn't given, it's worked out from the
+    /// input's extension or contents.
+    #[arg(long, value_enum)]
+    pub from: Option<InstanceFormat>,
+
+    /// Which instance to start from, counting from 0, for files that contain several.
+    #[arg(long, default_value_t = 0)]
+    pub instance: usize,
+
+    #[command(flatten)]
+    pub transformation: Transformation,
+
+    /// The seed for the random number generator, for `--items` and `--shuffle`. If this
+    /// isn't given, a random seed is chosen (and recorded in the new instance's header).
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Args)]
+pub struct CompareArgs {
+    /// The groups of runs to compare, each as `name=path` or just `path`, where the path
+    /// is a results file or a directory of them (every `.jsonl` file in it).
+    #[arg(required = true, num_args = 2..)]
+    pub groups: Vec<RunGroup>,
+}
+
+#[derive(Debug, Args)]
+pub struct ImportArgs {
+    /// An old log, or a directory to search (including subdirectories) for `output_*.txt`
+    /// logs.
+    pub input: PathBuf,
+
+    /// Where to write the results file for a single log, or the directory to write them
+    /// to (in the same layout as the input directory) for a directory of logs.
+    pub output: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct ConvergenceArgs {
+    /// The groups of runs (configurations), each as `name=path` or just `path`, where the
+    /// path is a results file or a directory of them (every `.jsonl` file in it).
+    #[arg(required = true)]
+    pub groups: Vec<RunGroup>,
+
+    /// Where to write the CSV, or `-` for standard output.
+    #[arg(long, default_value = "-")]
+    pub csv: PathBuf,
+
+    /// Also plot the curves as an SVG file here.
+    #[arg(long)]
+    pub svg: Option<PathBuf>,
+
+    /// The title of the plot.
+    #[arg(long, default_value = "Convergence")]
+    pub title: String,
+}
+
+#[derive(Debug, Args)]
+pub struct ReportArgs {
+    /// The experiment directory. Each subdirectory is either named
+    /// `<configuration>_pID_<instance>` (like the imported `Outputs/`), or named after an
+    /// instance with a subdirectory of results files for each configuration.
+    pub experiment: PathBuf,
+
+    /// The instance file for an instance name in the experiment, as `name=path` (like
+    /// `Sm3=knapsacks/SmallProblem3.txt`), used to work out the gap to its bound. This
+    /// can be given more than once.
+    #[arg(long = "instance", value_parser = parse_instance_file)]
+    pub instances: Vec<(String, PathBuf)>,
+
+    /// Where to look for the instance files that aren't given with `--instance`; the
+    /// instance `<name>` is read from `<knapsacks>/<name>.txt` if that exists.
+    #[arg(long, default_value = "knapsacks")]
+    pub knapsacks: PathBuf,
+
+    /// Where to write the report, or `-` for standard output.
+    #[arg(long, default_value = "-")]
+    pub output: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct ReplicatesArgs {
+    /// The 0/1 knapsack instance to run on.
+    pub knapsack: PathBuf,
+
+    /// The format of the instance file. If this isn't given, it's worked out from the
+    /// file's extension or contents.
+    #[arg(long, value_enum)]
+    pub format: Option<InstanceFormat>,
+
+    /// Which instance to run on, counting from 0, for files that contain several.
+    #[arg(long, default_value_t = 0)]
+    pub instance: usize,
+
+    /// The directory to write each run's results file (`run_<number>.jsonl`) to, along
+    /// with a summary (`replicates.csv`) of every run's seed and best score, and the
+    /// configuration of the runs (`config.toml`).
+    #[arg(long)]
+    pub output_dir: PathBuf,
+
+    /// How many runs to do.
+    #[arg(long, default_value_t = 30)]
+    pub runs: usize,
+
+    /// How many threads to use. This is how many runs happen at once, and the evaluation
+    /// within those runs shares the same number of threads. Defaults to the number of
+    /// cores.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub threads: Option<u64>,
+
+    /// Each run's seed is drawn from a generator seeded with this, so the whole set of
+    /// runs can be repeated. If this isn't given, a random seed is chosen (and printed
+    /// out).
+    #[arg(long, value_parser = clap::value_parser!(u64).range(..=MAX_SEED))]
+    pub seed: Option<u64>,
+
+    #[arg(long, default_value_t = 8)]
+    pub tournament_size: usize,
+
+    #[arg(long, default_value_t = 1_000)]
+    pub population_size: usize,
+
+    #[arg(long, default_value_t = 1_000)]
+    pub max_generations: usize,
+
+    #[arg(long, value_enum, default_value_t = Crossover::Uniform)]
+    pub crossover: Crossover,
+
+    /// The average number of bits mutation flips in each child.
+    #[arg(long, default_value_t = 1.0)]
+    pub mutation_flips: f64,
+}
+
+#[derive(Debug, Args)]
+pub struct ExperimentArgs {
+    /// The experiment file (see `experiment.toml` for an example).
+    pub config: PathBuf,
+
+    /// How many threads to use for each configuration's runs. Defaults to the number of
+    /// cores.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub threads: Option<u64>,
+}
+
+fn parse_instance_file(s: &str) -> Result<(String, PathBuf), String> {
+    match s.split_once('=') {
+        Some((name, path)) if !name.is_empty() && !path.is_empty() => {
+            Ok((name.to_string(), PathBuf::from(path)))
+        }
+        _ => Err(format!("expected `name=path`, but got '{s}'")),
+    }
+}
+
+/// The kinds of knapsack problem we know how to read and solve.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProblemKind {
+    /// A 0/1 knapsack with a single capacity, in any of the formats
+    /// `--format` supports.
+    #[default]
+    Knapsack,
+    /// A multi-dimensional knapsack, with a capacity in each dimension, in the
+    /// OR-Library `mknap` format.
+    Multidimensional,
+    /// A bounded knapsack, where each item line has a fourth field with the maximum
+    /// number of copies of that item that can be chosen.
+    Bounded,
+    /// An unbounded knapsack, in the same format as `knapsack`, where any number
+    /// of copies of each item can be chosen.
+    Unbounded,
+    /// A multiple-choice knapsack, where each item line has a fourth field with the
+    /// class of that item, and (see `--class-constraint`) exactly one or at most one
+    /// item can be chosen from each class.
+    MultipleChoice,
+    /// A quadratic knapsack, where pairs of chosen items add extra value, in the
+    /// standard QKP format.
+    Quadratic,
+    /// A 0/1 knapsack in the same format as `knapsack`, treated as a trade-off between
+    /// maximizing value and minimizing weight, and solved with NSGA-II.
+    MultiObjective,
+    /// Several knapsacks, each with its own capacity, in the same format as `knapsack`
+    /// except that the last line has the capacity of each knapsack.
+    MultipleKnapsacks,
+}
+
+/// The search algorithms we can run. All the ones other than `Ga` work on a single
+/// solution, using incremental evaluation so each step only takes constant time, and get
+/// the same number of evaluations as the genetic algorithm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Algorithm {
+    /// A generational genetic algorithm with tournament selection.
+    Ga,
+    /// Hill climbing with random flips and swaps.
+    HillClimb,
+    /// The (1+1) EA, which only uses mutation.
+    OnePlusOne,
+    /// Simulated annealing with random flips and swaps.
+    Annealing,
+}
@@ -1,4 +1,8 @@
-#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
+use serde::{Deserialize, Serialize};
+
+#[derive(
+    Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone, Serialize, Deserialize,
+)]
 pub enum CliffScore {
     // The derived implementation of `PartialOrd` will use the order of the
     // variants, with the top variants "smaller" than the lower variants.
@@ -21,19 +21,31 @@
 //     }
 // }
 
+use std::sync::Arc;
+
 use ec_core::individual::scorer::Scorer;
 use ec_linear::genome::bitstring::Bitstring;
 
 use crate::{cliff_score::CliffScore, knapsack::Knapsack};
 
 pub struct CliffScorer {
-    knapsack: Knapsack,
+    // This is shared (via `Arc`) so that the knapsack is still available
+    // elsewhere (e.g., for reporting) after the scorer has been handed to a run.
+    knapsack: Arc<Knapsack>,
 }
 
 impl CliffScorer {
     #[must_use]
-    pub const fn new(knapsack: Knapsack) -> Self {
-        Self { knapsack }
+    pub fn new(knapsack: impl Into<Arc<Knapsack>>) -> Self {
+        Self {
+            knapsack: knapsack.into(),
+        }
+    }
+
+    /// The knapsack instance this scorer scores choices against.
+    #[must_use]
+    pub fn knapsack(&self) -> &Knapsack {
+        &self.knapsack
     }
 }
 
@@ -41,8 +53,7 @@ impl Scorer<Bitstring> for CliffScorer {
     type Score = CliffScore;
 
     fn score(&self, genome: &Bitstring) -> Self::Score {
-        let value = self.knapsack.value(genome);
-        let weight = self.knapsack.weight(genome);
+        let (value, weight) = self.knapsack.value_and_weight(genome);
         if weight > self.knapsack.capacity() {
             CliffScore::Overloaded
         } else {
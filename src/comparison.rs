@@ -0,0 +1,232 @@
+use std::{
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::Context;
+
+use crate::{
+    cliff_score::CliffScore,
+    results::{read_records, RunRecord},
+    statistics::{Description, RankSumTest},
+};
+
+/// A named group of runs to compare, e.g., all the runs with tournament size 2, given on
+/// the command line as `name=path` or just `path`. The path is either a single results
+/// file or a directory, in which case every `.jsonl` file in it is a run. Without a name,
+/// the group is named after the last part of the path.
+#[derive(Debug, Clone)]
+pub struct RunGroup {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+impl FromStr for RunGroup {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, path) = match s.split_once('=') {
+            Some((name, path)) => (name.to_string(), PathBuf::from(path)),
+            None => {
+                let path = PathBuf::from(s);
+                let name = path
+                    .file_name()
+                    .map_or_else(|| s.to_string(), |name| name.to_string_lossy().into_owned());
+                (name, path)
+            }
+        };
+        anyhow::ensure!(!name.is_empty(), "The group '{s}' needs a name");
+        Ok(Self { name, path })
+    }
+}
+
+impl RunGroup {
+    /// The results files for the runs in this group, in order.
+    ///
+    /// # Errors
+    ///
+    /// This fails if the group's path is a directory we can't read, or one without any
+    /// `.jsonl` files in it.
+    pub fn results_files(&self) -> anyhow::Result<Vec<PathBuf>> {
+        if !self.path.is_dir() {
+            return Ok(vec![self.path.clone()]);
+        }
+        let mut files = fs::read_dir(&self.path)
+            .with_context(|| format!("Failed to read the directory {:?}", self.path))?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        files.retain(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "jsonl")
+        });
+        files.sort();
+        anyhow::ensure!(
+            !files.is_empty(),
+            "There are no results (.jsonl) files in {:?}",
+            self.path
+        );
+        Ok(files)
+    }
+}
+
+/// The best score in a results file, over every generation (and the best records at the
+/// end, if there are any).
+///
+/// # Errors
+///
+/// This fails if the file can't be read or parsed, or has no records in it (e.g., because
+/// the run crashed before it finished a generation).
+pub fn best_in_run(path: &Path) -> anyhow::Result<CliffScore> {
+    read_records(path)?
+        .into_iter()
+        .map(|record| match record {
+            RunRecord::Generation(generation) => generation.best,
+            RunRecord::Best(best) => best.score,
+        })
+        .max()
+        .with_context(|| format!("The results file {path:?} doesn't have any records in it"))
+}
+
+/// The best values of the runs in each group, to be compared.
+#[derive(Debug)]
+pub struct Comparison {
+    groups: Vec<(String, Vec<f64>)>,
+    /// The number of runs in each group that never found a legal solution.
+    infeasible: Vec<usize>,
+}
+
+impl Comparison {
+    /// Read the best score of every run in every group. A run that never found a legal
+    /// solution counts as a value of 0, so it's worse than any run that did.
+    ///
+    /// # Errors
+    ///
+    /// This fails if there are fewer than two groups, or any of the results files can't
+    /// be read.
+    pub fn read(groups: &[RunGroup]) -> anyhow::Result<Self> {
+        anyhow::ensure!(groups.len() >= 2, "We need at least two groups to compare");
+        let mut values = Vec::new();
+        let mut infeasible = Vec::new();
+        for group in groups {
+            let scores = group
+                .results_files()?
+                .iter()
+                .map(|path| best_in_run(path))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            infeasible.push(
+                scores
+                    .iter()
+                    .filter(|&&score| score == CliffScore::Overloaded)
+                    .count(),
+            );
+            let group_values = scores
+                .into_iter()
+                .map(|score| match score {
+                    CliffScore::Score(value) => value as f64,
+                    CliffScore::Overloaded => 0.0,
+                })
+                .collect();
+            values.push((group.name.clone(), group_values));
+        }
+        Ok(Self {
+            groups: values,
+            infeasible,
+        })
+    }
+
+    /// A Markdown report with a table describing each group, and a table comparing each
+    /// pair of groups with the Mann-Whitney U test and the Vargha-Delaney A12 effect size.
+    #[must_use]
+    pub fn report(&self) -> String {
+        let mut report = String::new();
+        let _ = writeln!(
+            report,
+            "| Group | Runs | Infeasible | Mean | Median | Std. dev. | 95% CI for mean |"
+        );
+        let _ = writeln!(report, "|---|---:|---:|---:|---:|---:|---|");
+        for ((name, values), infeasible) in self.groups.iter().zip(&self.infeasible) {
+            let Some(description) = Description::of(values) else {
+                continue;
+            };
+            let interval = description.confidence_interval.map_or_else(
+                || "-".to_string(),
+                |(low, high)| format!("{low:.2} to {high:.2}"),
+            );
+            let _ = writeln!(
+                report,
+                "| {name} | {} | {infeasible} | {:.2} | {:.2} | {:.2} | {interval} |",
+                description.count,
+                description.mean,
+                description.median,
+                description.standard_deviation
+            );
+        }
+
+        let _ = writeln!(report);
+        let _ = writeln!(report, "| First | Second | U | p-value | A12 | Effect |");
+        let _ = writeln!(report, "|---|---|---:|---:|---:|---|");
+        for (i, (first_name, first)) in self.groups.iter().enumerate() {
+            for (second_name, second) in &self.groups[i + 1..] {
+                let Some(test) = RankSumTest::of(first, second) else {
+                    continue;
+                };
+                let _ = writeln!(
+                    report,
+                    "| {first_name} | {second_name} | {:.1} | {:.4} | {:.3} | {} |",
+                    test.u,
+                    test.p_value,
+                    test.a12,
+                    test.magnitude()
+                );
+            }
+        }
+        report
+    }
+}
+
+#[expect(clippy::unwrap_used, reason = ".unwrap() is reasonable in tests")]
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf};
+
+    use super::{best_in_run, Comparison, RunGroup};
+
+    #[test]
+    fn group_names() {
+        let named = "ts_2=Outputs/ts_2".parse::<RunGroup>().unwrap();
+        assert_eq!(named.name, "ts_2");
+        assert_eq!(named.path, PathBuf::from("Outputs/ts_2"));
+        let unnamed = "results/ts_8".parse::<RunGroup>().unwrap();
+        assert_eq!(unnamed.name, "ts_8");
+        assert!("=results".parse::<RunGroup>().is_err());
+    }
+
+    #[test]
+    fn report_has_a_row_per_group_and_pair() {
+        let comparison = Comparison {
+            groups: vec![
+                ("a".to_string(), vec![1.0, 2.0, 3.0]),
+                ("b".to_string(), vec![4.0, 5.0, 6.0]),
+                ("c".to_string(), vec![0.0, 5.0, 6.0]),
+            ],
+            infeasible: vec![0, 0, 1],
+        };
+        let report = comparison.report();
+        assert!(report.contains("| a | 3 | 0 | 2.00 | 2.00 | 1.00 |"));
+        assert!(report.contains("| c | 3 | 1 |"));
+        assert!(report.contains("| a | b | 0.0 |"));
+        assert!(report.contains("| b | c |"));
+        assert!(report.contains("| a | c |"));
+    }
+
+    #[test]
+    fn empty_results_are_an_error() {
+        let path = env::temp_dir().join(format!("knapsack-empty-{}.jsonl", std::process::id()));
+        fs::write(&path, "").unwrap();
+        let best = best_in_run(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(best.is_err());
+    }
+}
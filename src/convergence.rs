@@ -0,0 +1,367 @@
+use std::{collections::BTreeMap, io::Write};
+
+use crate::{
+    cliff_score::CliffScore,
+    comparison::RunGroup,
+    results::{read_records, RunRecord},
+    statistics::quantile,
+};
+
+/// The spread of the best score in each generation over a group of runs (replicates of
+/// the same configuration). As in [`crate::comparison`], an `Overloaded` best counts as
+/// a value of 0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvergenceCurve {
+    pub name: String,
+    pub points: Vec<CurvePoint>,
+}
+
+/// The lower quartile, median, and upper quartile of the best scores in one generation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CurvePoint {
+    pub generation: usize,
+    /// The number of runs that got to this generation.
+    pub runs: usize,
+    pub lower_quartile: f64,
+    pub median: f64,
+    pub upper_quartile: f64,
+}
+
+impl ConvergenceCurve {
+    /// Read the curve for all the runs in `group`.
+    ///
+    /// # Errors
+    ///
+    /// This fails if any of the group's results files can't be read.
+    pub fn read(group: &RunGroup) -> anyhow::Result<Self> {
+        let runs = group
+            .results_files()?
+            .iter()
+            .map(|path| {
+                Ok(read_records(path)?
+                    .into_iter()
+                    .filter_map(|record| match record {
+                        RunRecord::Generation(generation) => Some((
+                            generation.generation,
+                            match generation.best {
+                                CliffScore::Score(value) => value as f64,
+                                CliffScore::Overloaded => 0.0,
+                            },
+                        )),
+                        RunRecord::Best(_) => None,
+                    })
+                    .collect())
+            })
+            .collect::<anyhow::Result<Vec<Vec<_>>>>()?;
+        Ok(Self::from_runs(group.name.clone(), &runs))
+    }
+
+    /// Work out the curve from the `(generation, best)` pairs of each run.
+    #[must_use]
+    pub fn from_runs(name: String, runs: &[Vec<(usize, f64)>]) -> Self {
+        let mut by_generation = BTreeMap::<usize, Vec<f64>>::new();
+        for &(generation, best) in runs.iter().flatten() {
+            by_generation.entry(generation).or_default().push(best);
+        }
+        let points = by_generation
+            .into_iter()
+            .map(|(generation, mut bests)| {
+                bests.sort_by(f64::total_cmp);
+                CurvePoint {
+                    generation,
+                    runs: bests.len(),
+                    lower_quartile: quantile(&bests, 0.25),
+                    median: quantile(&bests, 0.5),
+                    upper_quartile: quantile(&bests, 0.75),
+                }
+            })
+            .collect();
+        Self { name, points }
+    }
+}
+
+/// Write `curves` as CSV, with one row per configuration and generation.
+///
+/// # Errors
+///
+/// This fails if we can't write to `writer`.
+pub fn write_csv(curves: &[ConvergenceCurve], mut writer: impl Write) -> anyhow::Result<()> {
+    writeln!(
+        writer,
+        "configuration,generation,runs,lower_quartile,median,upper_quartile"
+    )?;
+    for curve in curves {
+        for point in &curve.points {
+            writeln!(
+                writer,
+                "{},{},{},{},{},{}",
+                curve.name,
+                point.generation,
+                point.runs,
+                point.lower_quartile,
+                point.median,
+                point.upper_quartile
+            )?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+const WIDTH: f64 = 800.0;
+const HEIGHT: f64 = 500.0;
+const LEFT: f64 = 90.0;
+const RIGHT: f64 = 180.0;
+const TOP: f64 = 40.0;
+const BOTTOM: f64 = 60.0;
+const COLOURS: [&str; 8] = [
+    "#1f77b4", "#d62728", "#2ca02c", "#ff7f0e", "#9467bd", "#8c564b", "#e377c2", "#17becf",
+];
+
+/// Draw `curves` as an SVG line plot, with a line for each configuration's median and a
+/// shaded band between its quartiles.
+///
+/// # Errors
+///
+/// This fails if we can't write to `writer`.
+pub fn write_svg(
+    curves: &[ConvergenceCurve],
+    title: &str,
+    mut writer: impl Write,
+) -> anyhow::Result<()> {
+    let points = curves.iter().flat_map(|curve| &curve.points);
+    let max_generation = points
+        .clone()
+        .map(|point| point.generation)
+        .max()
+        .unwrap_or(0);
+    let low = points
+        .clone()
+        .map(|point| point.lower_quartile)
+        .fold(f64::INFINITY, f64::min);
+    let high = points
+        .map(|point| point.upper_quartile)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let (low, high) = if low.is_finite() {
+        (low, high)
+    } else {
+        (0.0, 1.0)
+    };
+    let y_ticks = Ticks::covering(low, high);
+    let (y_min, y_max) = (y_ticks.values[0], y_ticks.values[y_ticks.values.len() - 1]);
+    let x_ticks = Ticks::covering(0.0, max_generation as f64);
+    let x_max = x_ticks.values[x_ticks.values.len() - 1];
+
+    let plot_width = WIDTH - LEFT - RIGHT;
+    let plot_height = HEIGHT - TOP - BOTTOM;
+    let x = |generation: f64| LEFT + generation / x_max * plot_width;
+    let y = |value: f64| TOP + plot_height - (value - y_min) / (y_max - y_min) * plot_height;
+
+    writeln!(
+        writer,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{HEIGHT}" viewBox="0 0 {WIDTH} {HEIGHT}" font-family="sans-serif" font-size="12">"#
+    )?;
+    writeln!(
+        writer,
+        r#"<rect width="{WIDTH}" height="{HEIGHT}" fill="white"/>"#
+    )?;
+    writeln!(
+        writer,
+        r#"<text x="{}" y="{}" text-anchor="middle" font-size="16">{}</text>"#,
+        LEFT + plot_width / 2.0,
+        TOP / 2.0 + 6.0,
+        escape(title)
+    )?;
+
+    // Grid lines and tick labels.
+    for &tick in &y_ticks.values {
+        writeln!(
+            writer,
+            r##"<line x1="{LEFT}" x2="{}" y1="{y:.1}" y2="{y:.1}" stroke="#ddd"/><text x="{}" y="{:.1}" text-anchor="end">{}</text>"##,
+            LEFT + plot_width,
+            LEFT - 6.0,
+            y(tick) + 4.0,
+            y_ticks.label(tick),
+            y = y(tick),
+        )?;
+    }
+    for &tick in &x_ticks.values {
+        writeln!(
+            writer,
+            r##"<line x1="{x:.1}" x2="{x:.1}" y1="{TOP}" y2="{}" stroke="#ddd"/><text x="{x:.1}" y="{}" text-anchor="middle">{}</text>"##,
+            TOP + plot_height,
+            TOP + plot_height + 18.0,
+            x_ticks.label(tick),
+            x = x(tick),
+        )?;
+    }
+    writeln!(
+        writer,
+        r#"<rect x="{LEFT}" y="{TOP}" width="{plot_width}" height="{plot_height}" fill="none" stroke="black"/>"#
+    )?;
+    writeln!(
+        writer,
+        r#"<text x="{}" y="{}" text-anchor="middle">Generation</text>"#,
+        LEFT + plot_width / 2.0,
+        HEIGHT - 15.0
+    )?;
+    writeln!(
+        writer,
+        r#"<text transform="translate(20 {}) rotate(-90)" text-anchor="middle">Best score (median and quartiles)</text>"#,
+        TOP + plot_height / 2.0
+    )?;
+
+    for (index, curve) in curves.iter().enumerate() {
+        let colour = COLOURS[index % COLOURS.len()];
+        let path = |value: fn(&CurvePoint) -> f64| {
+            curve
+                .points
+                .iter()
+                .map(|point| format!("{:.1},{:.1}", x(point.generation as f64), y(value(point))))
+                .collect::<Vec<_>>()
+        };
+        let mut band = path(|point| point.upper_quartile);
+        band.extend(path(|point| point.lower_quartile).into_iter().rev());
+        writeln!(
+            writer,
+            r#"<polygon points="{}" fill="{colour}" fill-opacity="0.2" stroke="none"/>"#,
+            band.join(" ")
+        )?;
+        writeln!(
+            writer,
+            r#"<polyline points="{}" fill="none" stroke="{colour}" stroke-width="2"/>"#,
+            path(|point| point.median).join(" ")
+        )?;
+        let legend_y = TOP + 10.0 + 20.0 * index as f64;
+        writeln!(
+            writer,
+            r#"<line x1="{}" x2="{}" y1="{legend_y}" y2="{legend_y}" stroke="{colour}" stroke-width="2"/><text x="{}" y="{}">{}</text>"#,
+            WIDTH - RIGHT + 15.0,
+            WIDTH - RIGHT + 40.0,
+            WIDTH - RIGHT + 46.0,
+            legend_y + 4.0,
+            escape(&curve.name)
+        )?;
+    }
+    writeln!(writer, "</svg>")?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// The ticks on one of the axes of a plot.
+struct Ticks {
+    /// Evenly spaced "nice" values (steps of 1, 2, or 5 times a power of 10), of which
+    /// there are always at least two.
+    values: Vec<f64>,
+    /// How many decimal places the labels need to tell the ticks apart.
+    decimals: usize,
+}
+
+impl Ticks {
+    /// Ticks covering `low..=high`.
+    fn covering(low: f64, high: f64) -> Self {
+        let range = if high > low { high - low } else { 1.0 };
+        let rough_step = range / 5.0;
+        let magnitude = 10_f64.powf(rough_step.log10().floor());
+        let step = [1.0, 2.0, 5.0, 10.0]
+            .into_iter()
+            .map(|multiple| multiple * magnitude)
+            .find(|&step| step >= rough_step)
+            .unwrap_or(10.0 * magnitude);
+        let first = (low / step).floor() as i64;
+        let last = ((low + range) / step).ceil() as i64;
+        Self {
+            values: (first..=last.max(first + 1))
+                .map(|i| i as f64 * step)
+                .collect(),
+            // The number of places it takes to write the step, e.g., 1 for 0.2.
+            decimals: (-step.log10().floor()).max(0.0) as usize,
+        }
+    }
+
+    /// The label for `tick`, which is one of `values`. Since the values are multiples of
+    /// the step, which isn't always exact in floating point, they're rounded to the
+    /// precision of the step.
+    fn label(&self, tick: f64) -> String {
+        format!("{tick:.*}", self.decimals)
+    }
+}
+
+/// Escape the characters that mean something in SVG (XML) text.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[expect(clippy::unwrap_used, reason = ".unwrap() is reasonable in tests")]
+#[cfg(test)]
+mod tests {
+    use super::{write_csv, write_svg, ConvergenceCurve, Ticks};
+
+    fn curve() -> ConvergenceCurve {
+        let runs = vec![
+            vec![(0, 1.0), (1, 4.0), (2, 8.0)],
+            vec![(0, 2.0), (1, 5.0), (2, 9.0)],
+            vec![(0, 3.0), (1, 6.0)],
+        ];
+        ConvergenceCurve::from_runs("ts_2".to_string(), &runs)
+    }
+
+    #[test]
+    fn quartiles_per_generation() {
+        let curve = curve();
+        assert_eq!(curve.points.len(), 3);
+        let first = curve.points[0];
+        assert_eq!((first.generation, first.runs), (0, 3));
+        assert!((first.lower_quartile - 1.5).abs() < 1e-12);
+        assert!((first.median - 2.0).abs() < 1e-12);
+        assert!((first.upper_quartile - 2.5).abs() < 1e-12);
+        // Only two runs got to generation 2.
+        assert_eq!(curve.points[2].runs, 2);
+    }
+
+    #[test]
+    fn csv_has_a_row_per_generation() {
+        let mut csv = Vec::new();
+        write_csv(&[curve()], &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().count(), 4);
+        assert_eq!(csv.lines().nth(1), Some("ts_2,0,3,1.5,2,2.5"));
+    }
+
+    #[test]
+    fn svg_has_a_line_per_curve() {
+        let mut svg = Vec::new();
+        write_svg(&[curve(), curve()], "Small <3>", &mut svg).unwrap();
+        let svg = String::from_utf8(svg).unwrap();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert_eq!(svg.matches("<polyline").count(), 2);
+        assert!(svg.contains("Small &lt;3&gt;"));
+    }
+
+    #[test]
+    fn nice_ticks() {
+        assert_eq!(
+            Ticks::covering(0.0, 1000.0).values,
+            [0.0, 200.0, 400.0, 600.0, 800.0, 1000.0]
+        );
+        assert_eq!(Ticks::covering(12_580.0, 14_080.0).values[0], 12_500.0);
+        assert_eq!(Ticks::covering(5.0, 5.0).values.len(), 6);
+    }
+
+    #[test]
+    fn tick_labels() {
+        let ticks = Ticks::covering(0.0, 1000.0);
+        assert_eq!(ticks.label(ticks.values[1]), "200");
+        // Every run overloaded, so all the values are 0.
+        let ticks = Ticks::covering(0.0, 0.0);
+        let labels = ticks
+            .values
+            .iter()
+            .map(|&tick| ticks.label(tick))
+            .collect::<Vec<_>>();
+        assert_eq!(labels, ["0.0", "0.2", "0.4", "0.6", "0.8", "1.0"]);
+    }
+}
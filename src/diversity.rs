@@ -0,0 +1,138 @@
+use std::{collections::HashSet, hash::Hash};
+
+use ec_core::individual::ec::EcIndividual;
+use ec_linear::genome::bitstring::Bitstring;
+use serde::{Deserialize, Serialize};
+
+/// Measures of how diverse a population of `Bitstring` genomes is.
+///
+/// These complement `course_helpers::statistics::entropy`, which is hard to
+/// interpret on its own, with numbers that have a direct meaning in terms of
+/// the genomes and scores in the population.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Diversity {
+    /// The mean Hamming distance over all pairs of (distinct) individuals in the population,
+    /// i.e., how many items two randomly chosen solutions disagree on, on average.
+    pub mean_hamming_distance: f64,
+    /// The number of distinct genomes in the population.
+    pub unique_genomes: usize,
+    /// The number of distinct scores in the population.
+    pub distinct_fitnesses: usize,
+    /// For each locus (item), the fraction of the population that has that item
+    /// in the knapsack. This is one number per item, so it's only collected if asked for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locus_frequencies: Option<Vec<f64>>,
+}
+
+impl Diversity {
+    /// Compute the diversity measures for the given population. The per-locus bit
+    /// frequencies are only included if `include_locus_frequencies` is `true`.
+    ///
+    /// Rather than comparing every pair of genomes (which is quadratic in the
+    /// population size, and far too slow on the big problems), the mean pairwise
+    /// Hamming distance is computed exactly from the number of 1s at each locus:
+    /// a locus with `c` ones in a population of `n` contributes `c * (n - c)` to the
+    /// total distance over all `n * (n - 1) / 2` pairs. This is linear in the size of
+    /// the population, so there's no need to sample pairs for big populations.
+    #[must_use]
+    pub fn of_population<S>(
+        population: &[EcIndividual<Bitstring, S>],
+        include_locus_frequencies: bool,
+    ) -> Self
+    where
+        S: Eq + Hash,
+    {
+        let ones_counts = ones_per_locus(population);
+        let n = population.len();
+
+        let mean_hamming_distance = if n < 2 {
+            0.0
+        } else {
+            let total_distance: u128 = ones_counts
+                .iter()
+                .map(|&ones| (ones as u128) * ((n - ones) as u128))
+                .sum();
+            let num_pairs = (n as u128) * ((n - 1) as u128) / 2;
+            total_distance as f64 / num_pairs as f64
+        };
+
+        let unique_genomes = population
+            .iter()
+            .map(|individual| &individual.genome)
+            .collect::<HashSet<_>>()
+            .len();
+        let distinct_fitnesses = population
+            .iter()
+            .map(|individual| &individual.test_results)
+            .collect::<HashSet<_>>()
+            .len();
+
+        let locus_frequencies = include_locus_frequencies.then(|| {
+            ones_counts
+                .iter()
+                .map(|&ones| ones as f64 / n as f64)
+                .collect()
+        });
+
+        Self {
+            mean_hamming_distance,
+            unique_genomes,
+            distinct_fitnesses,
+            locus_frequencies,
+        }
+    }
+}
+
+/// Count, for each locus, how many genomes in the population have a 1 there.
+fn ones_per_locus<S>(population: &[EcIndividual<Bitstring, S>]) -> Vec<usize> {
+    let num_loci = population
+        .iter()
+        .map(|individual| individual.genome.iter().count())
+        .max()
+        .unwrap_or(0);
+    let mut counts = vec![0; num_loci];
+    for individual in population {
+        for (count, &bit) in counts.iter_mut().zip(individual.genome.iter()) {
+            *count += usize::from(bit);
+        }
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use ec_core::individual::ec::EcIndividual;
+    use ec_linear::genome::bitstring::Bitstring;
+
+    use super::Diversity;
+
+    fn individual(bits: [bool; 4], score: u64) -> EcIndividual<Bitstring, u64> {
+        EcIndividual::new(Bitstring::from_iter(bits), score)
+    }
+
+    #[test]
+    fn identical_population_has_no_diversity() {
+        let population = vec![individual([true, false, true, false], 3); 5];
+        let diversity = Diversity::of_population(&population, true);
+        assert!(diversity.mean_hamming_distance.abs() < f64::EPSILON);
+        assert_eq!(diversity.unique_genomes, 1);
+        assert_eq!(diversity.distinct_fitnesses, 1);
+        assert_eq!(diversity.locus_frequencies, Some(vec![1.0, 0.0, 1.0, 0.0]));
+    }
+
+    #[test]
+    fn mean_hamming_distance_matches_pairwise_comparison() {
+        let population = vec![
+            individual([true, true, false, false], 2),
+            individual([true, false, true, false], 2),
+            individual([false, false, false, true], 1),
+        ];
+        // The three pairwise distances are 2, 3, and 3.
+        let expected = (2.0 + 3.0 + 3.0) / 3.0;
+        let diversity = Diversity::of_population(&population, false);
+        assert!((diversity.mean_hamming_distance - expected).abs() < 1e-12);
+        assert_eq!(diversity.unique_genomes, 3);
+        assert_eq!(diversity.distinct_fitnesses, 2);
+        assert_eq!(diversity.locus_frequencies, None);
+    }
+}
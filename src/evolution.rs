@@ -0,0 +1,309 @@
+use std::{fmt, marker::PhantomData};
+
+use clap::ValueEnum;
+use ec_core::individual::{ec::EcIndividual, scorer::Scorer};
+use ec_linear::genome::bitstring::Bitstring;
+use rand::Rng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// How to create, recombine, and mutate genomes of type `G` in an [`Evolution`].
+pub trait Variation<G> {
+    /// Create a new random genome.
+    fn random<R: Rng + ?Sized>(&self, rng: &mut R) -> G;
+
+    /// Create a child by recombining two parents and then mutating the result.
+    fn make_child<R: Rng + ?Sized>(&self, first_parent: &G, second_parent: &G, rng: &mut R) -> G;
+}
+
+/// How a [`BitstringVariation`] recombines two parents.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Crossover {
+    /// Take each bit from either parent with equal probability.
+    #[default]
+    Uniform,
+    /// Take the bits before a random point from the first parent, and the rest from the
+    /// second.
+    OnePoint,
+    /// Take the bits between two random points from the second parent, and the rest from
+    /// the first.
+    TwoPoint,
+}
+
+impl fmt::Display for Crossover {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Uniform => "uniform",
+            Self::OnePoint => "one-point",
+            Self::TwoPoint => "two-point",
+        })
+    }
+}
+
+/// The variation we use for `Bitstring`s: crossover followed by mutation that flips each
+/// bit with probability `mutation_flips / bit_length`. By default this is uniform
+/// crossover and flips one bit on average, which are the same as `UniformXo` and
+/// `WithOneOverLength` in `main`.
+#[derive(Debug, Clone, Copy)]
+pub struct BitstringVariation {
+    bit_length: usize,
+    crossover: Crossover,
+    mutation_flips: f64,
+}
+
+impl BitstringVariation {
+    #[must_use]
+    pub const fn new(bit_length: usize) -> Self {
+        Self {
+            bit_length,
+            crossover: Crossover::Uniform,
+            mutation_flips: 1.0,
+        }
+    }
+
+    #[must_use]
+    pub const fn with_crossover(mut self, crossover: Crossover) -> Self {
+        self.crossover = crossover;
+        self
+    }
+
+    /// Flip `mutation_flips` bits on average (rather than one) when mutating a child.
+    #[must_use]
+    pub const fn with_mutation_flips(mut self, mutation_flips: f64) -> Self {
+        self.mutation_flips = mutation_flips;
+        self
+    }
+}
+
+impl Variation<Bitstring> for BitstringVariation {
+    fn random<R: Rng + ?Sized>(&self, rng: &mut R) -> Bitstring {
+        (0..self.bit_length).map(|_| rng.random_bool(0.5)).collect()
+    }
+
+    fn make_child<R: Rng + ?Sized>(
+        &self,
+        first_parent: &Bitstring,
+        second_parent: &Bitstring,
+        rng: &mut R,
+    ) -> Bitstring {
+        let mutation_rate = (self.mutation_flips / self.bit_length.max(1) as f64).clamp(0.0, 1.0);
+        // The bits in `start..end` come from the second parent (for one and two point
+        // crossover).
+        let (start, end) = match self.crossover {
+            Crossover::Uniform => (0, 0),
+            Crossover::OnePoint => (rng.random_range(0..=self.bit_length), self.bit_length),
+            Crossover::TwoPoint => {
+                let first = rng.random_range(0..=self.bit_length);
+                let second = rng.random_range(0..=self.bit_length);
+                (first.min(second), first.max(second))
+            }
+        };
+        first_parent
+            .iter()
+            .zip(second_parent.iter())
+            .enumerate()
+            // Crossover, then flip each bit with probability `mutation_rate`.
+            .map(|(index, (&first, &second))| {
+                let bit = match self.crossover {
+                    Crossover::Uniform => {
+                        if rng.random_bool(0.5) {
+                            first
+                        } else {
+                            second
+                        }
+                    }
+                    Crossover::OnePoint | Crossover::TwoPoint => {
+                        if (start..end).contains(&index) {
+                            second
+                        } else {
+                            first
+                        }
+                    }
+                };
+                bit ^ rng.random_bool(mutation_rate)
+            })
+            .collect()
+    }
+}
+
+/// A generational genetic algorithm.
+///
+/// With a [`BitstringVariation`], this does the same thing as the run we build with
+/// `course_helpers::ec_run::Run` in `main`: tournament selection, uniform crossover, and
+/// mutation that flips each bit with probability `1 / bit_length`. The difference is that
+/// here *we* own the loop and the random number generator, so a run can be stopped after
+/// any generation and picked back up again, and a run with a given seed is reproducible.
+/// It also works with genomes other than `Bitstring`s, given a suitable [`Variation`].
+pub struct Evolution<Sc, V, G> {
+    scorer: Sc,
+    variation: V,
+    population_size: usize,
+    tournament_size: usize,
+    _genome: PhantomData<fn() -> G>,
+}
+
+impl<Sc, V, G> Evolution<Sc, V, G>
+where
+    Sc: Scorer<G> + Sync,
+    Sc::Score: Ord + Clone + Send,
+    V: Variation<G>,
+    G: Send,
+{
+    /// Construct a new `Evolution`.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `tournament_size` is 0, since we can't select from an empty tournament.
+    #[must_use]
+    pub fn new(scorer: Sc, variation: V, population_size: usize, tournament_size: usize) -> Self {
+        assert!(
+            tournament_size > 0,
+            "The tournament size must be at least 1"
+        );
+        Self {
+            scorer,
+            variation,
+            population_size,
+            tournament_size,
+            _genome: PhantomData,
+        }
+    }
+
+    /// Create a population of scored, random genomes.
+    pub fn initial_population(&self, rng: &mut impl Rng) -> Vec<EcIndividual<G, Sc::Score>> {
+        let genomes = (0..self.population_size)
+            .map(|_| self.variation.random(rng))
+            .collect();
+        self.score_all(genomes)
+    }
+
+    /// Create the next generation from the given `population`.
+    ///
+    /// All the random choices are made (in order) before any of the scoring happens, so
+    /// the result only depends on the state of `rng`, even though the children are
+    /// scored in parallel.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `population` is empty.
+    pub fn next_generation(
+        &self,
+        population: &[EcIndividual<G, Sc::Score>],
+        rng: &mut impl Rng,
+    ) -> Vec<EcIndividual<G, Sc::Score>> {
+        assert!(
+            !population.is_empty(),
+            "Can't make a new generation from an empty population"
+        );
+        let children = (0..self.population_size)
+            .map(|_| {
+                let first_parent = self.tournament(population, rng);
+                let second_parent = self.tournament(population, rng);
+                self.variation.make_child(first_parent, second_parent, rng)
+            })
+            .collect();
+        self.score_all(children)
+    }
+
+    /// Choose `tournament_size` individuals at random (with replacement) and
+    /// return the genome of the best of them.
+    fn tournament<'pop>(
+        &self,
+        population: &'pop [EcIndividual<G, Sc::Score>],
+        rng: &mut impl Rng,
+    ) -> &'pop G {
+        let mut winner = &population[rng.random_range(0..population.len())];
+        for _ in 1..self.tournament_size {
+            let contender = &population[rng.random_range(0..population.len())];
+            if contender.test_results > winner.test_results {
+                winner = contender;
+            }
+        }
+        &winner.genome
+    }
+
+    fn score_all(&self, genomes: Vec<G>) -> Vec<EcIndividual<G, Sc::Score>> {
+        let scorer = &self.scorer;
+        genomes
+            .into_par_iter()
+            .map(|genome| {
+                let score = scorer.score(&genome);
+                EcIndividual::new(genome, score)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ec_linear::genome::bitstring::Bitstring;
+    use rand::SeedableRng;
+    use rand_pcg::Pcg64;
+
+    use super::{BitstringVariation, Crossover, Evolution, Variation};
+    use crate::{cliff_scorer::CliffScorer, item::Item, knapsack::Knapsack};
+
+    fn evolution() -> Evolution<CliffScorer, BitstringVariation, Bitstring> {
+        let knapsack = Knapsack::new(
+            vec![
+                Item::new(1, 5, 8),
+                Item::new(2, 9, 6),
+                Item::new(3, 2, 7),
+                Item::new(4, 4, 3),
+            ],
+            15,
+        );
+        Evolution::new(
+            CliffScorer::new(knapsack),
+            BitstringVariation::new(4),
+            20,
+            2,
+        )
+    }
+
+    #[test]
+    fn same_seed_same_populations() {
+        let evolution = evolution();
+        let run = |seed| {
+            let mut rng = Pcg64::seed_from_u64(seed);
+            let mut population = evolution.initial_population(&mut rng);
+            for _ in 0..10 {
+                population = evolution.next_generation(&population, &mut rng);
+            }
+            population
+        };
+        assert_eq!(run(17), run(17));
+    }
+
+    #[test]
+    fn populations_have_the_right_shape() {
+        let evolution = evolution();
+        let mut rng = Pcg64::seed_from_u64(0);
+        let population = evolution.initial_population(&mut rng);
+        let children = evolution.next_generation(&population, &mut rng);
+        assert_eq!(children.len(), 20);
+        assert!(children
+            .iter()
+            .all(|child| child.genome.iter().count() == 4));
+    }
+
+    #[test]
+    fn point_crossovers_keep_runs_of_bits() {
+        let first = Bitstring::from_iter([false; 20]);
+        let second = Bitstring::from_iter([true; 20]);
+        let mut rng = Pcg64::seed_from_u64(3);
+        for (crossover, max_switches) in [(Crossover::OnePoint, 1), (Crossover::TwoPoint, 2)] {
+            // Without mutation, the child switches parents at most once or twice.
+            let variation = BitstringVariation::new(20)
+                .with_crossover(crossover)
+                .with_mutation_flips(0.0);
+            for _ in 0..50 {
+                let child = variation.make_child(&first, &second, &mut rng);
+                let bits = child.iter().copied().collect::<Vec<_>>();
+                let switches = bits.windows(2).filter(|pair| pair[0] != pair[1]).count();
+                assert!(switches <= max_switches, "{crossover}: {bits:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,475 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{
+    checkpoint::RunSettings,
+    cli::ProblemKind,
+    evolution::{BitstringVariation, Crossover},
+    formats::InstanceFormat,
+};
+
+/// The name of the file that the resolved configuration (see [`ResolvedConfig`]) is
+/// written to, in the same directory as the results of its runs.
+pub const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// The biggest seed a configuration can have, since TOML integers are signed.
+pub const MAX_SEED: u64 = i64::MAX.unsigned_abs();
+
+/// A random seed that can be written to a configuration file.
+pub fn random_seed(rng: &mut impl Rng) -> u64 {
+    rng.random_range(0..=MAX_SEED)
+}
+
+/// An experiment, as described in a TOML file like
+///
+/// ```toml
+/// output_dir = "results"
+/// replicates = 30
+/// seed = 4553
+///
+/// [[instances]]
+/// path = "knapsacks/SmallProblem3.txt"
+///
+/// [[instances]]
+/// name = "Sm4"
+/// path = "knapsacks/SmallProblem4.txt"
+///
+/// [operators]
+/// crossover = ["uniform", "two-point"]
+///
+/// [parameters]
+/// tournament_size = [2, 8]
+/// max_generations = 500
+/// ```
+///
+/// Each operator and parameter can be a single value or a list of them, and the
+/// experiment runs every combination of them (the grid) on every instance. Any that are
+/// left out have the same defaults as the `replicates` subcommand.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Experiment {
+    /// The results of each configuration on each instance go in
+    /// `<output_dir>/<instance>/<configuration>/`.
+    pub output_dir: PathBuf,
+    /// How many runs to do for each configuration on each instance.
+    #[serde(default = "default_replicates")]
+    pub replicates: usize,
+    /// Each configuration gets its own seed, drawn from a generator seeded with this. If
+    /// this isn't given, a random seed is chosen.
+    pub seed: Option<u64>,
+    pub instances: Vec<InstanceSpec>,
+    #[serde(default)]
+    pub operators: Operators,
+    #[serde(default)]
+    pub parameters: Parameters,
+}
+
+const fn default_replicates() -> usize {
+    30
+}
+
+/// A 0/1 knapsack instance to run on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InstanceSpec {
+    /// The name of the instance's output directory. If this isn't given, it's the name of
+    /// the instance file without its extension.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub path: PathBuf,
+    /// If this isn't given, it's worked out from the file's extension or contents.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<InstanceFormat>,
+    /// Which instance to run on, counting from 0, for files that contain several.
+    #[serde(default)]
+    pub instance: usize,
+}
+
+impl InstanceSpec {
+    #[must_use]
+    pub fn name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| {
+            self.path
+                .file_stem()
+                .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned())
+        })
+    }
+}
+
+/// The choices of genetic operators to try.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Operators {
+    #[serde(default = "default_crossover", deserialize_with = "one_or_many")]
+    pub crossover: Vec<Crossover>,
+}
+
+fn default_crossover() -> Vec<Crossover> {
+    vec![Crossover::Uniform]
+}
+
+impl Default for Operators {
+    fn default() -> Self {
+        Self {
+            crossover: default_crossover(),
+        }
+    }
+}
+
+/// The values of the genetic algorithm's parameters to try.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Parameters {
+    #[serde(default = "default_tournament_size", deserialize_with = "one_or_many")]
+    pub tournament_size: Vec<usize>,
+    #[serde(default = "default_population_size", deserialize_with = "one_or_many")]
+    pub population_size: Vec<usize>,
+    #[serde(default = "default_max_generations", deserialize_with = "one_or_many")]
+    pub max_generations: Vec<usize>,
+    /// The average number of bits mutation flips in each child.
+    #[serde(default = "default_mutation_flips", deserialize_with = "one_or_many")]
+    pub mutation_flips: Vec<f64>,
+}
+
+fn default_tournament_size() -> Vec<usize> {
+    vec![8]
+}
+
+fn default_population_size() -> Vec<usize> {
+    vec![1_000]
+}
+
+fn default_max_generations() -> Vec<usize> {
+    vec![1_000]
+}
+
+fn default_mutation_flips() -> Vec<f64> {
+    vec![1.0]
+}
+
+impl Default for Parameters {
+    fn default() -> Self {
+        Self {
+            tournament_size: default_tournament_size(),
+            population_size: default_population_size(),
+            max_generations: default_max_generations(),
+            mutation_flips: default_mutation_flips(),
+        }
+    }
+}
+
+/// Read either a single value or a list of them.
+fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany<T> {
+        One(T),
+        Many(Vec<T>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
+impl Experiment {
+    /// Read an experiment from the TOML file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// This fails if we can't read the file, or it isn't a valid experiment.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read the experiment file {path:?}"))?;
+        toml::from_str(&text)
+            .with_context(|| format!("Failed to parse the experiment file {path:?}"))
+    }
+
+    /// Work out every configuration to run on every instance, in order, with the
+    /// seed of each drawn from a generator seeded with `seed`.
+    ///
+    /// Each configuration is named after its tournament size (like `ts_8`, as in
+    /// `Outputs/`), followed by each of the other operators and parameters that have
+    /// more than one value in the grid (like `ts_8_two-point_pop_500`).
+    ///
+    /// # Errors
+    ///
+    /// This fails if there aren't any instances, one of the lists of operators or
+    /// parameters is empty, a value doesn't make sense (like a tournament size of 0), or
+    /// two configurations would have the same output directory.
+    pub fn resolve(&self, seed: u64) -> anyhow::Result<Vec<ResolvedConfig>> {
+        let Operators { crossover } = &self.operators;
+        let Parameters {
+            tournament_size,
+            population_size,
+            max_generations,
+            mutation_flips,
+        } = &self.parameters;
+        anyhow::ensure!(
+            !self.instances.is_empty(),
+            "The experiment needs at least one instance"
+        );
+        anyhow::ensure!(
+            self.replicates > 0,
+            "The experiment needs at least one replicate"
+        );
+        for (name, count) in [
+            ("crossover", crossover.len()),
+            ("tournament_size", tournament_size.len()),
+            ("population_size", population_size.len()),
+            ("max_generations", max_generations.len()),
+            ("mutation_flips", mutation_flips.len()),
+        ] {
+            anyhow::ensure!(count > 0, "There needs to be at least one `{name}`");
+        }
+        anyhow::ensure!(
+            tournament_size.iter().all(|&size| size > 0),
+            "The tournament size must be at least 1"
+        );
+        anyhow::ensure!(
+            population_size.iter().all(|&size| size > 0),
+            "The population size must be at least 1"
+        );
+        anyhow::ensure!(
+            mutation_flips
+                .iter()
+                .all(|flips| flips.is_finite() && *flips >= 0.0),
+            "The number of mutation flips must be a number that's at least 0"
+        );
+
+        let mut rng = Pcg64::seed_from_u64(seed);
+        let mut configs = Vec::new();
+        let mut output_dirs = HashSet::new();
+        for instance in &self.instances {
+            let instance_name = instance.name();
+            anyhow::ensure!(
+                !instance_name.is_empty(),
+                "The instance {:?} needs a name",
+                instance.path
+            );
+            for &crossover_choice in crossover {
+                for &tournament in tournament_size {
+                    for &population in population_size {
+                        for &generations in max_generations {
+                            for &flips in mutation_flips {
+                                let mut name = format!("ts_{tournament}");
+                                if crossover.len() > 1 {
+                                    name += &format!("_{crossover_choice}");
+                                }
+                                if population_size.len() > 1 {
+                                    name += &format!("_pop_{population}");
+                                }
+                                if max_generations.len() > 1 {
+                                    name += &format!("_gens_{generations}");
+                                }
+                                if mutation_flips.len() > 1 {
+                                    name += &format!("_flips_{flips}");
+                                }
+                                let output_dir = self.output_dir.join(&instance_name).join(&name);
+                                anyhow::ensure!(
+                                    output_dirs.insert(output_dir.clone()),
+                                    "Two configurations would both write to {output_dir:?}"
+                                );
+                                configs.push(ResolvedConfig {
+                                    configuration: name,
+                                    output_dir,
+                                    replicates: self.replicates,
+                                    seed: random_seed(&mut rng),
+                                    crossover: crossover_choice,
+                                    tournament_size: tournament,
+                                    population_size: population,
+                                    max_generations: generations,
+                                    mutation_flips: flips,
+                                    instance: InstanceSpec {
+                                        name: Some(instance_name.clone()),
+                                        ..instance.clone()
+                                    },
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(configs)
+    }
+}
+
+/// Everything about one configuration on one instance that's needed to repeat its runs.
+/// This is written to [`CONFIG_FILE_NAME`] in the directory with the results of the
+/// runs, so they describe themselves.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ResolvedConfig {
+    pub configuration: String,
+    /// The directory the runs' results are written to.
+    pub output_dir: PathBuf,
+    pub replicates: usize,
+    /// What the seeds of the runs are drawn from (see
+    /// [`crate::replicates::Replicate::plan`]). This is at most [`MAX_SEED`].
+    pub seed: u64,
+    pub crossover: Crossover,
+    pub tournament_size: usize,
+    pub population_size: usize,
+    pub max_generations: usize,
+    pub mutation_flips: f64,
+    // This has to come last, since it's a table in TOML.
+    pub instance: InstanceSpec,
+}
+
+impl ResolvedConfig {
+    /// Write this configuration to [`CONFIG_FILE_NAME`] in its output directory, creating
+    /// the directory if it doesn't exist yet.
+    ///
+    /// # Errors
+    ///
+    /// This fails if we can't create the directory or write the file.
+    pub fn save(&self) -> anyhow::Result<()> {
+        fs::create_dir_all(&self.output_dir)
+            .with_context(|| format!("Failed to create the directory {:?}", self.output_dir))?;
+        let path = self.output_dir.join(CONFIG_FILE_NAME);
+        fs::write(&path, toml::to_string(self)?)
+            .with_context(|| format!("Failed to write the configuration to {path:?}"))
+    }
+
+    /// Read the configuration saved in `directory`.
+    ///
+    /// # Errors
+    ///
+    /// This fails if there isn't one, or it can't be parsed.
+    pub fn load(directory: &Path) -> anyhow::Result<Self> {
+        let path = directory.join(CONFIG_FILE_NAME);
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read the configuration {path:?}"))?;
+        toml::from_str(&text).with_context(|| format!("Failed to parse the configuration {path:?}"))
+    }
+
+    #[must_use]
+    pub fn settings(&self) -> RunSettings {
+        RunSettings {
+            knapsack: self.instance.path.clone(),
+            problem: ProblemKind::Knapsack,
+            instance: self.instance.instance,
+            format: self.instance.format,
+            reduce: false,
+            tournament_size: self.tournament_size,
+            population_size: self.population_size,
+            max_generations: self.max_generations,
+        }
+    }
+
+    #[must_use]
+    pub const fn variation(&self, bit_length: usize) -> BitstringVariation {
+        BitstringVariation::new(bit_length)
+            .with_crossover(self.crossover)
+            .with_mutation_flips(self.mutation_flips)
+    }
+}
+
+#[expect(clippy::unwrap_used, reason = ".unwrap() is reasonable in tests")]
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{Experiment, ResolvedConfig};
+    use crate::evolution::Crossover;
+
+    const EXPERIMENT: &str = r#"
+output_dir = "results"
+replicates = 5
+
+[[instances]]
+path = "knapsacks/SmallProblem3.txt"
+
+[[instances]]
+name = "Sm4"
+path = "knapsacks/SmallProblem4.txt"
+format = "jooken"
+
+[operators]
+crossover = ["uniform", "two-point"]
+
+[parameters]
+tournament_size = [2, 8]
+max_generations = 500
+"#;
+
+    #[test]
+    fn the_grid_covers_every_combination() {
+        let experiment = toml::from_str::<Experiment>(EXPERIMENT).unwrap();
+        let configs = experiment.resolve(1).unwrap();
+        assert_eq!(configs.len(), 8);
+        let first = &configs[0];
+        assert_eq!(first.configuration, "ts_2_uniform");
+        assert_eq!(
+            first.output_dir,
+            Path::new("results/SmallProblem3/ts_2_uniform")
+        );
+        assert_eq!(first.max_generations, 500);
+        assert_eq!(first.population_size, 1_000);
+        assert_eq!(first.replicates, 5);
+        assert_eq!(configs[3].configuration, "ts_8_two-point");
+        assert_eq!(configs[3].crossover, Crossover::TwoPoint);
+        assert_eq!(
+            configs[7].output_dir,
+            Path::new("results/Sm4/ts_8_two-point")
+        );
+        assert_ne!(configs[0].seed, configs[1].seed);
+        assert_eq!(experiment.resolve(1).unwrap(), configs);
+    }
+
+    #[test]
+    fn the_example_experiment_is_valid() {
+        let experiment = Experiment::load(Path::new("experiment.toml")).unwrap();
+        let configs = experiment.resolve(experiment.seed.unwrap()).unwrap();
+        let names = configs
+            .iter()
+            .map(|config| config.output_dir.to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                "results/Sm3/ts_2",
+                "results/Sm3/ts_8",
+                "results/Sm4/ts_2",
+                "results/Sm4/ts_8"
+            ]
+        );
+    }
+
+    #[test]
+    fn resolved_configs_round_trip() {
+        let experiment = toml::from_str::<Experiment>(EXPERIMENT).unwrap();
+        for config in experiment.resolve(2).unwrap() {
+            let text = toml::to_string(&config).unwrap();
+            assert_eq!(toml::from_str::<ResolvedConfig>(&text).unwrap(), config);
+        }
+    }
+
+    #[test]
+    fn bad_experiments_are_rejected() {
+        assert!(
+            toml::from_str::<Experiment>("output_dir = \"x\"\ninstances = []\nrepeats = 3")
+                .is_err()
+        );
+        let empty = toml::from_str::<Experiment>("output_dir = \"x\"\ninstances = []").unwrap();
+        assert!(empty.resolve(0).is_err());
+        let duplicate = toml::from_str::<Experiment>(
+            "output_dir = \"x\"\n[[instances]]\npath = \"a.txt\"\n[parameters]\ntournament_size = [2, 2]",
+        )
+        .unwrap();
+        assert!(duplicate.resolve(0).is_err());
+    }
+}
@@ -0,0 +1,563 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::Context;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    instance_lines::{read_instance_file, InstanceLines, ItemIds},
+    item::Item,
+    knapsack::Knapsack,
+    multidimensional::MultidimensionalKnapsack,
+};
+
+/// The file formats we can read (and write) 0/1 knapsack instances in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum InstanceFormat {
+    /// The number of items, then an `id value weight` line for each item, then the
+    /// capacity, as in the Jooken instances (see `Knapsack::from_file_path`).
+    Jooken,
+    /// Pisinger's generated instances: a name line, then `n`, `c` (capacity), `z` (the
+    /// optimal value), and `time` lines, then an `id,value,weight,x` line for each item,
+    /// and `-----` at the end. A file can hold several instances.
+    Pisinger,
+    /// The OR-Library `mknap` format, with a single dimension. A file can hold several
+    /// instances.
+    OrLibrary,
+    /// JSON with the `capacity`, the `items` (each with an `id`, `value`, and `weight`),
+    /// and (optionally) the `best_known` value.
+    Json,
+    /// TOML with the same fields as the JSON format.
+    Toml,
+}
+
+impl InstanceFormat {
+    /// The format that goes with the extension of `path`, if it's one we recognize.
+    /// Pisinger distributes his instances in `.csv` files.
+    #[must_use]
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "json" => Some(Self::Json),
+            "toml" => Some(Self::Toml),
+            "csv" => Some(Self::Pisinger),
+            _ => None,
+        }
+    }
+
+    /// Work out the format of an instance at `path` from its contents.
+    ///
+    /// JSON starts with `{`, TOML has `key = value` lines or `[[tables]]`, and Pisinger's
+    /// instances start with a name. That leaves the Jooken and OR-Library formats, which
+    /// are both just whitespace separated numbers. A Jooken file has the number of items
+    /// on a line by itself, and then the first item's id, which is 0 or 1 (or there are no
+    /// items); `.txt` files that start with a number on a line by itself are also taken to
+    /// be in the Jooken format, since that's what all of ours are. That way, mistakes in
+    /// them are reported as such, rather than as not being an OR-Library file.
+    ///
+    /// An OR-Library file has the number of instances on a line by itself, and then the
+    /// number of items in the first instance, so it can look like a Jooken file if that
+    /// instance has 0 or 1 items. It's only taken to be an OR-Library file then if it
+    /// isn't laid out like a Jooken file, and is a valid OR-Library file.
+    #[must_use]
+    pub fn detect(path: &Path, text: &str) -> Self {
+        let lines = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect::<Vec<_>>();
+        let Some(first) = lines.first() else {
+            return Self::Jooken;
+        };
+        if first.starts_with('{') {
+            return Self::Json;
+        }
+        if first.starts_with('[') || first.contains('=') {
+            return Self::Toml;
+        }
+        let mut tokens = first.split_ascii_whitespace();
+        let Some(Ok(first_number)) = tokens.next().map(str::parse::<u64>) else {
+            return Self::Pisinger;
+        };
+        let second_line_starts_with_id = lines
+            .get(1)
+            .and_then(|line| line.split_ascii_whitespace().next())
+            .is_some_and(|token| token == "0" || token == "1");
+        let is_txt = path.extension().is_some_and(|extension| extension == "txt");
+        let has_jooken_header =
+            tokens.next().is_none() && (second_line_starts_with_id || first_number == 0 || is_txt);
+        if !has_jooken_header {
+            return Self::OrLibrary;
+        }
+        // A Jooken file has the number of items, a line for each item, and then the
+        // capacity on its own.
+        let has_jooken_layout = lines.len() as u64 == first_number.saturating_add(2)
+            && lines
+                .last()
+                .is_some_and(|line| line.split_ascii_whitespace().count() == 1);
+        if !has_jooken_layout && MultidimensionalKnapsack::parse_mknap(text).is_ok() {
+            Self::OrLibrary
+        } else {
+            Self::Jooken
+        }
+    }
+}
+
+/// How instances are stored in the JSON and TOML formats.
+#[derive(Debug, Serialize, Deserialize)]
+struct InstanceRecord {
+    capacity: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    best_known: Option<u64>,
+    items: Vec<Item>,
+}
+
+/// Parse all the instances in `text`, which came from `source` (used in error messages),
+/// in the given format.
+///
+/// # Errors
+///
+/// This fails if `text` isn't in the given format, or any of the instances have items
+/// whose ids aren't unique and sequential (see [`ItemIds`]) or whose total value or
+/// weight is too big (see [`Knapsack::try_new`]).
+pub fn parse_knapsacks(
+    text: &str,
+    source: &str,
+    format: InstanceFormat,
+) -> anyhow::Result<Vec<Knapsack>> {
+    match format {
+        InstanceFormat::Jooken => Ok(vec![Knapsack::parse(text, source)?]),
+        InstanceFormat::Pisinger => parse_pisinger(text, source),
+        InstanceFormat::OrLibrary => MultidimensionalKnapsack::parse_mknap(text)
+            .and_then(|instances| {
+                instances
+                    .into_iter()
+                    .map(MultidimensionalKnapsack::into_knapsack)
+                    .collect()
+            })
+            .with_context(|| format!("Failed to parse {source} as an OR-Library file")),
+        InstanceFormat::Json | InstanceFormat::Toml => {
+            let record: InstanceRecord = if format == InstanceFormat::Json {
+                serde_json::from_str(text)
+                    .with_context(|| format!("Failed to parse {source} as JSON"))?
+            } else {
+                toml::from_str(text).with_context(|| format!("Failed to parse {source} as TOML"))?
+            };
+            let mut ids = ItemIds::default();
+            let knapsack = record
+                .items
+                .iter()
+                .try_for_each(|item| ids.check(item.id()))
+                .and_then(|()| Knapsack::try_new(record.items, record.capacity))
+                .with_context(|| format!("The knapsack in {source} can't be used"))?;
+            Ok(vec![knapsack.with_best_known(record.best_known)])
+        }
+    }
+}
+
+/// Parse the instances in Pisinger's format (see [`InstanceFormat::Pisinger`]), e.g.:
+///
+/// ```text
+/// knapPI_1_3_10_1
+/// n 3
+/// c 10
+/// z 12
+/// time 0.00
+/// 1,3,8,1
+/// 2,2,8,0
+/// 3,9,1,1
+/// -----
+/// ```
+///
+/// The last field of each item says whether it's in the optimal solution; we don't
+/// use it, and it can be left out.
+fn parse_pisinger(text: &str, source: &str) -> anyhow::Result<Vec<Knapsack>> {
+    /// Parse a `key number` header line.
+    fn header(lines: &mut InstanceLines, key: &str) -> anyhow::Result<u64> {
+        let (line_number, line) = lines.next_line(&format!("the '{key}' line"))?;
+        let value = line
+            .strip_prefix(key)
+            .filter(|rest| rest.starts_with(char::is_whitespace))
+            .ok_or_else(|| {
+                lines.error_at(
+                    line_number,
+                    format!("expected a '{key}' line but got '{line}'"),
+                )
+            })?;
+        value.trim().parse().with_context(|| {
+            lines.error_at(
+                line_number,
+                format!("failed to parse the '{key}' line '{line}'"),
+            )
+        })
+    }
+
+    let mut lines = InstanceLines::new(text, source);
+    let mut instances = Vec::new();
+    // Every instance starts with a name, so we're done when there isn't one.
+    while let Ok((_, name)) = lines.next_line("the name of an instance") {
+        let num_items = usize::try_from(header(&mut lines, "n")?)?;
+        let capacity = header(&mut lines, "c")?;
+        let optimum = header(&mut lines, "z")?;
+        let (line_number, line) = lines.next_line("the 'time' line")?;
+        if !line.starts_with("time") {
+            return Err(lines.error_at(
+                line_number,
+                format!("expected a 'time' line but got '{line}'"),
+            ));
+        }
+
+        lines.restart_ids();
+        let mut items = Vec::new();
+        for n in 1..=num_items {
+            let what = format!("item {n} of {num_items} in {name}");
+            let (line_number, line) = lines.next_line(&what)?;
+            let fields = line
+                .split(',')
+                .map(|field| field.trim().parse::<u64>())
+                .collect::<Result<Vec<_>, _>>()
+                .with_context(|| {
+                    lines.error_at(line_number, format!("failed to parse {what} from '{line}'"))
+                })?;
+            let (&[id, value, weight] | &[id, value, weight, _]) = fields.as_slice() else {
+                return Err(lines.error_at(
+                    line_number,
+                    format!("{what} should be 'id,value,weight,x' but is '{line}'; is the 'n' line right?"),
+                ));
+            };
+            lines.check_item_id(line_number, id)?;
+            items.push(Item::new(id, value, weight));
+        }
+
+        let (line_number, line) = lines.next_line(&format!("the ----- at the end of {name}"))?;
+        if !line.starts_with("---") {
+            return Err(lines.error_at(
+                line_number,
+                format!(
+                    "expected ----- at the end of {name} but got '{line}'; is the 'n' line right?"
+                ),
+            ));
+        }
+        let knapsack = Knapsack::try_new(items, capacity)
+            .with_context(|| format!("The knapsack {name} in {source} can't be used"))?;
+        instances.push(knapsack.with_best_known((optimum > 0).then_some(optimum)));
+    }
+    anyhow::ensure!(
+        !instances.is_empty(),
+        "{source}: there are no instances in the file"
+    );
+    Ok(instances)
+}
+
+/// Read a 0/1 knapsack instance from `path` (`-` for standard input). If `format` isn't
+/// given, it's worked out from the extension of `path`, or failing that from the contents
+/// of the file. For files with several instances in them, `index` says which one to read
+/// (counting from 0).
+///
+/// # Errors
+///
+/// This can fail if:
+///    - We fail to read the file,
+///    - The file contents have the wrong format, or
+///    - There is no instance with the given `index` in the file
+pub fn read_knapsack(
+    path: &Path,
+    format: Option<InstanceFormat>,
+    index: usize,
+) -> anyhow::Result<Knapsack> {
+    let (text, source) = read_instance_file(path)?;
+    let format = format
+        .or_else(|| InstanceFormat::from_extension(path))
+        .unwrap_or_else(|| InstanceFormat::detect(path, &text));
+    let mut instances = parse_knapsacks(&text, &source, format)?;
+    let num_instances = instances.len();
+    anyhow::ensure!(
+        index < num_instances,
+        "Asked for instance {index} from {source}, but it only has {num_instances} instances (numbered from 0)"
+    );
+    Ok(instances.swap_remove(index))
+}
+
+/// Write `knapsack` in the given format. `name` is only used by the Pisinger format,
+/// where every instance has a name.
+///
+/// The Jooken format doesn't have a place for the best known value, so it's written
+/// as a comment. The Pisinger format has a place for the optimal solution, but we don't
+/// know it, so every item is marked as not being in it.
+///
+/// # Errors
+///
+/// This fails if we can't write to `writer`, or (for TOML) a number is too big for TOML,
+/// which only has 64-bit _signed_ integers.
+pub fn write_knapsack(
+    knapsack: &Knapsack,
+    format: InstanceFormat,
+    name: &str,
+    mut writer: impl Write,
+) -> anyhow::Result<()> {
+    let items = knapsack.items();
+    let best_known = knapsack.best_known();
+    match format {
+        InstanceFormat::Jooken => {
+            if let Some(best_known) = best_known {
+                writeln!(writer, "# Best known value: {best_known}")?;
+            }
+            writeln!(writer, "{}", items.len())?;
+            for item in items {
+                writeln!(writer, "{} {} {}", item.id(), item.value(), item.weight())?;
+            }
+            writeln!(writer, "{}", knapsack.capacity())?;
+        }
+        InstanceFormat::Pisinger => {
+            writeln!(writer, "{name}")?;
+            writeln!(writer, "n {}", items.len())?;
+            writeln!(writer, "c {}", knapsack.capacity())?;
+            writeln!(writer, "z {}", best_known.unwrap_or(0))?;
+            writeln!(writer, "time 0.00")?;
+            for item in items {
+                writeln!(writer, "{},{},{},0", item.id(), item.value(), item.weight())?;
+            }
+            writeln!(writer, "-----")?;
+            writeln!(writer)?;
+        }
+        InstanceFormat::OrLibrary => {
+            let join = |numbers: &mut dyn Iterator<Item = u64>| {
+                numbers.map(|n| n.to_string()).collect::<Vec<_>>().join(" ")
+            };
+            writeln!(writer, "1")?;
+            writeln!(writer, "{} 1 {}", items.len(), best_known.unwrap_or(0))?;
+            writeln!(writer, "{}", join(&mut items.iter().map(Item::value)))?;
+            writeln!(writer, "{}", join(&mut items.iter().map(Item::weight)))?;
+            writeln!(writer, "{}", knapsack.capacity())?;
+        }
+        InstanceFormat::Json | InstanceFormat::Toml => {
+            let record = InstanceRecord {
+                capacity: knapsack.capacity(),
+                best_known,
+                items: items.to_vec(),
+            };
+            if format == InstanceFormat::Json {
+                serde_json::to_writer_pretty(&mut writer, &record)?;
+                writeln!(writer)?;
+            } else {
+                write!(writer, "{}", toml::to_string(&record)?)?;
+            }
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Convert the instance in `input` (see [`read_knapsack`]) to another format, writing it
+/// to `output` (`-` for standard output). If `to` isn't given, the format is worked out
+/// from the extension of `output`, and is the Jooken format if that doesn't help.
+///
+/// # Errors
+///
+/// This fails if we can't read or parse the input, or can't write the output.
+pub fn convert(
+    input: &Path,
+    from: Option<InstanceFormat>,
+    index: usize,
+    output: &Path,
+    to: Option<InstanceFormat>,
+) -> anyhow::Result<()> {
+    let knapsack = read_knapsack(input, from, index)?;
+    let name = input
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .filter(|&stem| stem != "-")
+        .unwrap_or("knapsack");
+    save_knapsack(&knapsack, name, output, to)
+}
+
+/// Write `knapsack` to `output`, where `-` means standard output, in the given format.
+/// If there's no format we work it out from the output's extension, and use the Jooken
+/// format if that doesn't help.
+///
+/// # Errors
+///
+/// This fails if we can't create or write to the output.
+pub fn save_knapsack(
+    knapsack: &Knapsack,
+    name: &str,
+    output: &Path,
+    format: Option<InstanceFormat>,
+) -> anyhow::Result<()> {
+    let format = format
+        .or_else(|| InstanceFormat::from_extension(output))
+        .unwrap_or(InstanceFormat::Jooken);
+    write_knapsack(knapsack, format, name, create_output(output)?)
+        .with_context(|| format!("Failed to write the instance to {output:?}"))
+}
+
+/// Write `knapsack` to `output` (where `-` means standard output) in the Jooken format,
+/// starting with a comment for each line of `header`, e.g., to say where the instance
+/// came from. Comments are skipped when the instance is read back in.
+///
+/// # Errors
+///
+/// This fails if we can't create or write to the output.
+pub fn save_knapsack_with_header(
+    knapsack: &Knapsack,
+    header: &[String],
+    output: &Path,
+) -> anyhow::Result<()> {
+    let context = || format!("Failed to write the instance to {output:?}");
+    let mut writer = create_output(output)?;
+    for line in header {
+        writeln!(writer, "# {line}").with_context(context)?;
+    }
+    write_knapsack(knapsack, InstanceFormat::Jooken, "", writer).with_context(context)
+}
+
+/// Open `output` for writing, where `-` means standard output.
+fn create_output(output: &Path) -> anyhow::Result<Box<dyn Write>> {
+    if output == Path::new("-") {
+        Ok(Box::new(io::stdout().lock()))
+    } else {
+        let file = File::create(output)
+            .with_context(|| format!("Failed to create the file {output:?}"))?;
+        Ok(Box::new(BufWriter::new(file)))
+    }
+}
+
+#[expect(clippy::unwrap_used, reason = ".unwrap() is reasonable in tests")]
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use test_case::test_case;
+
+    use super::{parse_knapsacks, write_knapsack, InstanceFormat};
+    use crate::{item::Item, knapsack::Knapsack};
+
+    const PISINGER: &str = "\
+knapPI_1_3_10_1
+n 3
+c 10
+z 12
+time 0.00
+1,3,8,0
+2,2,8,0
+3,9,1,1
+-----
+
+knapPI_1_3_10_2
+n 2
+c 5
+z 0
+time 0.00
+1,4,5,1
+2,1,6,0
+-----
+";
+
+    #[test]
+    fn parse_pisinger() {
+        let instances = parse_knapsacks(PISINGER, "test", InstanceFormat::Pisinger).unwrap();
+        assert_eq!(instances.len(), 2);
+        assert_eq!(instances[0].items()[2], Item::new(3, 9, 1));
+        assert_eq!(instances[0].capacity(), 10);
+        assert_eq!(instances[0].best_known(), Some(12));
+        assert_eq!(instances[1].num_items(), 2);
+        assert_eq!(instances[1].best_known(), None);
+    }
+
+    #[test_case("knapPI\nn 2\nc 5\nz 0\ntime 0\n1,4,5\n-----\n"; "too few items")]
+    #[test_case("knapPI\nn 1\nc 5\ntime 0\n1,4,5\n-----\n"; "missing optimum")]
+    #[test_case("knapPI\nn 1\nc 5\nz 0\ntime 0\n1,4,5\n"; "missing end")]
+    #[test_case("knapPI\nn 18446744073709551615\nc 5\nz 0\ntime 0\n1,4,5\n-----\n"; "huge item count")]
+    fn parse_pisinger_invalid(text: &str) {
+        assert!(parse_knapsacks(text, "test", InstanceFormat::Pisinger).is_err());
+    }
+
+    #[test_case(InstanceFormat::Jooken)]
+    #[test_case(InstanceFormat::Pisinger)]
+    #[test_case(InstanceFormat::OrLibrary)]
+    #[test_case(InstanceFormat::Json)]
+    #[test_case(InstanceFormat::Toml)]
+    fn write_then_read(format: InstanceFormat) {
+        let knapsack = Knapsack::new(
+            vec![Item::new(1, 5, 8), Item::new(2, 9, 6), Item::new(3, 2, 7)],
+            15,
+        )
+        .with_best_known(Some(11));
+        let mut written = Vec::new();
+        write_knapsack(&knapsack, format, "tiny", &mut written).unwrap();
+        let text = String::from_utf8(written).unwrap();
+        assert_eq!(InstanceFormat::detect(Path::new("-"), &text), format);
+
+        let read = parse_knapsacks(&text, "test", format).unwrap();
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].items(), knapsack.items());
+        assert_eq!(read[0].capacity(), knapsack.capacity());
+        // The Jooken format only has the best known value in a comment.
+        if format != InstanceFormat::Jooken {
+            assert_eq!(read[0].best_known(), Some(11));
+        }
+    }
+
+    #[test_case(r#"{"capacity":5,"items":[{"id":1,"value":1,"weight":1},{"id":1,"value":2,"weight":2}]}"#; "repeated id")]
+    #[test_case(r#"{"capacity":5,"items":[{"id":0,"value":1,"weight":1},{"id":2,"value":2,"weight":2}]}"#; "skipped id")]
+    #[test_case(r#"{"capacity":5,"items":[{"id":7,"value":1,"weight":1}]}"#; "first id")]
+    fn json_ids_are_checked(text: &str) {
+        assert!(parse_knapsacks(text, "test", InstanceFormat::Json).is_err());
+    }
+
+    #[test]
+    fn detect_from_content() {
+        let detect = |text| InstanceFormat::detect(Path::new("-"), text);
+        let jooken = "3\n1 3 8\n2 2 8\n3 9 1\n10\n";
+        assert_eq!(detect(jooken), InstanceFormat::Jooken);
+        let mknap = "1\n3 1 0\n3 2 9\n8 8 1\n10\n";
+        assert_eq!(detect(mknap), InstanceFormat::OrLibrary);
+        assert_eq!(detect(PISINGER), InstanceFormat::Pisinger);
+        // OR-Library files whose first instance has 0 or 1 items.
+        let one_item = "2\n1 1 0\n5\n3\n10\n2 1 0\n4 6\n1 2\n5\n";
+        assert_eq!(detect(one_item), InstanceFormat::OrLibrary);
+        let no_items = "1\n0 2 0\n10 20\n";
+        assert_eq!(detect(no_items), InstanceFormat::OrLibrary);
+        // An instance without any items.
+        assert_eq!(detect("0\n10\n"), InstanceFormat::Jooken);
+    }
+
+    // Broken Jooken files should still be read as Jooken files, so the errors say what's
+    // wrong with them.
+    #[test_case("3\n1 3 8\n2 2 8\n10\n"; "too few items")]
+    #[test_case("3\n1 3 8\n2 2 8\n3 9 1\n"; "missing capacity")]
+    #[test_case("3\n1 3 8\n2 2 8\n3 9 1\n10\n5\n"; "trailing content")]
+    fn detect_broken_jooken(text: &str) {
+        assert_eq!(
+            InstanceFormat::detect(Path::new("-"), text),
+            InstanceFormat::Jooken
+        );
+        let error = parse_knapsacks(text, "test", InstanceFormat::Jooken).unwrap_err();
+        assert!(error.to_string().starts_with("test:"), "{error}");
+    }
+
+    #[test]
+    fn detect_txt_files() {
+        let text = "2\n5 3 8\n6 2 8\n10\n";
+        assert_eq!(
+            InstanceFormat::detect(Path::new("-"), text),
+            InstanceFormat::OrLibrary
+        );
+        assert_eq!(
+            InstanceFormat::detect(Path::new("knapsacks/bad.txt"), text),
+            InstanceFormat::Jooken
+        );
+    }
+
+    #[test_case("instance.json", Some(InstanceFormat::Json))]
+    #[test_case("instance.toml", Some(InstanceFormat::Toml))]
+    #[test_case("knapPI_1_50_1000.csv", Some(InstanceFormat::Pisinger))]
+    #[test_case("knapsacks/tiny.txt", None)]
+    fn detect_from_extension(path: &str, expected: Option<InstanceFormat>) {
+        assert_eq!(InstanceFormat::from_extension(Path::new(path)), expected);
+    }
+}
@@ -0,0 +1,251 @@
+use clap::{Args, ValueEnum};
+use rand::Rng;
+
+use crate::{item::Item, knapsack::Knapsack};
+
+/// The largest `range` we'll generate items with, and the largest spanner multiplier.
+/// This keeps values and weights (even after multiplying spanner items) well away from
+/// overflowing; see [`Knapsack::try_new`].
+const MAX_RANGE: u64 = 1 << 40;
+
+/// The classes of instance from Pisinger's "Where are the hard knapsack problems?"
+/// (Computers & Operations Research, 2005). In all of them the weights `w` are chosen
+/// uniformly from `1..=R` for the range `R`, except for inverse strongly correlated
+/// instances, where the values are chosen that way instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum InstanceClass {
+    /// Values chosen uniformly from `1..=R`, independently of the weights.
+    Uncorrelated,
+    /// Values chosen uniformly within `R/10` of the weight (but at least 1).
+    WeaklyCorrelated,
+    /// Values equal to the weight plus `R/10`.
+    StronglyCorrelated,
+    /// Values chosen uniformly from `1..=R`, and weights equal to the value plus `R/10`.
+    InverseStronglyCorrelated,
+    /// Values chosen uniformly within `R/500` of the weight plus `R/10`.
+    AlmostStronglyCorrelated,
+    /// Values equal to the weight.
+    SubsetSum,
+    /// Every item is a multiple of one of a small "spanner" set of items, which are
+    /// generated with `--spanner-class` and then divided by the multiplier limit plus one.
+    /// Each item picks a spanner item and a multiplier from `1..=m` at random.
+    Spanner,
+}
+
+/// The settings for generating knapsack instances in one of Pisinger's classes.
+#[derive(Debug, Clone, Args)]
+pub struct Generator {
+    /// The class of instance to generate.
+    #[arg(long, value_enum)]
+    pub class: InstanceClass,
+
+    /// The number of items.
+    #[arg(long, default_value_t = 1_000)]
+    pub items: usize,
+
+    /// The range `R` that weights (and values) are chosen from, `1..=R`.
+    #[arg(long, default_value_t = 1_000)]
+    pub range: u64,
+
+    /// The capacity, as a fraction of the total weight of all the items.
+    #[arg(long, default_value_t = 0.5)]
+    pub capacity_ratio: f64,
+
+    /// For spanner instances, the number of items in the spanner set.
+    #[arg(long, default_value_t = 2)]
+    pub spanner_size: usize,
+
+    /// For spanner instances, the largest multiplier of a spanner item.
+    #[arg(long, default_value_t = 10)]
+    pub spanner_multiplier: u64,
+
+    /// For spanner instances, the class the spanner set is generated with.
+    #[arg(long, value_enum, default_value_t = InstanceClass::StronglyCorrelated)]
+    pub spanner_class: InstanceClass,
+}
+
+impl Generator {
+    /// Generate an instance with these settings. The item ids count up from 1.
+    ///
+    /// # Errors
+    ///
+    /// This fails if the settings don't make sense, e.g., there are no items, the range or
+    /// capacity ratio isn't positive, or the spanner set is generated with the spanner class.
+    pub fn generate<R: Rng + ?Sized>(&self, rng: &mut R) -> anyhow::Result<Knapsack> {
+        anyhow::ensure!(self.items > 0, "There has to be at least one item");
+        anyhow::ensure!(
+            (1..=MAX_RANGE).contains(&self.range),
+            "The range has to be between 1 and {MAX_RANGE}"
+        );
+
+        let pairs = if self.class == InstanceClass::Spanner {
+            self.spanner_pairs(rng)?
+        } else {
+            (0..self.items)
+                .map(|_| value_and_weight(self.class, self.range, rng))
+                .collect()
+        };
+        let items = (1..)
+            .zip(pairs)
+            .map(|(id, (value, weight))| Item::new(id, value, weight))
+            .collect::<Vec<_>>();
+        Knapsack::try_new(items, 0)?.with_capacity_ratio(self.capacity_ratio)
+    }
+
+    /// The values and weights of spanner items (see [`InstanceClass::Spanner`]).
+    fn spanner_pairs<R: Rng + ?Sized>(&self, rng: &mut R) -> anyhow::Result<Vec<(u64, u64)>> {
+        anyhow::ensure!(
+            self.spanner_class != InstanceClass::Spanner,
+            "The spanner set can't itself be generated as a spanner instance"
+        );
+        anyhow::ensure!(self.spanner_size > 0, "The spanner set can't be empty");
+        anyhow::ensure!(
+            (1..=MAX_RANGE).contains(&self.spanner_multiplier),
+            "The spanner multiplier has to be between 1 and {MAX_RANGE}"
+        );
+        let divisor = self.spanner_multiplier + 1;
+        let spanner_set = (0..self.spanner_size)
+            .map(|_| {
+                let (value, weight) = value_and_weight(self.spanner_class, self.range, rng);
+                (value.div_ceil(divisor), weight.div_ceil(divisor))
+            })
+            .collect::<Vec<_>>();
+        Ok((0..self.items)
+            .map(|_| {
+                let (value, weight) = spanner_set[rng.random_range(0..spanner_set.len())];
+                let multiplier = rng.random_range(1..=self.spanner_multiplier);
+                (value * multiplier, weight * multiplier)
+            })
+            .collect())
+    }
+}
+
+/// A random value and weight for an item in the given (non-spanner) class.
+fn value_and_weight<R: Rng + ?Sized>(class: InstanceClass, range: u64, rng: &mut R) -> (u64, u64) {
+    let tenth = range / 10;
+    let uniform = |rng: &mut R| rng.random_range(1..=range);
+    match class {
+        InstanceClass::Uncorrelated => (uniform(rng), uniform(rng)),
+        InstanceClass::WeaklyCorrelated => {
+            let weight = uniform(rng);
+            let value = rng.random_range(weight.saturating_sub(tenth).max(1)..=weight + tenth);
+            (value, weight)
+        }
+        InstanceClass::StronglyCorrelated => {
+            let weight = uniform(rng);
+            (weight + tenth, weight)
+        }
+        InstanceClass::InverseStronglyCorrelated => {
+            let value = uniform(rng);
+            (value, value + tenth)
+        }
+        InstanceClass::AlmostStronglyCorrelated => {
+            let weight = uniform(rng);
+            let centre = weight + tenth;
+            let spread = range / 500;
+            (rng.random_range(centre - spread..=centre + spread), weight)
+        }
+        InstanceClass::SubsetSum => {
+            let weight = uniform(rng);
+            (weight, weight)
+        }
+        InstanceClass::Spanner => unreachable!("Spanner items are built from a spanner set"),
+    }
+}
+
+#[expect(clippy::unwrap_used, reason = ".unwrap() is reasonable in tests")]
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_pcg::Pcg64;
+    use test_case::test_case;
+
+    use super::{Generator, InstanceClass};
+
+    fn generator(class: InstanceClass) -> Generator {
+        Generator {
+            class,
+            items: 200,
+            range: 1_000,
+            capacity_ratio: 0.5,
+            spanner_size: 2,
+            spanner_multiplier: 10,
+            spanner_class: InstanceClass::StronglyCorrelated,
+        }
+    }
+
+    #[test_case(InstanceClass::Uncorrelated, |_, _| true)]
+    #[test_case(InstanceClass::WeaklyCorrelated, |v, w| v.abs_diff(w) <= 100)]
+    #[test_case(InstanceClass::StronglyCorrelated, |v, w| v == w + 100)]
+    #[test_case(InstanceClass::InverseStronglyCorrelated, |v, w| w == v + 100)]
+    #[test_case(InstanceClass::AlmostStronglyCorrelated, |v, w| (w + 98..=w + 102).contains(&v))]
+    #[test_case(InstanceClass::SubsetSum, |v, w| v == w)]
+    fn items_follow_the_class(class: InstanceClass, related: fn(u64, u64) -> bool) {
+        let knapsack = generator(class)
+            .generate(&mut Pcg64::seed_from_u64(0))
+            .unwrap();
+        assert_eq!(knapsack.num_items(), 200);
+        for (id, item) in (1..).zip(knapsack.items()) {
+            assert_eq!(item.id(), id);
+            assert!(item.value() >= 1 && item.weight() >= 1);
+            assert!(related(item.value(), item.weight()), "{item:?}");
+        }
+        let total_weight = knapsack
+            .items()
+            .iter()
+            .map(|item| item.weight())
+            .sum::<u64>();
+        assert_eq!(knapsack.capacity(), total_weight / 2);
+    }
+
+    #[test]
+    fn spanner_items_are_multiples_of_the_spanner_set() {
+        fn gcd(a: u64, b: u64) -> u64 {
+            if b == 0 {
+                a
+            } else {
+                gcd(b, a % b)
+            }
+        }
+
+        let knapsack = generator(InstanceClass::Spanner)
+            .generate(&mut Pcg64::seed_from_u64(0))
+            .unwrap();
+        // Multiplying an item doesn't change its value/weight ratio, so there can only be
+        // as many different ratios as there are items in the spanner set.
+        let mut ratios = knapsack
+            .items()
+            .iter()
+            .map(|item| {
+                let divisor = gcd(item.value(), item.weight());
+                (item.value() / divisor, item.weight() / divisor)
+            })
+            .collect::<Vec<_>>();
+        ratios.sort_unstable();
+        ratios.dedup();
+        assert!(ratios.len() <= 2, "{ratios:?}");
+    }
+
+    #[test]
+    fn same_seed_same_instance() {
+        let generator = generator(InstanceClass::WeaklyCorrelated);
+        let first = generator.generate(&mut Pcg64::seed_from_u64(7)).unwrap();
+        let second = generator.generate(&mut Pcg64::seed_from_u64(7)).unwrap();
+        assert_eq!(first.items(), second.items());
+    }
+
+    #[test]
+    fn spanner_set_cant_be_spanner() {
+        let mut generator = generator(InstanceClass::Spanner);
+        generator.spanner_class = InstanceClass::Spanner;
+        assert!(generator.generate(&mut Pcg64::seed_from_u64(0)).is_err());
+    }
+
+    #[test_case(0)]
+    #[test_case(u64::MAX)]
+    fn spanner_multiplier_is_bounded(spanner_multiplier: u64) {
+        let mut generator = generator(InstanceClass::Spanner);
+        generator.spanner_multiplier = spanner_multiplier;
+        assert!(generator.generate(&mut Pcg64::seed_from_u64(0)).is_err());
+    }
+}
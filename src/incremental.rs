@@ -0,0 +1,191 @@
+use ec_linear::genome::bitstring::Bitstring;
+
+use crate::{cliff_score::CliffScore, knapsack::Knapsack, packed::PackedBits};
+
+/// A set of choices for a [`Knapsack`] that carries its total value and weight along
+/// with it.
+///
+/// [`Knapsack::value`] and [`Knapsack::weight`] add up all the items every time they're
+/// called, which is a lot of work for big instances when only one or two choices have
+/// changed. Here flipping a single choice (or swapping a chosen item for an unchosen one)
+/// updates the totals in constant time, and the effect of a flip or swap can be checked
+/// before deciding whether to make it.
+#[derive(Debug, Clone)]
+pub struct IncrementalSolution<'a> {
+    knapsack: &'a Knapsack,
+    choices: PackedBits,
+    value: u64,
+    weight: u64,
+}
+
+impl<'a> IncrementalSolution<'a> {
+    /// Start keeping track of the given choices. This is the only step that has
+    /// to look at all the items.
+    #[must_use]
+    pub fn new(knapsack: &'a Knapsack, choices: &Bitstring) -> Self {
+        let choices = PackedBits::from(choices);
+        let (value, weight) = knapsack.packed_value_and_weight(&choices);
+        Self {
+            knapsack,
+            choices,
+            value,
+            weight,
+        }
+    }
+
+    /// The number of items (chosen or not).
+    #[must_use]
+    pub fn num_items(&self) -> usize {
+        self.choices.len()
+    }
+
+    /// The total value of the chosen items.
+    #[must_use]
+    pub const fn value(&self) -> u64 {
+        self.value
+    }
+
+    /// The total weight of the chosen items.
+    #[must_use]
+    pub const fn weight(&self) -> u64 {
+        self.weight
+    }
+
+    /// Do the chosen items fit in the knapsack?
+    #[must_use]
+    pub const fn fits(&self) -> bool {
+        self.weight <= self.knapsack.capacity()
+    }
+
+    /// The same score [`CliffScorer`](crate::cliff_scorer::CliffScorer) would give these choices.
+    #[must_use]
+    pub const fn score(&self) -> CliffScore {
+        if self.fits() {
+            CliffScore::Score(self.value)
+        } else {
+            CliffScore::Overloaded
+        }
+    }
+
+    /// Is the item at `index` chosen?
+    #[must_use]
+    pub fn is_chosen(&self, index: usize) -> bool {
+        self.choices.get(index)
+    }
+
+    /// The current choices as a `Bitstring`.
+    #[must_use]
+    pub fn choices(&self) -> Bitstring {
+        Bitstring::from(&self.choices)
+    }
+
+    /// What the total value and weight would be if the choice for the item at `index`
+    /// were flipped.
+    #[must_use]
+    pub fn after_flip(&self, index: usize) -> (u64, u64) {
+        let item = &self.knapsack.items()[index];
+        if self.choices.get(index) {
+            (self.value - item.value(), self.weight - item.weight())
+        } else {
+            (self.value + item.value(), self.weight + item.weight())
+        }
+    }
+
+    /// Flip the choice for the item at `index`.
+    pub fn flip(&mut self, index: usize) {
+        (self.value, self.weight) = self.after_flip(index);
+        self.choices.flip(index);
+    }
+
+    /// What the total value and weight would be if the (chosen) item at `removed`
+    /// were swapped for the (unchosen) item at `added`.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `removed` isn't chosen or `added` is.
+    #[must_use]
+    pub fn after_swap(&self, removed: usize, added: usize) -> (u64, u64) {
+        assert!(
+            self.choices.get(removed) && !self.choices.get(added),
+            "Can only swap a chosen item for an unchosen one"
+        );
+        let items = self.knapsack.items();
+        (
+            self.value - items[removed].value() + items[added].value(),
+            self.weight - items[removed].weight() + items[added].weight(),
+        )
+    }
+
+    /// Swap the (chosen) item at `removed` for the (unchosen) item at `added`.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `removed` isn't chosen or `added` is.
+    pub fn swap(&mut self, removed: usize, added: usize) {
+        (self.value, self.weight) = self.after_swap(removed, added);
+        self.choices.flip(removed);
+        self.choices.flip(added);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ec_linear::genome::bitstring::Bitstring;
+
+    use super::IncrementalSolution;
+    use crate::{cliff_score::CliffScore, item::Item, knapsack::Knapsack};
+
+    fn knapsack() -> Knapsack {
+        Knapsack::new(
+            vec![
+                Item::new(1, 5, 8),
+                Item::new(2, 9, 6),
+                Item::new(3, 2, 7),
+                Item::new(4, 4, 3),
+            ],
+            15,
+        )
+    }
+
+    fn assert_consistent(solution: &IncrementalSolution, knapsack: &Knapsack) {
+        let choices = solution.choices();
+        assert_eq!(solution.value(), knapsack.value(&choices));
+        assert_eq!(solution.weight(), knapsack.weight(&choices));
+    }
+
+    #[test]
+    fn flips_match_full_evaluation() {
+        let knapsack = knapsack();
+        let mut solution =
+            IncrementalSolution::new(&knapsack, &Bitstring::from_iter([true, false, true, false]));
+        assert_consistent(&solution, &knapsack);
+        for index in [1, 0, 3, 1, 2, 2] {
+            let expected = solution.after_flip(index);
+            solution.flip(index);
+            assert_eq!((solution.value(), solution.weight()), expected);
+            assert_consistent(&solution, &knapsack);
+        }
+    }
+
+    #[test]
+    fn swaps_match_full_evaluation() {
+        let knapsack = knapsack();
+        let mut solution = IncrementalSolution::new(&knapsack, &Bitstring::from_iter([false; 4]));
+        solution.flip(0);
+        assert_eq!(solution.after_swap(0, 1), (9, 6));
+        solution.swap(0, 1);
+        assert!(solution.is_chosen(1) && !solution.is_chosen(0));
+        assert_consistent(&solution, &knapsack);
+    }
+
+    #[test]
+    fn scores_like_cliff_scorer() {
+        let knapsack = knapsack();
+        let mut solution = IncrementalSolution::new(&knapsack, &Bitstring::from_iter([false; 4]));
+        solution.flip(0);
+        solution.flip(1);
+        assert_eq!(solution.score(), CliffScore::Score(14));
+        solution.flip(2);
+        assert_eq!(solution.score(), CliffScore::Overloaded);
+    }
+}
@@ -0,0 +1,283 @@
+use std::{
+    fmt::Display,
+    fs,
+    io::{self, Read},
+    iter::Enumerate,
+    ops::Range,
+    path::Path,
+    str::{FromStr, Lines},
+};
+
+use anyhow::{anyhow, Context};
+
+/// Read a whole instance file, where a path of `-` means standard input, so instances can
+/// be piped in from another program. This returns the contents along with a name for the
+/// file to use in error messages (`<stdin>` for standard input).
+///
+/// # Errors
+///
+/// This fails if we can't read the file (or standard input).
+pub fn read_instance_file(path: &Path) -> anyhow::Result<(String, String)> {
+    if path == Path::new("-") {
+        let mut contents = String::new();
+        io::stdin()
+            .read_to_string(&mut contents)
+            .context("Failed to read an instance from standard input")?;
+        Ok((contents, "<stdin>".to_string()))
+    } else {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read the file {path:?}"))?;
+        Ok((contents, path.display().to_string()))
+    }
+}
+
+/// The lines of a knapsack instance file that have something in them.
+///
+/// Blank lines and comment lines (whose first non-blank character is `#`) are skipped,
+/// so instance files can start with a header saying where they came from. Every error
+/// says which file and (1-based) line it's about, like `knapsacks/tiny.txt:3: ...`.
+///
+/// This also checks that item ids are unique and sequential: they have to count up by one
+/// from either 0 (like the Jooken instances) or 1 (like `knapsacks/tiny.txt`).
+pub struct InstanceLines<'a> {
+    source: String,
+    lines: Enumerate<Lines<'a>>,
+    ids: ItemIds,
+}
+
+/// Checks that item ids are unique and sequential, counting up by one from either 0 or 1.
+/// [`InstanceLines`] does this for text formats; this is for items from anywhere else.
+#[derive(Debug, Default)]
+pub struct ItemIds {
+    /// The ids of the items so far, once we've seen the first one.
+    ids: Option<Range<u64>>,
+}
+
+impl ItemIds {
+    /// Check that `id` is the next item id in sequence.
+    ///
+    /// # Errors
+    ///
+    /// This fails if `id` has already been used or isn't the next one in sequence.
+    pub fn check(&mut self, id: u64) -> anyhow::Result<()> {
+        match &mut self.ids {
+            None if id > 1 => Err(anyhow!(
+                "the first item's id should be 0 or 1, but it's {id}"
+            )),
+            None => {
+                self.ids = Some(id..id + 1);
+                Ok(())
+            }
+            Some(ids) if id == ids.end => {
+                ids.end += 1;
+                Ok(())
+            }
+            Some(ids) if ids.contains(&id) => Err(anyhow!("item id {id} is used more than once")),
+            Some(ids) => Err(anyhow!(
+                "item ids should count up by one, so this should be {}, but it's {id}",
+                ids.end
+            )),
+        }
+    }
+}
+
+impl<'a> InstanceLines<'a> {
+    /// Go through the lines of `text`, which came from `source` (usually a file path).
+    #[must_use]
+    pub fn new(text: &'a str, source: impl Display) -> Self {
+        Self {
+            source: source.to_string(),
+            lines: text.lines().enumerate(),
+            ids: ItemIds::default(),
+        }
+    }
+
+    /// An error about the given (1-based) line.
+    #[must_use]
+    pub fn error_at(&self, line_number: usize, message: impl Display) -> anyhow::Error {
+        anyhow!("{}:{line_number}: {message}", self.source)
+    }
+
+    /// Get the next line that isn't blank or a comment, along with its line number.
+    /// `what` describes what should be on that line, for the error if the file ends.
+    ///
+    /// # Errors
+    ///
+    /// This fails if there are no more lines.
+    pub fn next_line(&mut self, what: &str) -> anyhow::Result<(usize, &'a str)> {
+        self.lines
+            .find(|(_, line)| {
+                let line = line.trim();
+                !line.is_empty() && !line.starts_with('#')
+            })
+            .map(|(index, line)| (index + 1, line.trim()))
+            .ok_or_else(|| anyhow!("{}: the file ended before {what}", self.source))
+    }
+
+    /// Parse the next line as a single value, e.g., the number of items or the capacity.
+    ///
+    /// # Errors
+    ///
+    /// This fails if there are no more lines, or the next one can't be parsed.
+    pub fn parse_next<T>(&mut self, what: &str) -> anyhow::Result<T>
+    where
+        T: FromStr,
+        T::Err: std::error::Error + Send + Sync + 'static,
+    {
+        let (line_number, line) = self.next_line(what)?;
+        line.parse().with_context(|| {
+            format!(
+                "{}:{line_number}: failed to parse {what} from '{line}'",
+                self.source
+            )
+        })
+    }
+
+    /// Parse the next line as whitespace separated integers, however many there are.
+    ///
+    /// # Errors
+    ///
+    /// This fails if there are no more lines, or a field in the next one isn't an integer.
+    pub fn next_fields(&mut self, what: &str) -> anyhow::Result<(usize, Vec<u64>)> {
+        let (line_number, line) = self.next_line(what)?;
+        let fields = line
+            .split_ascii_whitespace()
+            .map(u64::from_str)
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| {
+                format!(
+                    "{}:{line_number}: failed to parse {what} from '{line}'",
+                    self.source
+                )
+            })?;
+        Ok((line_number, fields))
+    }
+
+    /// Parse the line for item `n` (counting from 1) of `num_items`, which should have one
+    /// integer for each of the `fields` (the first of which is the id), and check its id.
+    ///
+    /// # Errors
+    ///
+    /// This fails if there are no more lines, the next one doesn't have the right number
+    /// of integers, or its id isn't the next one in sequence.
+    pub fn next_item(
+        &mut self,
+        n: usize,
+        num_items: usize,
+        fields: &[&str],
+    ) -> anyhow::Result<Vec<u64>> {
+        let what = format!("item {n} of {num_items}");
+        let (line_number, values) = self.next_fields(&what)?;
+        if values.len() != fields.len() {
+            return Err(self.error_at(
+                line_number,
+                format!(
+                    "{what} should have {} whitespace separated fields ({}) but has {}; is the number of items right?",
+                    fields.len(),
+                    fields.join(", "),
+                    values.len()
+                ),
+            ));
+        }
+        self.check_item_id(line_number, values[0])?;
+        Ok(values)
+    }
+
+    /// Check that `id`, from the given line, is the next item id in sequence. This is
+    /// done by [`InstanceLines::next_item`], and is only needed separately for item
+    /// lines in other formats.
+    ///
+    /// # Errors
+    ///
+    /// This fails if `id` has already been used or isn't the next one in sequence.
+    pub fn check_item_id(&mut self, line_number: usize, id: u64) -> anyhow::Result<()> {
+        self.ids
+            .check(id)
+            .map_err(|problem| self.error_at(line_number, problem))
+    }
+
+    /// Start checking item ids from scratch, for files with several instances in them.
+    pub fn restart_ids(&mut self) {
+        self.ids = ItemIds::default();
+    }
+
+    /// Check that there's nothing left in the file except blank lines and comments.
+    /// `previous` describes the last thing that should be in the file.
+    ///
+    /// # Errors
+    ///
+    /// This fails if there's anything else in the file.
+    pub fn finish(mut self, previous: &str) -> anyhow::Result<()> {
+        match self.next_line("") {
+            Ok((line_number, line)) => Err(self.error_at(
+                line_number,
+                format!("unexpected '{line}' after {previous}; is the number of items right?"),
+            )),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InstanceLines;
+
+    const FIELDS: &[&str] = &["id", "value", "weight"];
+
+    #[test]
+    fn skips_blank_lines_and_comments() {
+        let text = "# A comment\n\n  3\n   # Another comment\n1 2 3\n\n";
+        let mut lines = InstanceLines::new(text, "test");
+        assert_eq!(lines.next_line("the count").ok(), Some((3, "3")));
+        assert_eq!(lines.next_fields("an item").ok(), Some((5, vec![1, 2, 3])));
+        assert!(lines.finish("the item").is_ok());
+    }
+
+    #[test]
+    fn errors_have_file_and_line() {
+        let mut lines = InstanceLines::new("\n\nthree\n", "tiny.txt");
+        let error = lines.parse_next::<usize>("the number of items");
+        assert!(error.is_err_and(|error| error.to_string().starts_with("tiny.txt:3: ")));
+    }
+
+    #[test]
+    fn trailing_content_is_rejected() {
+        let mut lines = InstanceLines::new("10\n\n5\n", "test");
+        assert_eq!(lines.parse_next::<u64>("the capacity").ok(), Some(10));
+        assert!(lines
+            .finish("the capacity")
+            .is_err_and(|error| error.to_string().starts_with("test:3: ")));
+    }
+
+    #[test]
+    fn ids_can_start_at_zero_or_one() {
+        for first in [0, 1] {
+            let text = format!("{first} 5 5\n{} 5 5\n", first + 1);
+            let mut lines = InstanceLines::new(&text, "test");
+            assert!(lines.next_item(1, 2, FIELDS).is_ok());
+            assert!(lines.next_item(2, 2, FIELDS).is_ok());
+        }
+        let mut lines = InstanceLines::new("2 5 5\n", "test");
+        assert!(lines.next_item(1, 1, FIELDS).is_err());
+    }
+
+    #[test]
+    fn ids_have_to_be_unique_and_sequential() {
+        let mut lines = InstanceLines::new("1 5 5\n2 5 5\n1 5 5\n", "test");
+        assert!(lines.next_item(1, 3, FIELDS).is_ok());
+        assert!(lines.next_item(2, 3, FIELDS).is_ok());
+        assert!(lines
+            .next_item(3, 3, FIELDS)
+            .is_err_and(|error| error.to_string().contains("more than once")));
+
+        let mut lines = InstanceLines::new("0 5 5\n2 5 5\n", "test");
+        assert!(lines.next_item(1, 2, FIELDS).is_ok());
+        assert!(lines.next_item(2, 2, FIELDS).is_err());
+    }
+
+    #[test]
+    fn items_need_the_right_number_of_fields() {
+        let mut lines = InstanceLines::new("1 5\n", "test");
+        assert!(lines.next_item(1, 1, FIELDS).is_err());
+    }
+}
@@ -1,7 +1,9 @@
 use std::str::FromStr;
 
+use serde::{Deserialize, Serialize};
+
 /// Represents an item with an id, value, and weight.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Item {
     id: u64,
     value: u64,
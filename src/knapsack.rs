@@ -1,32 +1,71 @@
-use anyhow::{anyhow, Context};
+use anyhow::Context;
 use ec_linear::genome::bitstring::Bitstring;
-use std::{
-    fs::File,
-    io::{self, BufRead},
-    path::Path,
-    str::FromStr,
+use rand::{
+    seq::{index, SliceRandom},
+    Rng,
 };
+use std::{fmt::Display, io::Read, path::Path, str::FromStr};
 
-use crate::item::Item;
+use crate::{
+    instance_lines::{read_instance_file, InstanceLines},
+    item::Item,
+    overflow::checked_total,
+    packed::PackedBits,
+};
 
 /// Representation of a given knapsack problem.
 ///
 /// A knapsack problem is a `capacity` along with a collection `items``,
 /// each of which has a value and weight.
 // We need to derive `Debug` so we can print out instances of `Knapsack`.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Knapsack {
     /// The collection of items to choose from in this instance
     items: Vec<Item>,
     /// The capacity of the knapsack, i.e., the maximum total weight it can hold
     capacity: u64,
+    /// The best known (often optimal) value for this instance, if the instance file had one.
+    best_known: Option<u64>,
 }
 
 impl Knapsack {
     /// Construct a knapsack instance from a collection of items and a capacity.
+    ///
+    /// # Panics
+    ///
+    /// This panics if the total value or weight of all the items is too big for a `u64`;
+    /// use [`Knapsack::try_new`] to get an error instead.
     #[must_use]
-    pub const fn new(items: Vec<Item>, capacity: u64) -> Self {
-        Self { items, capacity }
+    pub fn new(items: Vec<Item>, capacity: u64) -> Self {
+        match Self::try_new(items, capacity) {
+            Ok(knapsack) => knapsack,
+            Err(error) => panic!("{error}"),
+        }
+    }
+
+    /// Construct a knapsack instance from a collection of items and a capacity, checking
+    /// that no set of choices can have a total value or weight too big for a `u64`. This
+    /// means [`Knapsack::value`], [`Knapsack::weight`], and the like can never overflow.
+    ///
+    /// # Errors
+    ///
+    /// This fails if the total value or weight of all the items is more than `u64::MAX`.
+    pub fn try_new(items: Vec<Item>, capacity: u64) -> anyhow::Result<Self> {
+        checked_total(items.iter().map(|item| u128::from(item.value())), "value")?;
+        checked_total(items.iter().map(|item| u128::from(item.weight())), "weight")?;
+        Ok(Self {
+            items,
+            capacity,
+            best_known: None,
+        })
+    }
+
+    /// Record the best known (often optimal) value for this instance, e.g., from the
+    /// instance file.
+    #[must_use]
+    pub const fn with_best_known(mut self, best_known: Option<u64>) -> Self {
+        self.best_known = best_known;
+        self
     }
 
     /// Get the items available in this knapsack as a _slice_. This is essentially like
@@ -61,6 +100,130 @@ impl Knapsack {
         self.capacity
     }
 
+    /// Get the best known value for this instance, if there is one.
+    #[must_use]
+    pub const fn best_known(&self) -> Option<u64> {
+        self.best_known
+    }
+
+    /// Get the total weight of all the items. This can't overflow (see [`Knapsack::try_new`]).
+    #[must_use]
+    pub fn total_weight(&self) -> u64 {
+        self.items.iter().map(Item::weight).sum()
+    }
+
+    /// Make a new instance with the same items, whose capacity is `ratio` times the total
+    /// weight of all the items (rounded down). The best known value is dropped unless the
+    /// capacity doesn't change, since it won't apply to the new instance.
+    ///
+    /// # Errors
+    ///
+    /// This fails if `ratio` isn't a positive number.
+    pub fn with_capacity_ratio(mut self, ratio: f64) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            ratio > 0.0 && ratio.is_finite(),
+            "The capacity ratio has to be positive, but it's {ratio}"
+        );
+        let capacity = (ratio * self.total_weight() as f64).floor() as u64;
+        if capacity != self.capacity {
+            self.capacity = capacity;
+            self.best_known = None;
+        }
+        Ok(self)
+    }
+
+    /// Make a new instance from `num_items` of the items chosen at random, keeping their
+    /// order and the capacity. The items are given new ids so they still count up by one
+    /// from the first id (see [`Knapsack::from_file_path`]), and the best known value is
+    /// dropped.
+    ///
+    /// # Errors
+    ///
+    /// This fails if `num_items` is 0 or more than the number of items.
+    pub fn subsample<R: Rng + ?Sized>(self, num_items: usize, rng: &mut R) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            (1..=self.num_items()).contains(&num_items),
+            "Can't pick {num_items} items from an instance with {} items",
+            self.num_items()
+        );
+        let mut indices = index::sample(rng, self.num_items(), num_items).into_vec();
+        indices.sort_unstable();
+        let items = renumbered(&self.items, indices.into_iter().map(|i| self.items[i]));
+        Ok(Self {
+            items,
+            capacity: self.capacity,
+            best_known: None,
+        })
+    }
+
+    /// Make a new instance with every value multiplied by `value_factor` and every weight
+    /// (and the capacity) multiplied by `weight_factor`, rounding to the nearest integer
+    /// (but keeping values and weights at least 1). The best known value is kept (and
+    /// scaled) if both factors are whole numbers and no value or weight had to be raised
+    /// to 1, since the new instance then has the same solutions as the old one.
+    ///
+    /// # Errors
+    ///
+    /// This fails if either factor isn't a positive number, any of the amounts (before or
+    /// after scaling) is too big to be scaled exactly (more than 2^53), or the scaled total
+    /// value or weight is too big (see [`Knapsack::try_new`]).
+    pub fn scale(self, value_factor: f64, weight_factor: f64) -> anyhow::Result<Self> {
+        /// The largest integer that a `f64` (and so the scaling) can represent exactly.
+        const MAX_EXACT: u64 = 1 << f64::MANTISSA_DIGITS;
+
+        for (factor, what) in [(value_factor, "value"), (weight_factor, "weight")] {
+            anyhow::ensure!(
+                factor > 0.0 && factor.is_finite(),
+                "The {what} scaling factor has to be positive, but it's {factor}"
+            );
+        }
+        let scaled = |amount: u64, factor: f64| {
+            let scaled = (amount as f64 * factor).round();
+            anyhow::ensure!(
+                amount <= MAX_EXACT && scaled <= MAX_EXACT as f64,
+                "Can't scale {amount} by {factor} exactly, since one of them is more than {MAX_EXACT}"
+            );
+            Ok(scaled as u64)
+        };
+        let mut clamped = false;
+        let mut at_least_one = |amount: u64| {
+            clamped |= amount == 0;
+            amount.max(1)
+        };
+        let items = self
+            .items
+            .iter()
+            .map(|item| {
+                Ok(Item::new(
+                    item.id(),
+                    at_least_one(scaled(item.value(), value_factor)?),
+                    at_least_one(scaled(item.weight(), weight_factor)?),
+                ))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        let exact = value_factor.fract() == 0.0 && weight_factor.fract() == 0.0 && !clamped;
+        let best_known = self
+            .best_known
+            .filter(|_| exact)
+            .map(|best_known| scaled(best_known, value_factor))
+            .transpose()?;
+        Ok(
+            Self::try_new(items, scaled(self.capacity, weight_factor)?)?
+                .with_best_known(best_known),
+        )
+    }
+
+    /// Make a new instance with the items in a random order. The items are given new ids
+    /// so they still count up by one from the first id; it's otherwise the same instance,
+    /// so the best known value is kept.
+    #[must_use]
+    pub fn shuffle<R: Rng + ?Sized>(mut self, rng: &mut R) -> Self {
+        let mut items = self.items.clone();
+        items.shuffle(rng);
+        self.items = renumbered(&self.items, items);
+        self
+    }
+
     /// Get the value of a current set of choices for this knapsack. `choices` is a `Bitstring`
     /// indicating which `Item`s to include (1s in `choices`) and which to leave out (0s in `choices`).
     /// This is the sum of the value of all the chosen items as specified in `choices`.
@@ -85,6 +248,33 @@ impl Knapsack {
             .sum()
     }
 
+    /// Get both the value and the weight of a current set of choices for this knapsack.
+    /// This gives the same results as calling [`Knapsack::value`] and [`Knapsack::weight`],
+    /// but only goes through `choices` once.
+    #[must_use]
+    pub fn value_and_weight(&self, choices: &Bitstring) -> (u64, u64) {
+        self.items
+            .iter()
+            .zip(choices.iter())
+            .filter(|(_, &included)| included)
+            .fold((0, 0), |(value, weight), (item, _)| {
+                (value + item.value(), weight + item.weight())
+            })
+    }
+
+    /// Get both the value and the weight of a current set of choices for this knapsack,
+    /// where the choices are packed into words (see [`PackedBits`]). This only visits
+    /// the chosen items, skipping over runs of unchosen items a word at a time.
+    #[must_use]
+    pub fn packed_value_and_weight(&self, choices: &PackedBits) -> (u64, u64) {
+        choices
+            .ones()
+            .map(|index| &self.items[index])
+            .fold((0, 0), |(value, weight), item| {
+                (value + item.value(), weight + item.weight())
+            })
+    }
+
     /// Parse a knapsack instance from a text file.
     ///
     /// There are numerous different formats for instances of the knapsack problem.
@@ -101,72 +291,91 @@ impl Knapsack {
     ///
     /// - The first line is an integer `N` indicating how many items are available to choose from.
     /// - The next `N` lines are the `N` items, specified by three integers:
-    ///    - The first integer in the line is the item number (1, 2, 3, etc.).
+    ///    - The first integer in the line is the item number. These have to count up by one,
+    ///      starting from either 0 (as in the Jooken instances) or 1 (as above).
     ///    - The second integer is the value of the item.
     ///    - The third integer is the weight of the item.
     /// - The last line in the file is an integer `C` that is the capacity of the knapsack.
     ///
+    /// Blank lines, and comment lines starting with `#`, can go anywhere and are ignored.
+    ///
+    /// A `file_path` of `-` reads the instance from standard input.
+    ///
     /// # Errors
     ///
     /// This can fail if:
-    ///    - We fail to open the file, or
-    ///    - The file contents have the wrong format
+    ///    - We fail to read the file, or
+    ///    - The file contents have the wrong format (the error says which line is wrong), or
+    ///    - The items' total value or weight is too big (see [`Knapsack::try_new`])
     pub fn from_file_path(file_path: impl AsRef<Path>) -> anyhow::Result<Self> {
-        // Open the file, returning (via the `?` operator) an error if there's a problem opening the file.
-        let file = File::open(file_path.as_ref())?;
-        // Create a buffered reader for this file.
-        let reader = io::BufReader::new(file);
-        // Create an iterator over all the lines in the file.
-        let mut line_iter = reader.lines();
-
-        // Get the first line and parse it into a `usize` for the number of items.
-        let num_items = line_iter
-            // Get the first item (line) from the iterator.
-            // The result of `.next()` is an `Option`, with the `None` variant indicating
-            // that there _was_ no next value (i.e., no line). The `Some` variant wraps
-            // a `Result<String, std::io::Error>`. This will be `Some(s)` for some `String`
-            // `s` if it was able to successfully read the line; it will be `Err(e)` for some
-            // I/O error if there was an error reading the line.
-            .next()
-            // If the file was empty, `.next()` would return the `None` variant.
-            // We'll turn that into a `Result::Err` variant and return it with `?`. The second
-            // `?` is for the `Result` inside the `Option`, and will return that inner error
-            // if there is one, leaving us with the string for that line if everything was OK.
-            .ok_or_else(|| anyhow!("The input file {:?} was empty", file_path.as_ref()))??
-            // Parse that string into a `usize`, returning any error with the `?` operator.
-            .parse::<usize>()?;
-
-        let mut items: Vec<Item> = Vec::with_capacity(num_items);
-        for n in 0..num_items {
-            // Get the next item (line) from the iterator. The error handling is essentially
-            // the same as in reading `num_items` above.
-            let line = line_iter.next().ok_or_else(|| anyhow!("Failed to read line {n} from the file; is the number of items on the first line correct?"))??;
-            // Parse `line` into an `Item`, returning any parse error with the `?` operator.
-            let item = Item::from_str(&line)
-                .with_context(|| "Failed to parse line '{line}' into an `Item`.")?;
-            // Add the successfully parsed `Item` to the vector of `items`.
-            items.push(item);
-        }
+        // Read the whole file (or standard input, for `-`) into a string, returning (via
+        // the `?` operator) an error if there's a problem reading it.
+        let (contents, source) = read_instance_file(file_path.as_ref())?;
+        Self::parse(&contents, source)
+    }
 
-        // Ensure that we got the right number of `Item`s. This could fail if, for example, the
-        // file didn't have enough lines.
-        anyhow::ensure!(
-            items.len() == num_items,
-            "We weren't able to read {num_items} from the file, and only got {}.",
-            items.len()
-        );
+    /// Read a knapsack instance in the format described in [`Knapsack::from_file_path`]
+    /// from any reader, e.g., standard input or a network connection. `source` says where
+    /// the instance came from, for error messages.
+    ///
+    /// # Errors
+    ///
+    /// This can fail if:
+    ///    - We fail to read from `reader`, or
+    ///    - What we read has the wrong format, or
+    ///    - The items' total value or weight is too big (see [`Knapsack::try_new`])
+    pub fn from_reader(mut reader: impl Read, source: impl Display) -> anyhow::Result<Self> {
+        let mut contents = String::new();
+        reader
+            .read_to_string(&mut contents)
+            .with_context(|| format!("Failed to read an instance from {source}"))?;
+        Self::parse(&contents, source)
+    }
+
+    /// Parse a knapsack instance in the format described in [`Knapsack::from_file_path`]
+    /// from `text`, which came from `source` (used in error messages).
+    ///
+    /// # Errors
+    ///
+    /// This can fail if `text` has the wrong format, or the items' total value or weight
+    /// is too big (see [`Knapsack::try_new`]).
+    pub fn parse(text: &str, source: impl Display) -> anyhow::Result<Self> {
+        // This skips blank and comment lines, and keeps track of line numbers for errors.
+        let mut lines = InstanceLines::new(text, &source);
+
+        let num_items = lines.parse_next::<usize>("the number of items")?;
+        let items = (1..=num_items)
+            .map(|n| {
+                let fields = lines.next_item(n, num_items, &["id", "value", "weight"])?;
+                Ok(Item::new(fields[0], fields[1], fields[2]))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let capacity = lines.parse_next("the capacity")?;
+        // Anything after the capacity probably means the number of items was wrong.
+        lines.finish("the capacity")?;
+
+        Self::try_new(items, capacity)
+            .with_context(|| format!("The knapsack in {source} can't be used"))
+    }
+}
+
+/// Give `items` ids counting up by one from the first id in `original` (which will be
+/// 0 or 1 for instances we've read; see [`Knapsack::from_file_path`]).
+fn renumbered(original: &[Item], items: impl IntoIterator<Item = Item>) -> Vec<Item> {
+    let first_id = original.first().map_or(1, Item::id);
+    (first_id..)
+        .zip(items)
+        .map(|(id, item)| Item::new(id, item.value(), item.weight()))
+        .collect()
+}
 
-        // Parse the knapsack capacity from the last line, similar to how we parsed the number
-        // of items from the first line.
-        let capacity = line_iter
-            .next()
-            .ok_or_else(|| anyhow!(
-                "There was no capacity line in the input file {:?}\nThis might be because the number of items was set incorrectly.",
-                file_path.as_ref()
-            ))??
-            .parse()?;
+impl FromStr for Knapsack {
+    type Err = anyhow::Error;
 
-        Ok(Self { items, capacity })
+    /// Parse a knapsack instance in the format described in [`Knapsack::from_file_path`]
+    /// from a string, which is handy for small instances in tests.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s, "<string>")
     }
 }
 
@@ -174,7 +383,7 @@ impl Knapsack {
 #[cfg(test)]
 mod tests {
     use super::Knapsack;
-    use crate::item::Item;
+    use crate::{item::Item, packed::PackedBits};
     use ec_linear::genome::bitstring::Bitstring;
     use test_case::test_case;
 
@@ -188,6 +397,40 @@ mod tests {
         assert_eq!(knapsack.capacity(), 10);
     }
 
+    #[test]
+    fn parse_from_reader_and_string() {
+        const TINY: &str = "3\n1 3 8\n2 2 8\n3 9 1\n10\n";
+        let from_file = Knapsack::from_file_path("knapsacks/tiny.txt").unwrap();
+        let from_reader = Knapsack::from_reader(TINY.as_bytes(), "tiny").unwrap();
+        let from_string = TINY.parse::<Knapsack>().unwrap();
+        for knapsack in [from_reader, from_string] {
+            assert_eq!(knapsack.items(), from_file.items());
+            assert_eq!(knapsack.capacity(), from_file.capacity());
+        }
+    }
+
+    #[test]
+    fn parse_with_comments_and_blank_lines() {
+        let text = "# From knapsacks/tiny.txt\n3\n\n0 3 8\n1 2 8 \n# The last item\n2 9 1\n10\n\n";
+        let knapsack = text.parse::<Knapsack>().unwrap();
+        assert_eq!(knapsack.num_items(), 3);
+        assert_eq!(knapsack.get_item(2), Some(&Item::new(2, 9, 1)));
+        assert_eq!(knapsack.capacity(), 10);
+    }
+
+    #[test_case("3\n1 3 8\n2 2 8\n3 9 1\n", "test: the file ended"; "missing capacity")]
+    #[test_case("2\n1 3 8\n2 2 8\n3 9 1\n10\n", "test:4"; "too few items")]
+    #[test_case("3\n1 3 8\n2 2 8\n\n3 9 1\n10\n7\n", "test:7"; "trailing content")]
+    #[test_case("3\n1 3 8\n2 2 x\n3 9 1\n10\n", "test:3"; "bad item")]
+    #[test_case("3\n1 3 8\n1 2 8\n3 9 1\n10\n", "test:3"; "repeated id")]
+    fn parse_errors_say_where(text: &str, location: &str) {
+        let error = Knapsack::from_reader(text.as_bytes(), "test").unwrap_err();
+        assert!(
+            error.to_string().starts_with(location),
+            "'{error}' doesn't start with '{location}'"
+        );
+    }
+
     #[test_case([false, false, false], 0; "choose no items")]
     #[test_case([false, true, false], 9; "choose one item")]
     #[test_case([true, false, true], 7; "choose two items")]
@@ -201,6 +444,30 @@ mod tests {
         assert_eq!(knapsack.value(&choices), expected_value);
     }
 
+    #[test_case([false, false, false], (0, 0); "choose no items")]
+    #[test_case([false, true, false], (9, 6); "choose one item")]
+    #[test_case([true, false, true], (7, 15); "choose two items")]
+    fn test_values_and_weights(choices: [bool; 3], expected: (u64, u64)) {
+        let knapsack = Knapsack::new(
+            vec![Item::new(1, 5, 8), Item::new(2, 9, 6), Item::new(3, 2, 7)],
+            100,
+        );
+
+        let choices = Bitstring::from_iter(choices);
+        assert_eq!(knapsack.value_and_weight(&choices), expected);
+        assert_eq!(
+            knapsack.packed_value_and_weight(&PackedBits::from(&choices)),
+            expected
+        );
+    }
+
+    #[test]
+    fn totals_that_could_overflow_are_rejected() {
+        let items = vec![Item::new(1, 5, u64::MAX - 1), Item::new(2, 9, 2)];
+        assert!(Knapsack::try_new(items.clone(), 10).is_err());
+        assert!(Knapsack::try_new(items[..1].to_vec(), 10).is_ok());
+    }
+
     #[test_case([false, false, false], 0; "choose no items")]
     #[test_case([false, true, false], 6; "choose one item")]
     #[test_case([true, false, true], 15; "choose two items")]
@@ -0,0 +1,268 @@
+use std::{
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context};
+use ec_linear::genome::bitstring::Bitstring;
+
+use crate::{
+    cliff_score::CliffScore,
+    results::{BestRecord, BestScope, GenerationRecord, ResultsWriter, RunRecord},
+};
+
+/// Parse the printed output of an old run (like the `Outputs/ts_*_pID_*/output_N.txt`
+/// files) into the records we'd now write with `--results`. These logs have lines like
+///
+/// ```text
+/// Best score in generation 12 was Score(12619)
+///     Entropy of the population was -861.0524727708339
+/// Best in final generation EcIndividual { genome: Bitstring { bits: [false, true] }, test_results: Score(12619) }
+/// Best in overall run: Some(EcIndividual { genome: Bitstring { bits: [true, true] }, test_results: Score(12771) })
+/// ```
+///
+/// where the individuals are printed with `Debug`. Other lines (like the knapsack and
+/// tournament size at the start) are skipped. The old logs didn't have any of the
+/// diversity or population statistics, so those are left out of the records.
+///
+/// # Errors
+///
+/// This fails if one of the lines above can't be parsed, or a generation doesn't have
+/// an entropy line; the error gives `source` and the line number.
+pub fn parse_legacy_log(text: &str, source: impl Display) -> anyhow::Result<Vec<RunRecord>> {
+    let mut records = Vec::new();
+    // The generation and best score we've seen, waiting for their entropy line.
+    let mut pending: Option<(usize, CliffScore)> = None;
+    for (index, line) in text.lines().enumerate() {
+        let error = |message: String| anyhow!("{source}:{}: {message}", index + 1);
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if let Some(entropy) = trimmed.strip_prefix("Entropy of the population was ") {
+            let (generation, best) = pending
+                .take()
+                .ok_or_else(|| error("an entropy line without a generation before it".into()))?;
+            let entropy = entropy
+                .parse()
+                .map_err(|_| error(format!("failed to parse the entropy from '{entropy}'")))?;
+            records.push(RunRecord::Generation(GenerationRecord {
+                generation,
+                best,
+                entropy,
+                diversity: None,
+                population: None,
+            }));
+            continue;
+        }
+        if let Some((generation, _)) = pending {
+            return Err(error(format!(
+                "expected the entropy for generation {generation}, but found '{trimmed}'"
+            )));
+        }
+        if let Some(rest) = trimmed.strip_prefix("Best score in generation ") {
+            let (generation, score) = rest
+                .split_once(" was ")
+                .ok_or_else(|| error(format!("failed to parse '{trimmed}'")))?;
+            let generation = generation
+                .parse()
+                .map_err(|_| error(format!("failed to parse the generation from '{trimmed}'")))?;
+            pending = Some((generation, parse_score(score).map_err(error)?));
+        } else if let Some(individual) = trimmed.strip_prefix("Best in final generation ") {
+            let (genome, score) = parse_individual(individual).map_err(error)?;
+            records.push(RunRecord::Best(BestRecord {
+                scope: BestScope::FinalGeneration,
+                score,
+                genome,
+            }));
+        } else if let Some(best) = trimmed.strip_prefix("Best in overall run: ") {
+            if best == "None" {
+                continue;
+            }
+            let individual = best
+                .strip_prefix("Some(")
+                .and_then(|individual| individual.strip_suffix(')'))
+                .ok_or_else(|| {
+                    error(format!(
+                        "failed to parse '{best}' as an optional individual"
+                    ))
+                })?;
+            let (genome, score) = parse_individual(individual).map_err(error)?;
+            records.push(RunRecord::Best(BestRecord {
+                scope: BestScope::Run,
+                score,
+                genome,
+            }));
+        }
+    }
+    if let Some((generation, _)) = pending {
+        return Err(anyhow!(
+            "{source}: the file ended before the entropy for generation {generation}"
+        ));
+    }
+    Ok(records)
+}
+
+/// Parse a `CliffScore` printed with `Debug`, i.e., `Overloaded` or `Score(n)`.
+fn parse_score(text: &str) -> Result<CliffScore, String> {
+    if text == "Overloaded" {
+        return Ok(CliffScore::Overloaded);
+    }
+    text.strip_prefix("Score(")
+        .and_then(|score| score.strip_suffix(')'))
+        .and_then(|score| score.parse().ok())
+        .map(CliffScore::Score)
+        .ok_or_else(|| format!("failed to parse '{text}' as a score"))
+}
+
+/// Parse an individual printed with `Debug`, like
+/// `EcIndividual { genome: Bitstring { bits: [false, true] }, test_results: Score(3) }`.
+fn parse_individual(text: &str) -> Result<(Bitstring, CliffScore), String> {
+    let error = || format!("failed to parse '{text}' as an individual");
+    let (bits, score) = text
+        .strip_prefix("EcIndividual { genome: Bitstring { bits: [")
+        .and_then(|rest| rest.split_once("] }, test_results: "))
+        .ok_or_else(error)?;
+    let score = score.strip_suffix(" }").ok_or_else(error)?;
+    let genome = bits
+        .split(',')
+        .map(str::trim)
+        .filter(|bit| !bit.is_empty())
+        .map(|bit| match bit {
+            "true" => Ok(true),
+            "false" => Ok(false),
+            _ => Err(format!(
+                "found '{bit}' in a genome, which should only have booleans"
+            )),
+        })
+        .collect::<Result<Bitstring, _>>()?;
+    Ok((genome, parse_score(score)?))
+}
+
+/// Import the old log at `input`, or every `output_*.txt` log in the directory `input`
+/// (and its subdirectories), as results files. A single log is written to `output`;
+/// for a directory, each log is written to the same place under the directory `output`
+/// with a `.jsonl` extension, so `Outputs/ts_2_pID_Sm1/output_1.txt` becomes
+/// `<output>/ts_2_pID_Sm1/output_1.jsonl`. This returns the number of logs imported.
+///
+/// # Errors
+///
+/// This fails if we can't read a log or directory, a log can't be parsed, or we can't
+/// write a results file.
+pub fn import_legacy(input: &Path, output: &Path) -> anyhow::Result<usize> {
+    if !input.is_dir() {
+        import_log(input, output)?;
+        return Ok(1);
+    }
+    let mut logs = Vec::new();
+    find_logs(input, &mut logs)?;
+    logs.sort();
+    for log in &logs {
+        let relative = log.strip_prefix(input).unwrap_or(log);
+        let results = output.join(relative).with_extension("jsonl");
+        if let Some(parent) = results.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create the directory {parent:?}"))?;
+        }
+        import_log(log, &results)?;
+    }
+    Ok(logs.len())
+}
+
+/// Add the paths of all the `output_*.txt` files in `directory` and its subdirectories
+/// to `logs`.
+fn find_logs(directory: &Path, logs: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    let entries = fs::read_dir(directory)
+        .with_context(|| format!("Failed to read the directory {directory:?}"))?;
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_logs(&path, logs)?;
+        } else if path.extension().is_some_and(|extension| extension == "txt")
+            && path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with("output_"))
+        {
+            logs.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn import_log(log: &Path, results: &Path) -> anyhow::Result<()> {
+    let text =
+        fs::read_to_string(log).with_context(|| format!("Failed to read the log {log:?}"))?;
+    let records = parse_legacy_log(&text, log.display())?;
+    let mut writer = ResultsWriter::create(results)?;
+    for record in &records {
+        writer.write(record)?;
+    }
+    writer.finish()
+}
+
+#[expect(clippy::unwrap_used, reason = ".unwrap() is reasonable in tests")]
+#[cfg(test)]
+mod tests {
+    use ec_linear::genome::bitstring::Bitstring;
+
+    use super::{parse_individual, parse_legacy_log};
+    use crate::{
+        cliff_score::CliffScore,
+        results::{BestScope, RunRecord},
+    };
+
+    const LOG: &str = "\
+Running on knapsack at: \"knapsacks/SmallProblem3.txt\"
+Running with tournament size: 2
+Best score in generation 0 was Overloaded
+\tEntropy of the population was -398.5
+Best score in generation 1 was Score(12619)
+\tEntropy of the population was -861.0524727708339
+Best in final generation EcIndividual { genome: Bitstring { bits: [false, true, true] }, test_results: Score(12619) }
+Best in overall run: Some(EcIndividual { genome: Bitstring { bits: [true, true, false] }, test_results: Score(12771) })
+";
+
+    #[test]
+    fn parse_a_log() {
+        let records = parse_legacy_log(LOG, "test").unwrap();
+        assert_eq!(records.len(), 4);
+        let RunRecord::Generation(generation) = &records[1] else {
+            panic!("Expected a generation record, but got {:?}", records[1]);
+        };
+        assert_eq!(generation.generation, 1);
+        assert_eq!(generation.best, CliffScore::Score(12619));
+        assert!((generation.entropy + 861.052_472_770_833_9).abs() < 1e-12);
+        let RunRecord::Best(best) = &records[3] else {
+            panic!("Expected a best record, but got {:?}", records[3]);
+        };
+        assert_eq!(best.scope, BestScope::Run);
+        assert_eq!(best.score, CliffScore::Score(12771));
+        assert_eq!(best.genome, Bitstring::from_iter([true, true, false]));
+    }
+
+    #[test]
+    fn parse_individuals() {
+        let individual =
+            "EcIndividual { genome: Bitstring { bits: [] }, test_results: Overloaded }";
+        assert_eq!(
+            parse_individual(individual).unwrap(),
+            (Bitstring::from_iter([]), CliffScore::Overloaded)
+        );
+        assert!(parse_individual("EcIndividual { genome: [true] }").is_err());
+    }
+
+    #[test]
+    fn errors_say_where() {
+        let missing_entropy = "Best score in generation 0 was Overloaded\nBest score in generation 1 was Overloaded\n";
+        let error = parse_legacy_log(missing_entropy, "log").unwrap_err();
+        assert!(error.to_string().starts_with("log:2: "), "{error}");
+
+        let bad_score = "Best score in generation 0 was Score(x)\n";
+        let error = parse_legacy_log(bad_score, "log").unwrap_err();
+        assert!(error.to_string().starts_with("log:1: "), "{error}");
+
+        let truncated = "Best score in generation 0 was Overloaded\n";
+        assert!(parse_legacy_log(truncated, "log").is_err());
+    }
+}
@@ -0,0 +1,38 @@
+//! Evolving (and otherwise finding) solutions to knapsack problems. The binary in
+//! `main.rs` is the command line interface to all of this.
+
+pub mod analysis;
+pub mod bounded;
+pub mod bounds;
+pub mod checkpoint;
+pub mod cli;
+pub mod cliff_score;
+pub mod cliff_scorer;
+pub mod comparison;
+pub mod convergence;
+pub mod diversity;
+pub mod evolution;
+pub mod experiment;
+pub mod formats;
+pub mod generator;
+pub mod incremental;
+pub mod instance_lines;
+pub mod item;
+pub mod knapsack;
+pub mod legacy;
+pub mod local_search;
+pub mod multi_objective;
+pub mod multidimensional;
+pub mod multiple_choice;
+pub mod multiple_knapsacks;
+pub mod overflow;
+pub mod packed;
+pub mod population_stats;
+pub mod problem;
+pub mod quadratic;
+pub mod reduction;
+pub mod replicates;
+pub mod report;
+pub mod results;
+pub mod statistics;
+pub mod transform;
@@ -0,0 +1,242 @@
+use ec_linear::genome::bitstring::Bitstring;
+use rand::Rng;
+
+use crate::{incremental::IncrementalSolution, knapsack::Knapsack};
+
+/// Make a random small change to `solution`, if `accept` says to.
+///
+/// Two random items are picked; if exactly one of them is chosen, the change is to swap
+/// it for the other one, and otherwise the change is to flip the choice for the first.
+/// `accept` is given the value and weight the solution would have after the change.
+/// This returns whether the change was made.
+fn random_move<R: Rng + ?Sized>(
+    solution: &mut IncrementalSolution,
+    rng: &mut R,
+    accept: impl FnOnce(u64, u64, &mut R) -> bool,
+) -> bool {
+    let first = rng.random_range(0..solution.num_items());
+    let second = rng.random_range(0..solution.num_items());
+    match (solution.is_chosen(first), solution.is_chosen(second)) {
+        (true, false) | (false, true) => {
+            let (removed, added) = if solution.is_chosen(first) {
+                (first, second)
+            } else {
+                (second, first)
+            };
+            let (value, weight) = solution.after_swap(removed, added);
+            let accepted = accept(value, weight, rng);
+            if accepted {
+                solution.swap(removed, added);
+            }
+            accepted
+        }
+        _ => {
+            let (value, weight) = solution.after_flip(first);
+            let accepted = accept(value, weight, rng);
+            if accepted {
+                solution.flip(first);
+            }
+            accepted
+        }
+    }
+}
+
+/// Random-move hill climbing: starting from `start`, repeatedly try a random flip
+/// or swap (see [`IncrementalSolution`]), keeping it if the result still fits and is at
+/// least as valuable. Accepting moves that don't change the value lets the search wander
+/// across plateaus.
+///
+/// `on_improvement` is called with the number of evaluations so far and the solution
+/// each time the best value goes up.
+pub fn hill_climb<'a>(
+    knapsack: &'a Knapsack,
+    start: &Bitstring,
+    max_evaluations: usize,
+    rng: &mut impl Rng,
+    mut on_improvement: impl FnMut(usize, &IncrementalSolution),
+) -> IncrementalSolution<'a> {
+    let capacity = knapsack.capacity();
+    let mut solution = IncrementalSolution::new(knapsack, start);
+    if solution.num_items() == 0 {
+        return solution;
+    }
+    for evaluation in 1..=max_evaluations {
+        let current_value = solution.value();
+        let moved = random_move(&mut solution, rng, |value, weight, _| {
+            weight <= capacity && value >= current_value
+        });
+        if moved && solution.value() > current_value {
+            on_improvement(evaluation, &solution);
+        }
+    }
+    solution
+}
+
+/// The (1+1) EA: a single parent (initially `start`), and each generation a single child
+/// made by flipping each choice with probability `1 / num_items`, which replaces the
+/// parent if it scores at least as well.
+///
+/// The child is made by flipping the parent's choices in place (and flipping them back if
+/// the child is worse), and the positions to flip are found by jumping straight from one
+/// to the next, so each generation takes time proportional to the number of flips rather
+/// than the number of items.
+///
+/// `on_improvement` is called with the number of evaluations so far and the solution
+/// each time the score goes up.
+pub fn one_plus_one<'a>(
+    knapsack: &'a Knapsack,
+    start: &Bitstring,
+    max_evaluations: usize,
+    rng: &mut impl Rng,
+    mut on_improvement: impl FnMut(usize, &IncrementalSolution),
+) -> IncrementalSolution<'a> {
+    let mut parent = IncrementalSolution::new(knapsack, start);
+    let num_items = parent.num_items();
+    if num_items == 0 {
+        return parent;
+    }
+    // The gaps between flipped positions are geometrically distributed, and
+    // `log_keep` is used to sample them by inverting the distribution's CDF.
+    let log_keep = (1.0 - 1.0 / num_items as f64).ln();
+    let mut flipped = Vec::new();
+    for evaluation in 1..=max_evaluations {
+        let parent_score = parent.score();
+        flipped.clear();
+        let mut index = 0_usize;
+        loop {
+            let uniform: f64 = rng.random();
+            let gap = (uniform.ln() / log_keep).floor();
+            // This saturates to `usize::MAX` if the gap is huge (or infinite).
+            index = index.saturating_add(gap as usize);
+            if index >= num_items {
+                break;
+            }
+            parent.flip(index);
+            flipped.push(index);
+            index += 1;
+        }
+        let child_score = parent.score();
+        if child_score < parent_score {
+            for &index in &flipped {
+                parent.flip(index);
+            }
+        } else if child_score > parent_score {
+            on_improvement(evaluation, &parent);
+        }
+    }
+    parent
+}
+
+/// Simulated annealing: starting from `start`, repeatedly try a random flip or
+/// swap, always keeping it if the result fits and is at least as valuable, and keeping it
+/// with probability `exp(change / temperature)` if it fits but is less valuable. The
+/// temperature starts at the mean item value and cools geometrically to 1/1000th of that
+/// by the end of the run. Moves that would overload the knapsack are never taken.
+///
+/// This returns the best solution seen, which isn't necessarily the last one.
+/// `on_improvement` is called with the number of evaluations so far and the solution
+/// each time the best value goes up.
+pub fn anneal<'a>(
+    knapsack: &'a Knapsack,
+    start: &Bitstring,
+    max_evaluations: usize,
+    rng: &mut impl Rng,
+    mut on_improvement: impl FnMut(usize, &IncrementalSolution),
+) -> IncrementalSolution<'a> {
+    const FINAL_TEMPERATURE_RATIO: f64 = 1e-3;
+
+    let capacity = knapsack.capacity();
+    let mut solution = IncrementalSolution::new(knapsack, start);
+    let mut best = solution.clone();
+    if knapsack.num_items() == 0 {
+        return best;
+    }
+    let initial_temperature = (knapsack
+        .items()
+        .iter()
+        .map(|item| item.value() as f64)
+        .sum::<f64>()
+        / knapsack.num_items() as f64)
+        .max(f64::MIN_POSITIVE);
+    let cooling = FINAL_TEMPERATURE_RATIO.powf(1.0 / max_evaluations.max(1) as f64);
+    let mut temperature = initial_temperature;
+    for evaluation in 1..=max_evaluations {
+        let current_value = solution.value();
+        random_move(&mut solution, rng, |value, weight, rng| {
+            weight <= capacity
+                && (value >= current_value
+                    || rng.random_bool(((value as f64 - current_value as f64) / temperature).exp()))
+        });
+        if solution.value() > best.value() {
+            best = solution.clone();
+            on_improvement(evaluation, &best);
+        }
+        temperature *= cooling;
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use ec_linear::genome::bitstring::Bitstring;
+    use rand::SeedableRng;
+    use rand_pcg::Pcg64;
+    use test_case::test_case;
+
+    use super::{anneal, hill_climb, one_plus_one};
+    use crate::{
+        cliff_score::CliffScore, incremental::IncrementalSolution, item::Item, knapsack::Knapsack,
+    };
+
+    type Search = for<'a> fn(
+        &'a Knapsack,
+        &Bitstring,
+        usize,
+        &mut Pcg64,
+        fn(usize, &IncrementalSolution),
+    ) -> IncrementalSolution<'a>;
+
+    fn knapsack() -> Knapsack {
+        Knapsack::new(
+            vec![
+                Item::new(1, 5, 8),
+                Item::new(2, 9, 6),
+                Item::new(3, 2, 7),
+                Item::new(4, 4, 3),
+                Item::new(5, 6, 5),
+            ],
+            15,
+        )
+    }
+
+    #[test_case(hill_climb; "hill climbing")]
+    #[test_case(one_plus_one; "(1+1) EA")]
+    #[test_case(anneal; "simulated annealing")]
+    fn finds_the_optimum(search: Search) {
+        let knapsack = knapsack();
+        let mut rng = Pcg64::seed_from_u64(0);
+        let start = Bitstring::from_iter([false; 5]);
+        let solution = search(&knapsack, &start, 10_000, &mut rng, |_, _| {});
+        // Items 2, 4, and 5 have a total value of 19 and a total weight of 14.
+        assert_eq!(solution.score(), CliffScore::Score(19));
+        let choices = solution.choices();
+        assert_eq!(solution.value(), knapsack.value(&choices));
+        assert_eq!(solution.weight(), knapsack.weight(&choices));
+    }
+
+    #[test_case(hill_climb; "hill climbing")]
+    #[test_case(one_plus_one; "(1+1) EA")]
+    #[test_case(anneal; "simulated annealing")]
+    fn handles_empty_instances(search: Search) {
+        let knapsack = Knapsack::new(Vec::new(), 10);
+        let mut rng = Pcg64::seed_from_u64(0);
+        let solution = search(
+            &knapsack,
+            &Bitstring::from_iter([]),
+            100,
+            &mut rng,
+            |_, _| {},
+        );
+        assert_eq!(solution.score(), CliffScore::Score(0));
+    }
+}
@@ -1,36 +1,109 @@
-mod cliff_score;
-mod cliff_scorer;
-mod item;
-mod knapsack;
-
-use cliff_score::CliffScore;
-use cliff_scorer::CliffScorer;
-use course_helpers::{ec_run::Run, statistics::entropy};
+use anyhow::Context;
+use clap::Parser;
+use course_helpers::ec_run::Run;
 use ec_core::{
-    individual::ec::EcIndividual,
+    individual::{ec::EcIndividual, scorer::Scorer},
     operator::selector::{best::Best, tournament::Tournament, Selector},
 };
 use ec_linear::{
     genome::bitstring::Bitstring, mutator::with_one_over_length::WithOneOverLength,
     recombinator::uniform_xo::UniformXo,
 };
-use knapsack::Knapsack;
-use rand::Rng;
+use knapsack_from_scratch::{
+    analysis::InstanceAnalysis,
+    bounded::{BoundedCliffScorer, BoundedKnapsack, BoundedVariation},
+    checkpoint::{Checkpoint, RunSettings, SavedIndividual},
+    cli::{
+        Algorithm, Cli, Command, ConvergenceArgs, ExperimentArgs, GenerateArgs, ProblemKind,
+        ReplicatesArgs, ReportArgs, TransformArgs,
+    },
+    cliff_score::CliffScore,
+    cliff_scorer::CliffScorer,
+    comparison::Comparison,
+    convergence::{self, ConvergenceCurve},
+    evolution::{BitstringVariation, Evolution, Variation},
+    experiment::{random_seed, Experiment, InstanceSpec, ResolvedConfig, CONFIG_FILE_NAME},
+    formats::{convert, read_knapsack, save_knapsack, save_knapsack_with_header},
+    incremental::IncrementalSolution,
+    knapsack::Knapsack,
+    legacy::import_legacy,
+    local_search,
+    multi_objective::{hypervolume, write_pareto_front, Nsga2},
+    multidimensional::{MultidimensionalCliffScorer, MultidimensionalKnapsack},
+    multiple_choice::{MultipleChoiceCliffScorer, MultipleChoiceKnapsack},
+    multiple_knapsacks::{AssignmentVariation, MultipleKnapsacks, MultipleKnapsacksCliffScorer},
+    problem::BitstringScorer,
+    quadratic::{QuadraticCliffScorer, QuadraticKnapsack},
+    reduction::Reduction,
+    replicates::{self, Replicate},
+    report,
+    results::{GenerationRecord, ResultsWriter, RunRecord},
+};
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64;
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    fs::File,
+    io::{self, BufWriter},
+    num::NonZeroUsize,
+    path::Path,
+    sync::Arc,
+    thread,
+};
 
 fn report_on_generation(
+    knapsack: Option<&Knapsack>,
     generation_number: usize,
     population: &Vec<EcIndividual<Bitstring, CliffScore>>,
     best_in_run: &mut Option<EcIndividual<Bitstring, CliffScore>>,
+    results: Option<&mut ResultsWriter>,
+    include_locus_frequencies: bool,
     rng: &mut impl Rng,
-) {
+) -> anyhow::Result<()> {
     // Get the best individual in the population and print out its score.
     let best = Best.select(population, rng).unwrap();
     println!(
         "Best score in generation {generation_number} was {:?}",
         best.test_results
     );
-    // Calculate the entropy of the population and print it out.
-    println!("\tEntropy of the population was {}", entropy(population));
+    // Work out the entropy, diversity, and (for single capacity knapsacks) a summary of the
+    // values and weights over the whole population, and print those out.
+    let record = GenerationRecord::of_population(
+        generation_number,
+        population,
+        knapsack,
+        include_locus_frequencies,
+    );
+    println!("\tEntropy of the population was {}", record.entropy);
+    if let Some(diversity) = &record.diversity {
+        println!(
+            "\tMean pairwise Hamming distance was {:.2}, with {} unique genomes and {} distinct scores",
+            diversity.mean_hamming_distance, diversity.unique_genomes, diversity.distinct_fitnesses
+        );
+    }
+    if let Some(stats) = &record.population {
+        println!(
+            "\t{:.1}% of the population was overloaded; mean capacity utilization was {}",
+            stats.overloaded_fraction * 100.0,
+            stats.mean_capacity_utilization.map_or_else(
+                || "undefined (the capacity is 0)".to_string(),
+                |utilization| format!("{utilization:.3}")
+            )
+        );
+        println!(
+            "\tValue: mean {:.1}, median {:.1}, min {}, max {}",
+            stats.value.mean, stats.value.median, stats.value.min, stats.value.max
+        );
+        println!(
+            "\tWeight: mean {:.1}, median {:.1}, min {}, max {}",
+            stats.weight.mean, stats.weight.median, stats.weight.min, stats.weight.max
+        );
+    }
+    // If we're collecting structured results, add a record for this generation.
+    if let Some(results) = results {
+        results.write(&RunRecord::Generation(record))?;
+    }
     // If the best individual in this generation is better than the best in the run so far,
     // update the best in the run.
     match best_in_run {
@@ -41,27 +114,795 @@ fn report_on_generation(
         // If there is a best in the run so far, and the best in this generation is not better, do nothing.
         _ => (),
     }
+    Ok(())
+}
+
+/// Run our own implementation of the genetic algorithm (see [`Evolution`]), saving
+/// a [`Checkpoint`] to `checkpoint_dir` every `--checkpoint-every` generations so the
+/// run can be resumed (with `--resume`) if it's interrupted. A resumed run produces
+/// exactly the same results as one that was never interrupted.
+///
+/// This returns the best individual in the run.
+fn run_with_checkpoints(
+    cli: &Cli,
+    checkpoint_dir: &Path,
+    scorer: BitstringScorer,
+    knapsack: Option<&Knapsack>,
+    settings: &RunSettings,
+) -> anyhow::Result<Option<EcIndividual<Bitstring, CliffScore>>> {
+    let bit_length = scorer.num_items();
+    let evolution = Evolution::new(
+        scorer,
+        BitstringVariation::new(bit_length),
+        settings.population_size,
+        settings.tournament_size,
+    );
+    // This is only used to select the best individual when reporting, so it doesn't
+    // need to be part of the checkpoint.
+    let mut report_rng = rand::rng();
+
+    let (mut generation, mut population, mut best_in_run, mut rng, mut results);
+    if cli.resume {
+        let checkpoint = Checkpoint::load(checkpoint_dir)?;
+        anyhow::ensure!(
+            &checkpoint.settings == settings,
+            "The checkpoint in {checkpoint_dir:?} was made with different settings ({:?}) than this run ({settings:?})",
+            checkpoint.settings
+        );
+        println!(
+            "Resuming from the checkpoint after generation {}",
+            checkpoint.generation
+        );
+        results = cli
+            .results
+            .as_ref()
+            .map(|path| ResultsWriter::resume(path, checkpoint.generation))
+            .transpose()?;
+        generation = checkpoint.generation;
+        population = checkpoint.population();
+        best_in_run = checkpoint.best_in_run();
+        rng = checkpoint.rng;
+    } else {
+        let seed = cli.seed.unwrap_or_else(|| rand::rng().random());
+        println!("Running with seed: {seed}");
+        results = cli
+            .results
+            .as_ref()
+            .map(ResultsWriter::create)
+            .transpose()?;
+        rng = Pcg64::seed_from_u64(seed);
+        generation = 0;
+        population = evolution.initial_population(&mut rng);
+        best_in_run = None;
+        report_on_generation(
+            knapsack,
+            generation,
+            &population,
+            &mut best_in_run,
+            results.as_mut(),
+            cli.locus_frequencies,
+            &mut report_rng,
+        )?;
+    }
+
+    while generation < settings.max_generations {
+        population = evolution.next_generation(&population, &mut rng);
+        generation += 1;
+        report_on_generation(
+            knapsack,
+            generation,
+            &population,
+            &mut best_in_run,
+            results.as_mut(),
+            cli.locus_frequencies,
+            &mut report_rng,
+        )?;
+        if (generation as u64).is_multiple_of(cli.checkpoint_every)
+            || generation == settings.max_generations
+        {
+            // Make sure everything reported so far is on disk before the checkpoint
+            // says it is, so a resumed run doesn't lose any records.
+            if let Some(results) = results.as_mut() {
+                results.flush()?;
+            }
+            Checkpoint {
+                settings: settings.clone(),
+                generation,
+                population: population.iter().map(SavedIndividual::from).collect(),
+                best_in_run: best_in_run.as_ref().map(SavedIndividual::from),
+                rng: rng.clone(),
+            }
+            .save(checkpoint_dir)?;
+        }
+    }
+    let best = Best.select(&population, &mut report_rng)?;
+    if let Some(mut results) = results {
+        results.write_best(best, best_in_run.as_ref())?;
+        results.finish()?;
+    }
+
+    println!("Best in final generation {best:?}");
+    println!("Best in overall run: {best_in_run:?}");
+    Ok(best_in_run)
+}
+
+/// Print the items that are actually chosen by the best individual in a multiple-choice
+/// run, i.e., after its genome has been repaired to satisfy the class constraint.
+fn report_multiple_choice(
+    knapsack: &MultipleChoiceKnapsack,
+    best_in_run: Option<&EcIndividual<Bitstring, CliffScore>>,
+) {
+    if let Some(best) = best_in_run {
+        let repaired = knapsack.repair(&best.genome);
+        debug_assert!(knapsack.satisfies_classes(&repaired));
+        println!(
+            "Items chosen by the best in the run (after repair): {:?}",
+            knapsack.chosen_ids(&repaired)
+        );
+    }
+}
+
+/// Show how much the best individual in a quadratic run can be improved by
+/// flipping single bits.
+fn report_quadratic(
+    knapsack: &QuadraticKnapsack,
+    best_in_run: Option<&EcIndividual<Bitstring, CliffScore>>,
+) {
+    if let Some(best) = best_in_run {
+        let climbed = knapsack.hill_climb(&best.genome);
+        println!(
+            "Hill climbing from the best in the run reached value {} (weight {} of {}) with {:?}",
+            climbed.value(),
+            climbed.weight(),
+            knapsack.capacity(),
+            climbed.choices()
+        );
+    }
+}
+
+/// Show what `choices` for the reduced instance in `reduction` mean for the original.
+fn report_reduction(reduction: &Reduction, choices: &Bitstring) {
+    let (value, weight) = reduction.reduced().value_and_weight(choices);
+    if weight <= reduction.reduced().capacity() {
+        println!(
+            "In the original instance, that's a value of {} with items {:?}: {:?}",
+            reduction.original_value(value),
+            reduction.original_ids(choices),
+            reduction.original_choices(choices)
+        );
+    }
+}
+
+/// Search for a solution with one of the single-solution algorithms in [`local_search`],
+/// giving it `max_evaluations` evaluations.
+fn run_local_search(
+    cli: &Cli,
+    knapsack: &Knapsack,
+    reduction: Option<&Reduction>,
+    max_evaluations: usize,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        cli.results.is_none() && cli.checkpoint_dir.is_none(),
+        "Structured results and checkpoints are only supported for the genetic algorithm"
+    );
+    let seed = cli.seed.unwrap_or_else(|| rand::rng().random());
+    println!("Running with seed: {seed}");
+    let mut rng = Pcg64::seed_from_u64(seed);
+    let report = |evaluation: usize, solution: &IncrementalSolution| {
+        println!(
+            "Best score after {evaluation} evaluations was {:?}",
+            solution.score()
+        );
+    };
+    // All the searches start from the empty knapsack, which always fits.
+    let start = Bitstring::from_iter(vec![false; knapsack.num_items()]);
+    let best = match cli.algorithm {
+        Algorithm::HillClimb => {
+            local_search::hill_climb(knapsack, &start, max_evaluations, &mut rng, report)
+        }
+        Algorithm::OnePlusOne => {
+            local_search::one_plus_one(knapsack, &start, max_evaluations, &mut rng, report)
+        }
+        Algorithm::Annealing => {
+            local_search::anneal(knapsack, &start, max_evaluations, &mut rng, report)
+        }
+        Algorithm::Ga => unreachable!("The genetic algorithm isn't a local search"),
+    };
+    let choices = best.choices();
+    // The cached totals should always agree with adding everything up from scratch.
+    debug_assert_eq!(best.value(), knapsack.value(&choices));
+    debug_assert_eq!(best.weight(), knapsack.weight(&choices));
+    println!(
+        "Best in run: {:?} with weight {} of {}: {choices:?}",
+        best.score(),
+        best.weight(),
+        knapsack.capacity()
+    );
+    if let Some(reduction) = reduction {
+        report_reduction(reduction, &choices);
+    }
+    Ok(())
+}
+
+/// Evolve solutions with our own implementation of the genetic algorithm (see
+/// [`Evolution`]), for problems whose genomes aren't `Bitstring`s, which is all `Run`
+/// works with. This prints the best score in each generation, and returns the best
+/// individual in the run.
+fn run_evolution<Sc, V, G>(
+    cli: &Cli,
+    evolution: &Evolution<Sc, V, G>,
+    max_generations: usize,
+) -> anyhow::Result<EcIndividual<G, CliffScore>>
+where
+    Sc: Scorer<G, Score = CliffScore> + Sync,
+    V: Variation<G>,
+    G: Send + Clone + Debug,
+{
+    anyhow::ensure!(
+        cli.results.is_none() && cli.checkpoint_dir.is_none(),
+        "Structured results and checkpoints are only supported for 0/1 problems"
+    );
+    let seed = cli.seed.unwrap_or_else(|| rand::rng().random());
+    println!("Running with seed: {seed}");
+    let mut rng = Pcg64::seed_from_u64(seed);
+
+    let mut population = evolution.initial_population(&mut rng);
+    let mut best_in_run: Option<EcIndividual<G, CliffScore>> = None;
+    for generation in 0..=max_generations {
+        if generation > 0 {
+            population = evolution.next_generation(&population, &mut rng);
+        }
+        let best = population
+            .iter()
+            .max_by_key(|individual| individual.test_results)
+            .context("The population was empty")?;
+        println!(
+            "Best score in generation {generation} was {:?}",
+            best.test_results
+        );
+        if best_in_run
+            .as_ref()
+            .is_none_or(|b| best.test_results > b.test_results)
+        {
+            best_in_run = Some(best.clone());
+        }
+    }
+
+    let best = population
+        .iter()
+        .max_by_key(|individual| individual.test_results)
+        .context("The population was empty")?;
+    println!("Best in final generation {best:?}");
+    println!("Best in overall run: {best_in_run:?}");
+    best_in_run.context("The run didn't have any generations")
+}
+
+/// Evolve solutions to a bounded (or unbounded) knapsack problem, where the genomes
+/// are the number of copies of each item.
+fn run_bounded(
+    cli: &Cli,
+    knapsack: BoundedKnapsack,
+    population_size: usize,
+    max_generations: usize,
+    tournament_size: usize,
+) -> anyhow::Result<()> {
+    let knapsack = Arc::new(knapsack);
+    let evolution = Evolution::new(
+        BoundedCliffScorer::new(Arc::clone(&knapsack)),
+        BoundedVariation::new(Arc::clone(&knapsack)),
+        population_size,
+        tournament_size,
+    );
+    run_evolution(cli, &evolution, max_generations)?;
+    if cli.verify {
+        let (optimum, counts) = knapsack.solve_exactly()?;
+        println!("Optimal value (from dynamic programming) is {optimum}, with counts {counts:?}");
+    }
+    Ok(())
+}
+
+/// Evolve assignments of items to several knapsacks, where the genomes give the
+/// bin each item is in (or 0 if it isn't in any).
+fn run_multiple_knapsacks(
+    cli: &Cli,
+    knapsacks: MultipleKnapsacks,
+    population_size: usize,
+    max_generations: usize,
+    tournament_size: usize,
+) -> anyhow::Result<()> {
+    let knapsacks = Arc::new(knapsacks);
+    let evolution = Evolution::new(
+        MultipleKnapsacksCliffScorer::new(Arc::clone(&knapsacks)),
+        AssignmentVariation::new(Arc::clone(&knapsacks)),
+        population_size,
+        tournament_size,
+    );
+    let best = run_evolution(cli, &evolution, max_generations)?;
+    if cli.verify {
+        knapsacks.verify(&best.genome)?;
+        println!(
+            "Verified the best in the run: loads {:?} are within capacities {:?}",
+            knapsacks.loads(&best.genome),
+            knapsacks.capacities()
+        );
+    }
+    Ok(())
+}
+
+/// Evolve a set of trade-offs between value and weight with NSGA-II, reporting the size
+/// and hypervolume of the Pareto front in each generation.
+fn run_multi_objective(
+    cli: &Cli,
+    knapsack: Knapsack,
+    population_size: usize,
+    max_generations: usize,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        cli.results.is_none() && cli.checkpoint_dir.is_none(),
+        "Structured results and checkpoints are only supported for single objective problems"
+    );
+    let knapsack = Arc::new(knapsack);
+    let nsga2 = Nsga2::new(Arc::clone(&knapsack), population_size);
+    let seed = cli.seed.unwrap_or_else(|| rand::rng().random());
+    println!("Running with seed: {seed}");
+    let mut rng = Pcg64::seed_from_u64(seed);
+
+    let mut population = nsga2.initial_population(&mut rng);
+    for generation in 0..=max_generations {
+        if generation > 0 {
+            population = nsga2.next_generation(population, &mut rng);
+        }
+        let front = population
+            .pareto_front()
+            .iter()
+            .map(|individual| individual.test_results)
+            .collect::<Vec<_>>();
+        println!(
+            "Pareto front in generation {generation} had {} solutions, with hypervolume {}",
+            front.len(),
+            hypervolume(&front, knapsack.capacity())
+        );
+    }
+
+    let front = population.pareto_front();
+    if let Some(path) = &cli.pareto_front {
+        write_pareto_front(path, &knapsack, &front)?;
+        println!("Wrote the final Pareto front to {path:?}");
+    } else {
+        for individual in front {
+            println!(
+                "Value {}, weight {}",
+                individual.test_results.value, individual.test_results.weight
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Generate an instance in one of Pisinger's classes and save it. The seed is printed
+/// to standard error, since the instance itself may be going to standard output.
+fn generate(args: &GenerateArgs) -> anyhow::Result<()> {
+    let seed = args.seed.unwrap_or_else(|| rand::rng().random());
+    eprintln!("Generating with seed: {seed}");
+    let knapsack = args.generator.generate(&mut Pcg64::seed_from_u64(seed))?;
+    let name = args
+        .output
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .filter(|&stem| stem != "-")
+        .unwrap_or("generated");
+    save_knapsack(&knapsack, name, &args.output, args.format)
+}
+
+/// Derive a new instance from an existing one, and save it with a header saying where it
+/// came from and how, so it can be made again.
+fn transform(args: &TransformArgs) -> anyhow::Result<()> {
+    let seed = args.seed.unwrap_or_else(|| rand::rng().random());
+    let knapsack = read_knapsack(&args.input, args.from, args.instance)?;
+    let original_items = knapsack.num_items();
+    let knapsack = args
+        .transformation
+        .apply(knapsack, &mut Pcg64::seed_from_u64(seed))?;
+
+    let mut header = vec![format!(
+        "Derived from instance {} of {} ({original_items} items) with seed {seed}:",
+        args.instance,
+        args.input.display()
+    )];
+    header.extend(
+        args.transformation
+            .describe()
+            .into_iter()
+            .map(|step| format!("  - {step}")),
+    );
+    save_knapsack_with_header(&knapsack, &header, &args.output)
+}
+
+/// Write the convergence curves of the groups of runs in `args` to CSV, and SVG if
+/// that was asked for.
+fn export_convergence(args: &ConvergenceArgs) -> anyhow::Result<()> {
+    let curves = args
+        .groups
+        .iter()
+        .map(ConvergenceCurve::read)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let create = |path: &Path| {
+        File::create(path)
+            .map(BufWriter::new)
+            .with_context(|| format!("Failed to create the file {path:?}"))
+    };
+    if args.csv == Path::new("-") {
+        convergence::write_csv(&curves, io::stdout().lock())?;
+    } else {
+        convergence::write_csv(&curves, create(&args.csv)?)?;
+    }
+    if let Some(svg) = &args.svg {
+        convergence::write_svg(&curves, &args.title, create(svg)?)?;
+    }
+    Ok(())
+}
+
+fn write_report(args: &ReportArgs) -> anyhow::Result<()> {
+    let mut instances = HashMap::new();
+    for (name, path) in &args.instances {
+        instances.insert(name.clone(), read_knapsack(path, None, 0)?);
+    }
+    for (instance, groups) in report::find_groups(&args.experiment)? {
+        if instances.contains_key(&instance) {
+            continue;
+        }
+        // Runs from `experiment` and `replicates` say which instance they were run on;
+        // other runs (like those imported from `Outputs/`) don't have a configuration.
+        let config = groups
+            .iter()
+            .find(|group| group.path.join(CONFIG_FILE_NAME).is_file())
+            .map(|group| ResolvedConfig::load(&group.path))
+            .transpose()?;
+        let path = args.knapsacks.join(format!("{instance}.txt"));
+        if let Some(InstanceSpec {
+            path,
+            format,
+            instance: index,
+            ..
+        }) = config.map(|config| config.instance)
+        {
+            instances.insert(instance, read_knapsack(&path, format, index)?);
+        } else if path.is_file() {
+            instances.insert(instance, read_knapsack(&path, None, 0)?);
+        }
+    }
+    if args.output == Path::new("-") {
+        report::write_report(&args.experiment, &instances, io::stdout().lock())
+    } else {
+        let file = File::create(&args.output)
+            .with_context(|| format!("Failed to create the file {:?}", args.output))?;
+        report::write_report(&args.experiment, &instances, BufWriter::new(file))
+    }
+}
+
+/// Run all the replicates of `config` with `threads` threads, after saving `config`
+/// alongside where their results go.
+fn run_configuration(config: &ResolvedConfig, threads: usize) -> anyhow::Result<()> {
+    let instance = &config.instance;
+    let knapsack = Arc::new(read_knapsack(
+        &instance.path,
+        instance.format,
+        instance.instance,
+    )?);
+    let settings = config.settings();
+    let variation = config.variation(knapsack.num_items());
+    config.save()?;
+    let replicates = Replicate::plan(config.replicates, config.seed, &config.output_dir);
+    println!(
+        "Running {} runs of {} on {:?} with {threads} threads, and seed {}",
+        replicates.len(),
+        config.configuration,
+        instance.path,
+        config.seed
+    );
+    let bests = replicates::run_all(
+        &replicates,
+        threads,
+        |replicate| replicates::run_replicate(&knapsack, variation, &settings, replicate),
+        |replicate, best, finished| {
+            println!(
+                "[{finished}/{}] Run {} (seed {}) finished with best {best:?}; {} remaining",
+                replicates.len(),
+                replicate.number,
+                replicate.seed,
+                replicates.len() - finished
+            );
+        },
+    )?;
+    let summary = config.output_dir.join("replicates.csv");
+    replicates::write_summary(&summary, &replicates, &bests)?;
+    println!("Wrote a summary of the runs to {summary:?}");
+    Ok(())
+}
+
+/// The number of threads to use: `threads` if it's given, and otherwise the number of
+/// cores.
+fn thread_count(threads: Option<u64>) -> usize {
+    threads.map_or_else(
+        || thread::available_parallelism().map_or(1, NonZeroUsize::get),
+        |threads| threads as usize,
+    )
+}
+
+fn run_replicates(args: &ReplicatesArgs) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        args.tournament_size > 0,
+        "The tournament size must be at least 1"
+    );
+    anyhow::ensure!(
+        args.population_size > 0,
+        "The population size must be at least 1"
+    );
+    anyhow::ensure!(
+        args.mutation_flips.is_finite() && args.mutation_flips >= 0.0,
+        "The number of mutation flips must be a number that's at least 0"
+    );
+    let configuration = args.output_dir.file_name().map_or_else(
+        || format!("ts_{}", args.tournament_size),
+        |name| name.to_string_lossy().into_owned(),
+    );
+    let config = ResolvedConfig {
+        configuration,
+        output_dir: args.output_dir.clone(),
+        replicates: args.runs,
+        seed: args.seed.unwrap_or_else(|| random_seed(&mut rand::rng())),
+        crossover: args.crossover,
+        tournament_size: args.tournament_size,
+        population_size: args.population_size,
+        max_generations: args.max_generations,
+        mutation_flips: args.mutation_flips,
+        instance: InstanceSpec {
+            name: None,
+            path: args.knapsack.clone(),
+            format: args.format,
+            instance: args.instance,
+        },
+    };
+    run_configuration(&config, thread_count(args.threads))
+}
+
+fn run_experiment(args: &ExperimentArgs) -> anyhow::Result<()> {
+    let experiment = Experiment::load(&args.config)?;
+    let seed = experiment
+        .seed
+        .unwrap_or_else(|| random_seed(&mut rand::rng()));
+    let configs = experiment.resolve(seed)?;
+    println!(
+        "Running {} configurations from {:?}, with seed {seed}",
+        configs.len(),
+        args.config
+    );
+    let threads = thread_count(args.threads);
+    for config in &configs {
+        run_configuration(config, threads)?;
+    }
+    Ok(())
 }
 
 fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    if let Some(command) = &cli.command {
+        return match command {
+            Command::Convert(args) => {
+                convert(&args.input, args.from, args.instance, &args.output, args.to)
+            }
+            Command::Analyze(args) => {
+                let knapsack = read_knapsack(&args.input, args.format, args.instance)?;
+                print!("{}", InstanceAnalysis::of(&knapsack));
+                Ok(())
+            }
+            Command::Generate(args) => generate(args),
+            Command::Transform(args) => transform(args),
+            Command::Import(args) => {
+                let count = import_legacy(&args.input, &args.output)?;
+                println!("Imported {count} runs into {:?}", args.output);
+                Ok(())
+            }
+            Command::Convergence(args) => export_convergence(args),
+            Command::Report(args) => write_report(args),
+            Command::Replicates(args) => run_replicates(args),
+            Command::Experiment(args) => run_experiment(args),
+            Command::Compare(args) => {
+                print!("{}", Comparison::read(&args.groups)?.report());
+                Ok(())
+            }
+        };
+    }
     let mut rng = rand::rng();
     const TOURNAMENT_SIZE: usize = 8; // edit tournament size here
-    let file_path = "knapsacks/SmallProblem4.txt"; // edit knapsack here
-    let knapsack = Knapsack::from_file_path(file_path)?;
-
-    let mut best_in_run = None;
+    const MAX_GENERATIONS: usize = 1_000;
+    const POPULATION_SIZE: usize = 1_000;
+    let file_path = &cli.knapsack;
+    // For single capacity knapsacks, this is shared between the scorer and the
+    // inspector, which both need it.
+    let mut knapsack = None;
+    // Likewise, we hold on to multiple-choice knapsacks so we can show which items
+    // the best individual chooses once its genome is repaired.
+    let mut multiple_choice = None;
+    let mut quadratic = None;
+    let mut reduction = None;
+    anyhow::ensure!(
+        cli.algorithm == Algorithm::Ga || cli.problem == ProblemKind::Knapsack,
+        "{:?} only works with 0/1 knapsacks",
+        cli.algorithm
+    );
+    let scorer = match cli.problem {
+        ProblemKind::Knapsack => {
+            let mut instance = read_knapsack(file_path, cli.format, cli.instance)?;
+            if cli.reduce {
+                let reduced = Reduction::of(&instance);
+                println!(
+                    "Reduced the instance from {} to {} items: {} are always taken, and {} never are",
+                    instance.num_items(),
+                    reduced.reduced().num_items(),
+                    reduced.num_taken(),
+                    reduced.num_left_out()
+                );
+                if reduced.is_solved() {
+                    report_reduction(&reduced, &Bitstring::from_iter([]));
+                    return Ok(());
+                }
+                instance = reduced.reduced().clone();
+                reduction = Some(reduced);
+            }
+            let instance = Arc::new(instance);
+            knapsack = Some(Arc::clone(&instance));
+            BitstringScorer::Knapsack(CliffScorer::new(instance))
+        }
+        ProblemKind::Multidimensional => {
+            let instance = MultidimensionalKnapsack::from_file_path(file_path, cli.instance)?;
+            BitstringScorer::Multidimensional(MultidimensionalCliffScorer::new(instance))
+        }
+        ProblemKind::MultipleChoice => {
+            let instance = Arc::new(MultipleChoiceKnapsack::from_file_path(
+                file_path,
+                cli.class_constraint,
+            )?);
+            multiple_choice = Some(Arc::clone(&instance));
+            BitstringScorer::MultipleChoice(MultipleChoiceCliffScorer::new(instance))
+        }
+        ProblemKind::Quadratic => {
+            let instance = Arc::new(QuadraticKnapsack::from_file_path(file_path)?);
+            quadratic = Some(Arc::clone(&instance));
+            BitstringScorer::Quadratic(QuadraticCliffScorer::new(instance))
+        }
+        ProblemKind::Bounded | ProblemKind::Unbounded => {
+            let instance = if cli.problem == ProblemKind::Bounded {
+                BoundedKnapsack::from_file_path(file_path)?
+            } else {
+                BoundedKnapsack::unbounded(&read_knapsack(file_path, cli.format, cli.instance)?)?
+            };
+            println!("Running on knapsack at: {file_path:?}");
+            println!("Running with tournament size: {TOURNAMENT_SIZE:?}");
+            return run_bounded(
+                &cli,
+                instance,
+                POPULATION_SIZE,
+                MAX_GENERATIONS,
+                TOURNAMENT_SIZE,
+            );
+        }
+        ProblemKind::MultipleKnapsacks => {
+            let instance = MultipleKnapsacks::from_file_path(file_path)?;
+            println!("Running on knapsack at: {file_path:?}");
+            println!("Running with tournament size: {TOURNAMENT_SIZE:?}");
+            println!("Running with bin capacities {:?}", instance.capacities());
+            return run_multiple_knapsacks(
+                &cli,
+                instance,
+                POPULATION_SIZE,
+                MAX_GENERATIONS,
+                TOURNAMENT_SIZE,
+            );
+        }
+        ProblemKind::MultiObjective => {
+            let instance = read_knapsack(file_path, cli.format, cli.instance)?;
+            println!("Running on knapsack at: {file_path:?}");
+            return run_multi_objective(&cli, instance, POPULATION_SIZE, MAX_GENERATIONS);
+        }
+    };
+    let knapsack = knapsack.as_deref();
+    if let Some(best_known) = knapsack.and_then(Knapsack::best_known) {
+        println!("The best known value for this instance is {best_known}");
+    }
+    if cli.algorithm != Algorithm::Ga {
+        let Some(knapsack) = knapsack else {
+            unreachable!("We checked above that this is a 0/1 knapsack");
+        };
+        println!("Running on knapsack at: {file_path:?}");
+        println!("Running with algorithm: {:?}", cli.algorithm);
+        return run_local_search(
+            &cli,
+            knapsack,
+            reduction.as_ref(),
+            MAX_GENERATIONS * POPULATION_SIZE,
+        );
+    }
 
     println!("Running on knapsack at: {file_path:?}");
     println!("Running with tournament size: {TOURNAMENT_SIZE:?}");
+    if let BitstringScorer::Multidimensional(scorer) = &scorer {
+        let instance = scorer.knapsack();
+        println!(
+            "Running on instance {} with capacities {:?}; the best known value is {:?}",
+            cli.instance,
+            instance.capacities(),
+            instance.best_known()
+        );
+    }
+    if let Some(instance) = &multiple_choice {
+        println!(
+            "Running with {} classes, choosing {:?} from each",
+            instance.num_classes(),
+            cli.class_constraint
+        );
+    }
+    if let Some(instance) = &quadratic {
+        println!(
+            "Running on quadratic instance {:?} with capacity {}",
+            instance.name(),
+            instance.capacity()
+        );
+    }
+    // Anything extra to say about the best individual at the end of the run.
+    let report_best = |best_in_run: Option<&EcIndividual<Bitstring, CliffScore>>| {
+        if let Some(instance) = &multiple_choice {
+            report_multiple_choice(instance, best_in_run);
+        }
+        if let Some(instance) = &quadratic {
+            report_quadratic(instance, best_in_run);
+        }
+        if let (Some(reduction), Some(best)) = (&reduction, best_in_run) {
+            report_reduction(reduction, &best.genome);
+        }
+    };
+
+    if let Some(checkpoint_dir) = &cli.checkpoint_dir {
+        let settings = RunSettings {
+            knapsack: file_path.clone(),
+            problem: cli.problem,
+            instance: cli.instance,
+            format: cli.format,
+            reduce: cli.reduce,
+            tournament_size: TOURNAMENT_SIZE,
+            population_size: POPULATION_SIZE,
+            max_generations: MAX_GENERATIONS,
+        };
+        let best_in_run = run_with_checkpoints(&cli, checkpoint_dir, scorer, knapsack, &settings)?;
+        report_best(best_in_run.as_ref());
+        return Ok(());
+    }
+
+    // `Run` can't be given a seed, so we can't honour `--seed` here.
+    anyhow::ensure!(
+        cli.seed.is_none(),
+        "--seed needs --checkpoint-dir for {:?} problems, since the default genetic algorithm can't be seeded",
+        cli.problem
+    );
+
+    let mut best_in_run = None;
+    let mut results = cli
+        .results
+        .as_ref()
+        .map(ResultsWriter::create)
+        .transpose()?;
+    // The inspector can't return an error, so we hold on to the first one
+    // (if any) and return it once the run is over.
+    let mut report_error = None;
 
     let run = Run::builder()
         // The number of bits should equal the number of items.
-        .bit_length(knapsack.num_items())
+        .bit_length(scorer.num_items())
         // The maximum number of generations to run; this is somewhat arbitrary
-        .max_generations(1_000)
+        .max_generations(MAX_GENERATIONS)
         // The population size, which is also somewhat arbitrary, but larger is better
         // until it's so big that memory management becomes a problem.
-        .population_size(1_000)
+        .population_size(POPULATION_SIZE)
         // How do we want to select parent individuals? This takes two individuals at
         // random from the population, and then chooses the better of the two from this
         // tournament. You can change this to larger tournaments by changing `2` to your
@@ -79,26 +920,44 @@ fn main() -> anyhow::Result<()> {
         // up the process considerably, at the cost of heating up your CPU.
         .parallel_evaluation(true)
         // How do we want to score different knapsack "solutions"? This is the only
-        // problem dependent part of building the run. We'll start with a simple scorer
-        // that returns a `CliffScore`. This is an `enum` with two variants: `Score(v)`
-        // where `v` is the value of the items if they fit in the knapsack
-        // and `Overloaded` otherwise.  This is implemented so that `Overloaded` is
-        // always worse than any `Score(v)` value.
-        .scorer(CliffScorer::new(knapsack))
+        // problem dependent part of building the run. We use a simple scorer (chosen
+        // above, depending on the kind of problem) that returns a `CliffScore`. This is
+        // an `enum` with two variants: `Score(v)` where `v` is the value of the items if
+        // they fit in the knapsack and `Overloaded` otherwise.  This is implemented so
+        // that `Overloaded` is always worse than any `Score(v)` value.
+        .scorer(scorer)
         // Add an inspector. This is a function that is called after each generation
         // and can be used to collect and/or print out information about the run. We'll use this to
         // print out the best score in each generation, and to keep track of the best score in the run.
         .inspector(|generation_number, population| {
-            report_on_generation(generation_number, population, &mut best_in_run, &mut rng);
+            if let Err(error) = report_on_generation(
+                knapsack,
+                generation_number,
+                population,
+                &mut best_in_run,
+                results.as_mut(),
+                cli.locus_frequencies,
+                &mut rng,
+            ) {
+                report_error.get_or_insert(error);
+            }
         })
         // Now that we've specified all the elements, we can build the run.
         .build();
 
     let final_population = run.execute()?;
-
+    if let Some(error) = report_error {
+        return Err(error);
+    }
     let best = Best.select(&final_population, &mut rng)?;
+    if let Some(mut results) = results {
+        results.write_best(best, best_in_run.as_ref())?;
+        results.finish()?;
+    }
+
     println!("Best in final generation {best:?}");
     println!("Best in overall run: {best_in_run:?}");
+    report_best(best_in_run.as_ref());
 
     // The returns the unit type `()` wrapped in the `Ok` variant of
     // `Result`. The lack of a semicolon (`;`) at the end of the line
@@ -0,0 +1,495 @@
+use std::{
+    cmp::Ordering,
+    collections::HashSet,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    sync::Arc,
+};
+
+use anyhow::Context;
+use ec_core::individual::{ec::EcIndividual, scorer::Scorer};
+use ec_linear::genome::bitstring::Bitstring;
+use rand::Rng;
+use rayon::prelude::*;
+
+use crate::{
+    evolution::{BitstringVariation, Variation},
+    knapsack::Knapsack,
+};
+
+/// The objectives for treating a knapsack as a multi-objective problem: we want to
+/// _maximize_ the value while _minimizing_ the weight, instead of just maximizing the
+/// value subject to the capacity.
+///
+/// Sets of choices that are over the capacity are still infeasible; `overload` is how
+/// far over the capacity they are (and is 0 for feasible choices).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Objectives {
+    pub value: u64,
+    pub weight: u64,
+    pub overload: u64,
+}
+
+impl Objectives {
+    /// Is this set of choices within the capacity?
+    #[must_use]
+    pub const fn is_feasible(&self) -> bool {
+        self.overload == 0
+    }
+
+    /// Does `self` (constrained-)dominate `other`?
+    ///
+    /// - A feasible solution dominates any infeasible one.
+    /// - An infeasible solution dominates another if it's less overloaded.
+    /// - A feasible solution dominates another if it's at least as valuable and no heavier,
+    ///   and is strictly better in at least one of those.
+    #[must_use]
+    pub const fn dominates(&self, other: &Self) -> bool {
+        match (self.is_feasible(), other.is_feasible()) {
+            (true, false) => true,
+            (false, true) => false,
+            (false, false) => self.overload < other.overload,
+            (true, true) => {
+                self.value >= other.value
+                    && self.weight <= other.weight
+                    && (self.value > other.value || self.weight < other.weight)
+            }
+        }
+    }
+}
+
+/// A scorer that computes the [`Objectives`] of a set of choices.
+pub struct MultiObjectiveScorer {
+    knapsack: Arc<Knapsack>,
+}
+
+impl MultiObjectiveScorer {
+    #[must_use]
+    pub fn new(knapsack: impl Into<Arc<Knapsack>>) -> Self {
+        Self {
+            knapsack: knapsack.into(),
+        }
+    }
+}
+
+impl Scorer<Bitstring> for MultiObjectiveScorer {
+    type Score = Objectives;
+
+    fn score(&self, genome: &Bitstring) -> Self::Score {
+        let weight = self.knapsack.weight(genome);
+        Objectives {
+            value: self.knapsack.value(genome),
+            weight,
+            overload: weight.saturating_sub(self.knapsack.capacity()),
+        }
+    }
+}
+
+/// Sort the given objectives into non-dominated fronts, using the "fast non-dominated
+/// sort" from NSGA-II.
+///
+/// The first front has (the indices of) everything that isn't dominated by anything
+/// else; the second has everything that's only dominated by things in the first front;
+/// and so on.
+#[must_use]
+pub fn non_dominated_fronts(objectives: &[Objectives]) -> Vec<Vec<usize>> {
+    // For each solution, the solutions it dominates and how many solutions dominate it.
+    let mut dominated = vec![Vec::new(); objectives.len()];
+    let mut num_dominating = vec![0_usize; objectives.len()];
+    for (i, first) in objectives.iter().enumerate() {
+        for (j, second) in objectives.iter().enumerate().skip(i + 1) {
+            if first.dominates(second) {
+                dominated[i].push(j);
+                num_dominating[j] += 1;
+            } else if second.dominates(first) {
+                dominated[j].push(i);
+                num_dominating[i] += 1;
+            }
+        }
+    }
+
+    let mut fronts = Vec::new();
+    let mut front = (0..objectives.len())
+        .filter(|&i| num_dominating[i] == 0)
+        .collect::<Vec<_>>();
+    while !front.is_empty() {
+        let mut next_front = Vec::new();
+        for &i in &front {
+            for &j in &dominated[i] {
+                num_dominating[j] -= 1;
+                if num_dominating[j] == 0 {
+                    next_front.push(j);
+                }
+            }
+        }
+        fronts.push(front);
+        front = next_front;
+    }
+    fronts
+}
+
+/// The crowding distance of each member of `front` (in the same order as `front`).
+///
+/// This is how spread out the neighbours of each solution on the front are, summed over
+/// the (normalized) objectives. The solutions at the ends of the front get an infinite
+/// distance so they're always kept.
+#[must_use]
+pub fn crowding_distances(objectives: &[Objectives], front: &[usize]) -> Vec<f64> {
+    let mut distances = vec![0.0; front.len()];
+    let objective_fns: [fn(&Objectives) -> u64; 3] = [|o| o.value, |o| o.weight, |o| o.overload];
+    for objective in objective_fns {
+        let mut order = (0..front.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&position| objective(&objectives[front[position]]));
+        let (Some(&first), Some(&last)) = (order.first(), order.last()) else {
+            return distances;
+        };
+        let min = objective(&objectives[front[first]]);
+        let max = objective(&objectives[front[last]]);
+        // An objective that's the same across the whole front (like the overload of
+        // feasible solutions) doesn't tell us anything about crowding.
+        if max == min {
+            continue;
+        }
+        distances[first] = f64::INFINITY;
+        distances[last] = f64::INFINITY;
+        let range = (max - min) as f64;
+        for window in order.windows(3) {
+            let below = objective(&objectives[front[window[0]]]);
+            let above = objective(&objectives[front[window[2]]]);
+            distances[window[1]] += (above - below) as f64 / range;
+        }
+    }
+    distances
+}
+
+/// The hypervolume of the feasible solutions in `objectives`, i.e., the area of the
+/// region of (weight, value) space that they dominate, measured from the reference
+/// point of weight `capacity` and value 0.
+///
+/// This only grows as a front improves, so it's a handy single number for tracking the
+/// progress of a multi-objective run.
+#[must_use]
+pub fn hypervolume(objectives: &[Objectives], capacity: u64) -> u128 {
+    let mut feasible = objectives
+        .iter()
+        .filter(|objectives| objectives.is_feasible())
+        .collect::<Vec<_>>();
+    feasible.sort_by_key(|objectives| objectives.weight);
+    // Sweeping from light to heavy, each solution that's more valuable than everything
+    // lighter than it adds a strip from its weight up to the capacity.
+    let mut best_value = 0;
+    let mut volume = 0;
+    for objectives in feasible {
+        if objectives.value > best_value {
+            volume += u128::from(objectives.value - best_value)
+                * u128::from(capacity - objectives.weight);
+            best_value = objectives.value;
+        }
+    }
+    volume
+}
+
+/// A population that has been sorted into non-dominated fronts, along with the
+/// rank (front number) and crowding distance of each individual.
+pub struct RankedPopulation {
+    individuals: Vec<EcIndividual<Bitstring, Objectives>>,
+    ranks: Vec<usize>,
+    crowding: Vec<f64>,
+}
+
+impl RankedPopulation {
+    fn new(individuals: Vec<EcIndividual<Bitstring, Objectives>>) -> Self {
+        let mut population = Self {
+            ranks: vec![0; individuals.len()],
+            crowding: vec![0.0; individuals.len()],
+            individuals,
+        };
+        let objectives = population.objectives();
+        for (rank, front) in non_dominated_fronts(&objectives).iter().enumerate() {
+            for (&i, distance) in front.iter().zip(crowding_distances(&objectives, front)) {
+                population.ranks[i] = rank;
+                population.crowding[i] = distance;
+            }
+        }
+        population
+    }
+
+    fn objectives(&self) -> Vec<Objectives> {
+        self.individuals
+            .iter()
+            .map(|individual| individual.test_results)
+            .collect()
+    }
+
+    /// The crowded comparison from NSGA-II: a lower rank is better, and within
+    /// a rank a larger crowding distance is better.
+    fn compare(&self, first: usize, second: usize) -> Ordering {
+        self.ranks[second].cmp(&self.ranks[first]).then_with(|| {
+            self.crowding[first]
+                .partial_cmp(&self.crowding[second])
+                .unwrap_or(Ordering::Equal)
+        })
+    }
+
+    /// The feasible individuals on the first front, without duplicate genomes.
+    #[must_use]
+    pub fn pareto_front(&self) -> Vec<&EcIndividual<Bitstring, Objectives>> {
+        let mut seen = HashSet::new();
+        self.individuals
+            .iter()
+            .zip(&self.ranks)
+            .filter(|(individual, &rank)| rank == 0 && individual.test_results.is_feasible())
+            .map(|(individual, _)| individual)
+            .filter(|individual| seen.insert(&individual.genome))
+            .collect()
+    }
+}
+
+/// The NSGA-II multi-objective evolutionary algorithm, applied to treating a knapsack as
+/// a trade-off between value and weight (see [`Objectives`]).
+///
+/// Parents are chosen with binary tournaments using the crowded comparison, children are
+/// made with the same variation as our other runs (see [`BitstringVariation`]), and the
+/// next generation is the best half of the parents and children combined, taking whole
+/// fronts while they fit and then the least crowded members of the next front.
+pub struct Nsga2 {
+    scorer: MultiObjectiveScorer,
+    variation: BitstringVariation,
+    population_size: usize,
+}
+
+impl Nsga2 {
+    #[must_use]
+    pub fn new(knapsack: Arc<Knapsack>, population_size: usize) -> Self {
+        Self {
+            variation: BitstringVariation::new(knapsack.num_items()),
+            scorer: MultiObjectiveScorer::new(knapsack),
+            population_size,
+        }
+    }
+
+    /// Create a ranked population of scored, random genomes.
+    pub fn initial_population(&self, rng: &mut impl Rng) -> RankedPopulation {
+        let genomes = (0..self.population_size)
+            .map(|_| self.variation.random(rng))
+            .collect();
+        RankedPopulation::new(self.score_all(genomes))
+    }
+
+    /// Create the next generation from the given `population`.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `population` is empty.
+    pub fn next_generation(
+        &self,
+        population: RankedPopulation,
+        rng: &mut impl Rng,
+    ) -> RankedPopulation {
+        assert!(
+            !population.individuals.is_empty(),
+            "Can't make a new generation from an empty population"
+        );
+        let children = (0..self.population_size)
+            .map(|_| {
+                let first_parent = Self::tournament(&population, rng);
+                let second_parent = Self::tournament(&population, rng);
+                self.variation.make_child(
+                    &population.individuals[first_parent].genome,
+                    &population.individuals[second_parent].genome,
+                    rng,
+                )
+            })
+            .collect();
+
+        let mut combined = population.individuals;
+        combined.extend(self.score_all(children));
+        let combined = RankedPopulation::new(combined);
+        let mut order = (0..combined.individuals.len()).collect::<Vec<_>>();
+        // Sorting by the crowded comparison puts whole fronts in order, with the least
+        // crowded members of each front first.
+        order.sort_by(|&first, &second| combined.compare(second, first));
+        order.truncate(self.population_size);
+
+        let mut individuals = combined
+            .individuals
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<_>>();
+        RankedPopulation {
+            ranks: order.iter().map(|&i| combined.ranks[i]).collect(),
+            crowding: order.iter().map(|&i| combined.crowding[i]).collect(),
+            individuals: order
+                .iter()
+                .filter_map(|&i| individuals[i].take())
+                .collect(),
+        }
+    }
+
+    fn tournament(population: &RankedPopulation, rng: &mut impl Rng) -> usize {
+        let size = population.individuals.len();
+        let first = rng.random_range(0..size);
+        let second = rng.random_range(0..size);
+        if population.compare(second, first) == Ordering::Greater {
+            second
+        } else {
+            first
+        }
+    }
+
+    fn score_all(&self, genomes: Vec<Bitstring>) -> Vec<EcIndividual<Bitstring, Objectives>> {
+        let scorer = &self.scorer;
+        genomes
+            .into_par_iter()
+            .map(|genome| {
+                let score = scorer.score(&genome);
+                EcIndividual::new(genome, score)
+            })
+            .collect()
+    }
+}
+
+/// Write a Pareto front to a CSV file with a `value,weight,items` header and one row per
+/// solution, where `items` is the ids of the chosen items separated by spaces. The rows
+/// are in order of increasing weight.
+///
+/// # Errors
+///
+/// This fails if we can't create or write to the file.
+pub fn write_pareto_front(
+    path: impl AsRef<Path>,
+    knapsack: &Knapsack,
+    front: &[&EcIndividual<Bitstring, Objectives>],
+) -> anyhow::Result<()> {
+    let file = File::create(path.as_ref())
+        .with_context(|| format!("Failed to create the Pareto front file {:?}", path.as_ref()))?;
+    let mut writer = BufWriter::new(file);
+    let mut front = front.to_vec();
+    front.sort_by_key(|individual| {
+        (
+            individual.test_results.weight,
+            individual.test_results.value,
+        )
+    });
+    writeln!(writer, "value,weight,items")?;
+    for individual in front {
+        let items = knapsack
+            .items()
+            .iter()
+            .zip(individual.genome.iter())
+            .filter(|(_, &included)| included)
+            .map(|(item, _)| item.id().to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        writeln!(
+            writer,
+            "{},{},{items}",
+            individual.test_results.value, individual.test_results.weight
+        )?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rand::SeedableRng;
+    use rand_pcg::Pcg64;
+    use test_case::test_case;
+
+    use super::{crowding_distances, hypervolume, non_dominated_fronts, Nsga2, Objectives};
+    use crate::{item::Item, knapsack::Knapsack};
+
+    const fn objectives(value: u64, weight: u64, overload: u64) -> Objectives {
+        Objectives {
+            value,
+            weight,
+            overload,
+        }
+    }
+
+    #[test_case(objectives(5, 3, 0), objectives(4, 3, 0), true; "more valuable")]
+    #[test_case(objectives(5, 3, 0), objectives(5, 4, 0), true; "lighter")]
+    #[test_case(objectives(5, 3, 0), objectives(5, 3, 0), false; "equal")]
+    #[test_case(objectives(5, 3, 0), objectives(6, 4, 0), false; "trade-off")]
+    #[test_case(objectives(1, 9, 0), objectives(50, 20, 2), true; "feasible beats infeasible")]
+    #[test_case(objectives(50, 20, 2), objectives(1, 25, 7), true; "less overloaded")]
+    fn dominates(first: Objectives, second: Objectives, expected: bool) {
+        assert_eq!(first.dominates(&second), expected);
+    }
+
+    #[test]
+    fn fronts() {
+        let points = [
+            objectives(5, 5, 0),
+            objectives(3, 2, 0),
+            objectives(4, 5, 0),
+            objectives(9, 12, 2),
+            objectives(2, 2, 0),
+        ];
+        assert_eq!(
+            non_dominated_fronts(&points),
+            vec![vec![0, 1], vec![2, 4], vec![3]]
+        );
+    }
+
+    #[test]
+    fn crowding() {
+        let points = [
+            objectives(1, 1, 0),
+            objectives(2, 2, 0),
+            objectives(5, 4, 0),
+            objectives(6, 5, 0),
+        ];
+        let distances = crowding_distances(&points, &[0, 1, 2, 3]);
+        assert!(distances[0].is_infinite() && distances[3].is_infinite());
+        // Point 1's neighbours span 4/5 of the value range and 3/4 of the weight range.
+        assert!((distances[1] - (0.8 + 0.75)).abs() < 1e-9);
+        assert!((distances[2] - (0.8 + 0.75)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn hypervolume_of_a_front() {
+        let points = [
+            objectives(3, 2, 0),
+            objectives(5, 6, 0),
+            // Dominated, so this doesn't add anything.
+            objectives(2, 4, 0),
+            // Infeasible, so this doesn't count.
+            objectives(20, 12, 2),
+        ];
+        // 3 * (10 - 2) + (5 - 3) * (10 - 6)
+        assert_eq!(hypervolume(&points, 10), 32);
+    }
+
+    #[test]
+    fn population_size_is_kept() {
+        let knapsack = Knapsack::new(
+            vec![
+                Item::new(1, 5, 8),
+                Item::new(2, 9, 6),
+                Item::new(3, 2, 7),
+                Item::new(4, 4, 3),
+            ],
+            15,
+        );
+        let nsga2 = Nsga2::new(Arc::new(knapsack), 20);
+        let mut rng = Pcg64::seed_from_u64(0);
+        let mut population = nsga2.initial_population(&mut rng);
+        for _ in 0..20 {
+            population = nsga2.next_generation(population, &mut rng);
+        }
+        assert_eq!(population.individuals.len(), 20);
+        let front = population.pareto_front();
+        assert!(!front.is_empty());
+        // Nothing on the front should dominate anything else on it.
+        for first in &front {
+            assert!(front
+                .iter()
+                .all(|second| !first.test_results.dominates(&second.test_results)));
+        }
+    }
+}
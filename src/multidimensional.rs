@@ -0,0 +1,375 @@
+use std::{path::Path, str::FromStr};
+
+use anyhow::{anyhow, Context};
+use ec_core::individual::scorer::Scorer;
+use ec_linear::genome::bitstring::Bitstring;
+
+use crate::{
+    cliff_score::CliffScore, instance_lines::read_instance_file, item::Item, knapsack::Knapsack,
+    overflow::checked_total,
+};
+
+/// An item in a multi-dimensional knapsack problem, which has a value and
+/// a weight in _each_ of the dimensions of the knapsack.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultidimensionalItem {
+    id: u64,
+    value: u64,
+    weights: Vec<u64>,
+}
+
+impl MultidimensionalItem {
+    /// Creates a new `MultidimensionalItem` with the given weight in each dimension.
+    #[must_use]
+    pub const fn new(id: u64, value: u64, weights: Vec<u64>) -> Self {
+        Self { id, value, weights }
+    }
+
+    /// Returns the value of the item.
+    #[must_use]
+    pub const fn value(&self) -> u64 {
+        self.value
+    }
+}
+
+/// Representation of a multi-dimensional knapsack problem.
+///
+/// This is like [`Knapsack`], except the knapsack has a
+/// capacity in each of several dimensions (e.g., weight _and_ volume), and each item
+/// has a weight in each of those dimensions. A set of choices only fits if the total
+/// weight in _every_ dimension is within the capacity for that dimension.
+#[derive(Debug)]
+pub struct MultidimensionalKnapsack {
+    items: Vec<MultidimensionalItem>,
+    capacities: Vec<u64>,
+    /// The best known (often optimal) value for this instance, if the instance file had one.
+    best_known: Option<u64>,
+}
+
+impl MultidimensionalKnapsack {
+    /// Construct a multi-dimensional knapsack instance from a collection of items
+    /// and a capacity for each dimension.
+    ///
+    /// # Errors
+    ///
+    /// This fails if any item doesn't have exactly one weight per capacity, or if the
+    /// total value or the total weight in some dimension of all the items is too big
+    /// for a `u64`.
+    pub fn new(items: Vec<MultidimensionalItem>, capacities: Vec<u64>) -> anyhow::Result<Self> {
+        if let Some(item) = items
+            .iter()
+            .find(|item| item.weights.len() != capacities.len())
+        {
+            anyhow::bail!(
+                "Item {} has {} weights, but the knapsack has {} dimensions",
+                item.id,
+                item.weights.len(),
+                capacities.len()
+            );
+        }
+        checked_total(items.iter().map(|item| u128::from(item.value)), "value")?;
+        for dimension in 0..capacities.len() {
+            checked_total(
+                items.iter().map(|item| u128::from(item.weights[dimension])),
+                format_args!("weight in dimension {dimension}"),
+            )?;
+        }
+        Ok(Self {
+            items,
+            capacities,
+            best_known: None,
+        })
+    }
+
+    /// Get the number of items in this knapsack instance.
+    #[must_use]
+    pub fn num_items(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Get the number of dimensions (constraints) in this knapsack instance.
+    #[must_use]
+    pub fn num_dimensions(&self) -> usize {
+        self.capacities.len()
+    }
+
+    /// Get the capacity of the knapsack in each dimension.
+    #[must_use]
+    pub fn capacities(&self) -> &[u64] {
+        &self.capacities
+    }
+
+    /// Get the best known value for this instance, if there is one.
+    #[must_use]
+    pub const fn best_known(&self) -> Option<u64> {
+        self.best_known
+    }
+
+    /// Turn a single-dimension instance into a regular [`Knapsack`], keeping its best
+    /// known value. This is how single-constraint OR-Library files are read as regular
+    /// knapsacks.
+    ///
+    /// # Errors
+    ///
+    /// This fails if the instance doesn't have exactly one dimension.
+    pub fn into_knapsack(self) -> anyhow::Result<Knapsack> {
+        let &[capacity] = self.capacities.as_slice() else {
+            anyhow::bail!(
+                "A regular knapsack has one capacity, but this instance has {} dimensions",
+                self.num_dimensions()
+            );
+        };
+        let items = self
+            .items
+            .into_iter()
+            .map(|item| Item::new(item.id, item.value, item.weights[0]))
+            .collect();
+        Ok(Knapsack::try_new(items, capacity)?.with_best_known(self.best_known))
+    }
+
+    /// Get the value of a current set of choices for this knapsack. This is the
+    /// sum of the value of all the chosen items as specified in `choices`.
+    #[must_use]
+    pub fn value(&self, choices: &Bitstring) -> u64 {
+        self.items
+            .iter()
+            .zip(choices.iter())
+            .filter_map(|(item, included)| included.then_some(item.value()))
+            .sum()
+    }
+
+    /// Get the total weight in each dimension of a current set of choices for this knapsack.
+    #[must_use]
+    pub fn weights(&self, choices: &Bitstring) -> Vec<u64> {
+        let mut totals = vec![0; self.num_dimensions()];
+        for (item, _) in self
+            .items
+            .iter()
+            .zip(choices.iter())
+            .filter(|(_, &included)| included)
+        {
+            for (total, weight) in totals.iter_mut().zip(&item.weights) {
+                *total += weight;
+            }
+        }
+        totals
+    }
+
+    /// Does the current set of choices fit in the knapsack in every dimension?
+    #[must_use]
+    pub fn fits(&self, choices: &Bitstring) -> bool {
+        self.weights(choices)
+            .iter()
+            .zip(&self.capacities)
+            .all(|(weight, capacity)| weight <= capacity)
+    }
+
+    /// Parse the instance with the given (0-based) `index` from a file in the OR-Library
+    /// `mknap` format.
+    ///
+    /// # Errors
+    ///
+    /// This can fail if:
+    ///    - We fail to read the file,
+    ///    - The file contents have the wrong format, or
+    ///    - There is no instance with the given `index` in the file
+    pub fn from_file_path(file_path: impl AsRef<Path>, index: usize) -> anyhow::Result<Self> {
+        let (contents, source) = read_instance_file(file_path.as_ref())?;
+        let mut instances = Self::parse_mknap(&contents)
+            .with_context(|| format!("Failed to parse the file {source}"))?;
+        let num_instances = instances.len();
+        anyhow::ensure!(
+            index < num_instances,
+            "Asked for instance {index} from {:?}, but it only has {num_instances} instances (numbered from 0)",
+            file_path.as_ref()
+        );
+        Ok(instances.swap_remove(index))
+    }
+
+    /// Parse all the instances in a string in the OR-Library `mknap` format
+    /// (<https://people.brunel.ac.uk/~mastjjb/jeb/orlib/mknapinfo.html>), which looks like:
+    ///
+    /// ```text
+    /// 1
+    /// 3 2 14
+    /// 5 9 2
+    /// 8 6 7
+    /// 1 4 9
+    /// 14 10
+    /// ```
+    ///
+    /// - The first number `K` is how many instances are in the file.
+    /// - Each instance then starts with three numbers: the number of items `n`,
+    ///   the number of dimensions `m`, and the optimal value (or 0 if that's unknown).
+    /// - Next come the `n` values of the items.
+    /// - Then, for each of the `m` dimensions, the `n` weights of the items in that dimension.
+    /// - Finally, the `m` capacities.
+    ///
+    /// The numbers are separated by whitespace, and can be split over lines in any way.
+    ///
+    /// # Errors
+    ///
+    /// This fails if the string isn't in the `mknap` format.
+    pub fn parse_mknap(s: &str) -> anyhow::Result<Vec<Self>> {
+        let mut numbers = s.split_ascii_whitespace().enumerate().map(|(n, token)| {
+            u64::from_str(token).with_context(|| {
+                format!("Failed to parse number {} ('{token}') as an integer", n + 1)
+            })
+        });
+        let mut next = |what: &str| {
+            numbers
+                .next()
+                .ok_or_else(|| anyhow!("The input ended while looking for {what}"))?
+        };
+        let to_usize = |n: u64| usize::try_from(n).context("Count is too large");
+
+        let num_instances = to_usize(next("the number of instances")?)?;
+        let mut instances = Vec::new();
+        for instance in 0..num_instances {
+            let num_items = to_usize(next("the number of items")?)?;
+            let num_dimensions = to_usize(next("the number of dimensions")?)?;
+            let optimum = next("the optimal value")?;
+            let values = (0..num_items)
+                .map(|_| next("an item value"))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let weights = (0..num_dimensions)
+                .map(|_| {
+                    (0..num_items)
+                        .map(|_| next("an item weight"))
+                        .collect::<anyhow::Result<Vec<_>>>()
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let capacities = (0..num_dimensions)
+                .map(|_| next("a capacity"))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+
+            let items = values
+                .into_iter()
+                .enumerate()
+                .map(|(i, value)| {
+                    let item_weights = weights.iter().map(|dimension| dimension[i]).collect();
+                    MultidimensionalItem::new(i as u64 + 1, value, item_weights)
+                })
+                .collect();
+            let mut knapsack = Self::new(items, capacities)
+                .with_context(|| format!("Instance {instance} is inconsistent"))?;
+            knapsack.best_known = (optimum > 0).then_some(optimum);
+            instances.push(knapsack);
+        }
+        anyhow::ensure!(
+            numbers.next().is_none(),
+            "There was unexpected extra content after the {num_instances} instances"
+        );
+        Ok(instances)
+    }
+}
+
+/// A scorer for multi-dimensional knapsacks that returns `CliffScore::Overloaded` if
+/// the choices exceed the capacity in _any_ dimension, and the total value otherwise.
+pub struct MultidimensionalCliffScorer {
+    knapsack: MultidimensionalKnapsack,
+}
+
+impl MultidimensionalCliffScorer {
+    #[must_use]
+    pub const fn new(knapsack: MultidimensionalKnapsack) -> Self {
+        Self { knapsack }
+    }
+
+    /// The knapsack instance this scorer scores choices against.
+    #[must_use]
+    pub const fn knapsack(&self) -> &MultidimensionalKnapsack {
+        &self.knapsack
+    }
+}
+
+impl Scorer<Bitstring> for MultidimensionalCliffScorer {
+    type Score = CliffScore;
+
+    fn score(&self, genome: &Bitstring) -> Self::Score {
+        if self.knapsack.fits(genome) {
+            CliffScore::Score(self.knapsack.value(genome))
+        } else {
+            CliffScore::Overloaded
+        }
+    }
+}
+
+#[expect(clippy::unwrap_used, reason = ".unwrap() is reasonable in tests")]
+#[cfg(test)]
+mod tests {
+    use ec_core::individual::scorer::Scorer;
+    use ec_linear::genome::bitstring::Bitstring;
+    use test_case::test_case;
+
+    use super::{MultidimensionalCliffScorer, MultidimensionalItem, MultidimensionalKnapsack};
+    use crate::cliff_score::CliffScore;
+
+    fn knapsack(capacities: Vec<u64>) -> MultidimensionalKnapsack {
+        MultidimensionalKnapsack::new(
+            vec![
+                MultidimensionalItem::new(1, 5, vec![8, 1]),
+                MultidimensionalItem::new(2, 9, vec![6, 4]),
+                MultidimensionalItem::new(3, 2, vec![7, 9]),
+            ],
+            capacities,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn parse_mknap() {
+        let instances = MultidimensionalKnapsack::parse_mknap(
+            "2\n3 2 14\n5 9 2\n8 6 7\n1 4 9\n14 10\n\n1 1 0\n 4\n 3\n 2\n",
+        )
+        .unwrap();
+        assert_eq!(instances.len(), 2);
+
+        let first = &instances[0];
+        assert_eq!(first.num_items(), 3);
+        assert_eq!(first.num_dimensions(), 2);
+        assert_eq!(first.capacities(), &[14, 10]);
+        assert_eq!(first.best_known(), Some(14));
+        assert_eq!(first.items[1], MultidimensionalItem::new(2, 9, vec![6, 4]));
+
+        let second = &instances[1];
+        assert_eq!(second.items, vec![MultidimensionalItem::new(1, 4, vec![3])]);
+        assert_eq!(second.capacities(), &[2]);
+        assert_eq!(second.best_known(), None);
+    }
+
+    #[test_case("1\n3 2 0\n5 9 2\n8 6 7\n1 4 9\n14"; "missing capacity")]
+    #[test_case("1\n1 1 0\n4 3 2 7"; "extra content")]
+    #[test_case("1\n1 1 0\n4 three 2"; "non-numeric")]
+    #[test_case("18446744073709551615\n1 1 0\n4 3 7"; "huge instance count")]
+    fn parse_mknap_invalid(contents: &str) {
+        assert!(MultidimensionalKnapsack::parse_mknap(contents).is_err());
+    }
+
+    #[test]
+    fn mismatched_dimensions() {
+        let result = MultidimensionalKnapsack::new(
+            vec![MultidimensionalItem::new(1, 5, vec![8, 1])],
+            vec![10],
+        );
+        assert!(result.is_err());
+    }
+
+    #[test_case([false, false, false], vec![0, 0]; "choose no items")]
+    #[test_case([false, true, false], vec![6, 4]; "choose one item")]
+    #[test_case([true, false, true], vec![15, 10]; "choose two items")]
+    fn test_weights(choices: [bool; 3], expected_weights: Vec<u64>) {
+        let knapsack = knapsack(vec![100, 100]);
+        let choices = Bitstring::from_iter(choices);
+        assert_eq!(knapsack.weights(&choices), expected_weights);
+    }
+
+    #[test_case([false, true, false], CliffScore::Score(9); "fits in both dimensions")]
+    #[test_case([true, true, false], CliffScore::Overloaded; "too heavy in the first dimension")]
+    #[test_case([false, true, true], CliffScore::Overloaded; "too heavy in the second dimension")]
+    fn test_scores(choices: [bool; 3], expected_score: CliffScore) {
+        let scorer = MultidimensionalCliffScorer::new(knapsack(vec![13, 12]));
+        let choices = Bitstring::from_iter(choices);
+        assert_eq!(scorer.score(&choices), expected_score);
+    }
+}
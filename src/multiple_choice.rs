@@ -0,0 +1,297 @@
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use anyhow::Context;
+use clap::ValueEnum;
+use ec_core::individual::scorer::Scorer;
+use ec_linear::genome::bitstring::Bitstring;
+
+use crate::{
+    cliff_score::CliffScore,
+    instance_lines::{read_instance_file, InstanceLines},
+    item::Item,
+    knapsack::Knapsack,
+};
+
+/// How many items must be chosen from each class in a multiple-choice knapsack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ClassConstraint {
+    /// Exactly one item must be chosen from every class.
+    ExactlyOne,
+    /// At most one item can be chosen from each class; a class can be left out entirely.
+    AtMostOne,
+}
+
+/// Representation of a multiple-choice knapsack problem.
+///
+/// This is a regular [`Knapsack`], except that the items are grouped into classes,
+/// and (depending on the [`ClassConstraint`]) exactly one or at most one item can be
+/// chosen from each class.
+///
+/// We still use `Bitstring` genomes (one bit per item) for these, so everything that works
+/// with 0/1 knapsacks (e.g., `Run`) works here too. Genomes that break the class constraint
+/// are fixed by [`MultipleChoiceKnapsack::repair`] before they're scored.
+#[derive(Debug)]
+pub struct MultipleChoiceKnapsack {
+    knapsack: Knapsack,
+    /// The indices (into `knapsack.items()`) of the items in each class.
+    classes: Vec<Vec<usize>>,
+    constraint: ClassConstraint,
+}
+
+impl MultipleChoiceKnapsack {
+    /// Construct a multiple-choice knapsack from a collection of items, the class of each
+    /// item, and a capacity. Class labels can be any numbers; items with the same label
+    /// are in the same class.
+    ///
+    /// # Errors
+    ///
+    /// This fails if there isn't exactly one class label per item, or if the total value
+    /// or weight of all the items is too big (see [`Knapsack::try_new`]).
+    pub fn new(
+        items: Vec<Item>,
+        class_labels: &[u64],
+        capacity: u64,
+        constraint: ClassConstraint,
+    ) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            items.len() == class_labels.len(),
+            "There are {} items but {} class labels",
+            items.len(),
+            class_labels.len()
+        );
+        // Number the classes in the order they first appear.
+        let mut class_numbers = HashMap::new();
+        let mut classes: Vec<Vec<usize>> = Vec::new();
+        for (index, label) in class_labels.iter().enumerate() {
+            let class = *class_numbers.entry(label).or_insert_with(|| {
+                classes.push(Vec::new());
+                classes.len() - 1
+            });
+            classes[class].push(index);
+        }
+        Ok(Self {
+            knapsack: Knapsack::try_new(items, capacity)?,
+            classes,
+            constraint,
+        })
+    }
+
+    /// Get the number of items in this knapsack instance.
+    #[must_use]
+    pub fn num_items(&self) -> usize {
+        self.knapsack.num_items()
+    }
+
+    /// Get the number of classes in this knapsack instance.
+    #[must_use]
+    pub fn num_classes(&self) -> usize {
+        self.classes.len()
+    }
+
+    /// Do the given choices satisfy the class constraint?
+    #[must_use]
+    pub fn satisfies_classes(&self, choices: &Bitstring) -> bool {
+        let bits = choices.iter().copied().collect::<Vec<_>>();
+        self.classes.iter().all(|class| {
+            let num_chosen = class.iter().filter(|&&index| bits[index]).count();
+            match self.constraint {
+                ClassConstraint::ExactlyOne => num_chosen == 1,
+                ClassConstraint::AtMostOne => num_chosen <= 1,
+            }
+        })
+    }
+
+    /// Change the given choices as little as possible so they satisfy the class constraint.
+    ///
+    /// - If several items in a class are chosen, only the most valuable of them
+    ///   (the lightest, if there's a tie) is kept.
+    /// - If no items in a class are chosen and the constraint is
+    ///   [`ClassConstraint::ExactlyOne`], the lightest item in the class is chosen.
+    #[must_use]
+    pub fn repair(&self, choices: &Bitstring) -> Bitstring {
+        let items = self.knapsack.items();
+        let mut bits = choices.iter().copied().collect::<Vec<_>>();
+        for class in &self.classes {
+            let keep = class
+                .iter()
+                .copied()
+                .filter(|&index| bits[index])
+                .max_by_key(|&index| {
+                    (
+                        items[index].value(),
+                        std::cmp::Reverse(items[index].weight()),
+                    )
+                })
+                .or_else(|| match self.constraint {
+                    ClassConstraint::ExactlyOne => class
+                        .iter()
+                        .copied()
+                        .min_by_key(|&index| items[index].weight()),
+                    ClassConstraint::AtMostOne => None,
+                });
+            for &index in class {
+                bits[index] = Some(index) == keep;
+            }
+        }
+        bits.into_iter().collect()
+    }
+
+    /// The ids of the chosen items.
+    #[must_use]
+    pub fn chosen_ids(&self, choices: &Bitstring) -> Vec<u64> {
+        self.knapsack
+            .items()
+            .iter()
+            .zip(choices.iter())
+            .filter_map(|(item, &included)| included.then_some(item.id()))
+            .collect()
+    }
+
+    /// Parse a multiple-choice knapsack instance from a text file.
+    ///
+    /// This is the same format as described in [`Knapsack::from_file_path`], except that
+    /// each item line has a fourth integer: the class the item belongs to.
+    ///
+    /// ```text
+    /// 4
+    /// 1 3 8 1
+    /// 2 2 8 1
+    /// 3 9 1 2
+    /// 4 4 6 2
+    /// 10
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This can fail if:
+    ///    - We fail to read the file, or
+    ///    - The file contents have the wrong format
+    pub fn from_file_path(
+        file_path: impl AsRef<Path>,
+        constraint: ClassConstraint,
+    ) -> anyhow::Result<Self> {
+        let (contents, source) = read_instance_file(file_path.as_ref())?;
+        let mut lines = InstanceLines::new(&contents, &source);
+        let num_items = lines.parse_next::<usize>("the number of items")?;
+
+        let mut items = Vec::new();
+        let mut class_labels = Vec::new();
+        for n in 1..=num_items {
+            let fields = lines.next_item(n, num_items, &["id", "value", "weight", "class"])?;
+            items.push(Item::new(fields[0], fields[1], fields[2]));
+            class_labels.push(fields[3]);
+        }
+
+        let capacity = lines.parse_next("the capacity")?;
+        lines.finish("the capacity")?;
+
+        Self::new(items, &class_labels, capacity, constraint)
+            .with_context(|| format!("The knapsack in {source} can't be used"))
+    }
+}
+
+/// A scorer for multiple-choice knapsacks. This [repairs](MultipleChoiceKnapsack::repair)
+/// the choices so they satisfy the class constraint, and then returns
+/// `CliffScore::Overloaded` if the repaired choices exceed the capacity, and their
+/// total value otherwise.
+pub struct MultipleChoiceCliffScorer {
+    knapsack: Arc<MultipleChoiceKnapsack>,
+}
+
+impl MultipleChoiceCliffScorer {
+    #[must_use]
+    pub fn new(knapsack: impl Into<Arc<MultipleChoiceKnapsack>>) -> Self {
+        Self {
+            knapsack: knapsack.into(),
+        }
+    }
+
+    /// The knapsack instance this scorer scores choices against.
+    #[must_use]
+    pub fn knapsack(&self) -> &MultipleChoiceKnapsack {
+        &self.knapsack
+    }
+}
+
+impl Scorer<Bitstring> for MultipleChoiceCliffScorer {
+    type Score = CliffScore;
+
+    fn score(&self, genome: &Bitstring) -> Self::Score {
+        let repaired = self.knapsack.repair(genome);
+        let knapsack = &self.knapsack.knapsack;
+        if knapsack.weight(&repaired) > knapsack.capacity() {
+            CliffScore::Overloaded
+        } else {
+            CliffScore::Score(knapsack.value(&repaired))
+        }
+    }
+}
+
+#[expect(clippy::unwrap_used, reason = ".unwrap() is reasonable in tests")]
+#[cfg(test)]
+mod tests {
+    use ec_core::individual::scorer::Scorer;
+    use ec_linear::genome::bitstring::Bitstring;
+    use test_case::test_case;
+
+    use super::{ClassConstraint, MultipleChoiceCliffScorer, MultipleChoiceKnapsack};
+    use crate::{cliff_score::CliffScore, item::Item};
+
+    fn knapsack(constraint: ClassConstraint) -> MultipleChoiceKnapsack {
+        MultipleChoiceKnapsack::new(
+            vec![
+                Item::new(1, 5, 8),
+                Item::new(2, 9, 6),
+                Item::new(3, 2, 7),
+                Item::new(4, 4, 3),
+            ],
+            &[10, 20, 10, 20],
+            100,
+            constraint,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn classes() {
+        let knapsack = knapsack(ClassConstraint::ExactlyOne);
+        assert_eq!(knapsack.num_items(), 4);
+        assert_eq!(knapsack.num_classes(), 2);
+        assert_eq!(knapsack.classes, vec![vec![0, 2], vec![1, 3]]);
+    }
+
+    #[test_case(ClassConstraint::ExactlyOne, [true, true, false, false], true; "exactly one, one from each")]
+    #[test_case(ClassConstraint::ExactlyOne, [true, false, false, false], false; "exactly one, missing a class")]
+    #[test_case(ClassConstraint::ExactlyOne, [true, true, true, false], false; "exactly one, two from a class")]
+    #[test_case(ClassConstraint::AtMostOne, [true, false, false, false], true; "at most one, missing a class")]
+    #[test_case(ClassConstraint::AtMostOne, [false, true, false, true], false; "at most one, two from a class")]
+    fn satisfies_classes(constraint: ClassConstraint, choices: [bool; 4], expected: bool) {
+        let choices = Bitstring::from_iter(choices);
+        assert_eq!(knapsack(constraint).satisfies_classes(&choices), expected);
+    }
+
+    #[test_case(ClassConstraint::ExactlyOne, [true, true, true, true], [true, true, false, false]; "keeps the most valuable")]
+    #[test_case(ClassConstraint::ExactlyOne, [false, false, false, false], [false, false, true, true]; "adds the lightest")]
+    #[test_case(ClassConstraint::AtMostOne, [false, false, true, true], [false, false, true, true]; "leaves valid choices alone")]
+    #[test_case(ClassConstraint::AtMostOne, [false, false, false, false], [false, false, false, false]; "leaves empty classes empty")]
+    fn repair(constraint: ClassConstraint, choices: [bool; 4], expected: [bool; 4]) {
+        let knapsack = knapsack(constraint);
+        let repaired = knapsack.repair(&Bitstring::from_iter(choices));
+        assert_eq!(repaired, Bitstring::from_iter(expected));
+        assert!(knapsack.satisfies_classes(&repaired));
+    }
+
+    #[test]
+    fn scores_repaired_choices() {
+        let scorer = MultipleChoiceCliffScorer::new(knapsack(ClassConstraint::ExactlyOne));
+        // This is repaired to choose items 1 and 2.
+        let choices = Bitstring::from_iter([true, true, true, true]);
+        assert_eq!(scorer.score(&choices), CliffScore::Score(14));
+        assert_eq!(
+            scorer
+                .knapsack()
+                .chosen_ids(&scorer.knapsack().repair(&choices)),
+            vec![1, 2]
+        );
+    }
+}
@@ -0,0 +1,395 @@
+use std::{path::Path, sync::Arc};
+
+use anyhow::Context;
+use ec_core::individual::scorer::Scorer;
+use rand::Rng;
+
+use crate::{
+    cliff_score::CliffScore,
+    evolution::Variation,
+    instance_lines::{read_instance_file, InstanceLines},
+    item::Item,
+    knapsack::Knapsack,
+    overflow::checked_total,
+};
+
+/// Representation of a multiple knapsack problem, where there are several knapsacks
+/// (bins), each with its own capacity, and each item can be put in at most one of them.
+///
+/// Solutions are _assignments_: a `Vec<usize>` with one entry per item, where `0` means
+/// the item isn't chosen and `b` (from 1 up to the number of bins) means it's put in bin `b`.
+/// A regular [`Knapsack`] can be converted (with [`From`]) to an instance with a single
+/// bin, where an assignment is just the usual choices with `1` for "chosen" and `0` for
+/// "not chosen", and scores the same as those choices do with [`CliffScorer`].
+///
+/// [`CliffScorer`]: crate::cliff_scorer::CliffScorer
+#[derive(Debug, PartialEq, Eq)]
+pub struct MultipleKnapsacks {
+    items: Vec<Item>,
+    capacities: Vec<u64>,
+}
+
+impl From<&Knapsack> for MultipleKnapsacks {
+    fn from(knapsack: &Knapsack) -> Self {
+        Self {
+            items: knapsack.items().to_vec(),
+            capacities: vec![knapsack.capacity()],
+        }
+    }
+}
+
+impl MultipleKnapsacks {
+    /// Construct a multiple knapsack instance from a collection of items and the
+    /// capacity of each bin.
+    ///
+    /// # Errors
+    ///
+    /// This fails if there are no bins, or if the total value or weight of all the items
+    /// is too big for a `u64`.
+    pub fn new(items: Vec<Item>, capacities: Vec<u64>) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            !capacities.is_empty(),
+            "There has to be at least one knapsack"
+        );
+        checked_total(items.iter().map(|item| u128::from(item.value())), "value")?;
+        checked_total(items.iter().map(|item| u128::from(item.weight())), "weight")?;
+        Ok(Self { items, capacities })
+    }
+
+    /// Get the number of items in this instance.
+    #[must_use]
+    pub fn num_items(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Get the number of bins (knapsacks) in this instance.
+    #[must_use]
+    pub fn num_bins(&self) -> usize {
+        self.capacities.len()
+    }
+
+    /// Get the capacity of each bin.
+    #[must_use]
+    pub fn capacities(&self) -> &[u64] {
+        &self.capacities
+    }
+
+    /// Get the total value of all the assigned items.
+    #[must_use]
+    pub fn value(&self, assignment: &[usize]) -> u64 {
+        self.items
+            .iter()
+            .zip(assignment)
+            .filter_map(|(item, &bin)| (bin > 0).then_some(item.value()))
+            .sum()
+    }
+
+    /// Get the total weight in each bin. Assignments to bins that don't exist are ignored.
+    #[must_use]
+    pub fn loads(&self, assignment: &[usize]) -> Vec<u64> {
+        let mut loads = vec![0; self.num_bins()];
+        for (item, &bin) in self.items.iter().zip(assignment) {
+            if let Some(load) = bin.checked_sub(1).and_then(|bin| loads.get_mut(bin)) {
+                *load += item.weight();
+            }
+        }
+        loads
+    }
+
+    /// Check that an assignment is a legal solution: it has one entry per item, every
+    /// entry is `0` or an existing bin, and no bin is over its capacity.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error describing the first problem found, if there is one.
+    pub fn verify(&self, assignment: &[usize]) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            assignment.len() == self.num_items(),
+            "The assignment has {} entries, but there are {} items",
+            assignment.len(),
+            self.num_items()
+        );
+        if let Some((item, &bin)) = self
+            .items
+            .iter()
+            .zip(assignment)
+            .find(|(_, &bin)| bin > self.num_bins())
+        {
+            anyhow::bail!(
+                "Item {} is assigned to bin {bin}, but there are only {} bins",
+                item.id(),
+                self.num_bins()
+            );
+        }
+        for (bin, (load, capacity)) in self
+            .loads(assignment)
+            .iter()
+            .zip(&self.capacities)
+            .enumerate()
+        {
+            anyhow::ensure!(
+                load <= capacity,
+                "Bin {} has a load of {load}, which is over its capacity of {capacity}",
+                bin + 1
+            );
+        }
+        Ok(())
+    }
+
+    /// Turn an assignment into a legal one (see [`MultipleKnapsacks::verify`]).
+    ///
+    /// Entries for bins that don't exist become `0`, and then items are taken out of each
+    /// overloaded bin, least valuable for their weight first, until it fits.
+    #[must_use]
+    pub fn repair(&self, assignment: &[usize]) -> Vec<usize> {
+        let mut repaired = assignment
+            .iter()
+            .map(|&bin| if bin > self.num_bins() { 0 } else { bin })
+            .collect::<Vec<_>>();
+        let mut loads = self.loads(&repaired);
+        // Item indices from the lowest to the highest value/weight ratio, comparing
+        // `a.value / a.weight` and `b.value / b.weight` by cross-multiplying.
+        let mut order = (0..self.num_items()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| {
+            let (a, b) = (&self.items[a], &self.items[b]);
+            (u128::from(a.value()) * u128::from(b.weight()))
+                .cmp(&(u128::from(b.value()) * u128::from(a.weight())))
+        });
+        for index in order {
+            let bin = repaired[index];
+            if bin > 0 && loads[bin - 1] > self.capacities[bin - 1] {
+                loads[bin - 1] -= self.items[index].weight();
+                repaired[index] = 0;
+            }
+        }
+        repaired
+    }
+
+    /// Parse a multiple knapsack instance from a text file.
+    ///
+    /// This is the same format as described in [`Knapsack::from_file_path`], except that
+    /// the last line has the capacity of _each_ of the bins, separated by whitespace. A
+    /// regular knapsack file is therefore a multiple knapsack instance with one bin.
+    ///
+    /// ```text
+    /// 3
+    /// 1 3 8
+    /// 2 2 8
+    /// 3 9 1
+    /// 10 8
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This can fail if:
+    ///    - We fail to read the file, or
+    ///    - The file contents have the wrong format
+    pub fn from_file_path(file_path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let (contents, source) = read_instance_file(file_path.as_ref())?;
+        let mut lines = InstanceLines::new(&contents, &source);
+        let num_items = lines.parse_next::<usize>("the number of items")?;
+
+        let items = (1..=num_items)
+            .map(|n| {
+                let fields = lines.next_item(n, num_items, &["id", "value", "weight"])?;
+                Ok(Item::new(fields[0], fields[1], fields[2]))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let (_, capacities) = lines.next_fields("the capacities")?;
+        lines.finish("the capacities")?;
+
+        Self::new(items, capacities)
+            .with_context(|| format!("The knapsacks in {source} can't be used"))
+    }
+}
+
+/// A scorer for multiple knapsack assignments that returns `CliffScore::Overloaded` if the
+/// assignment isn't legal (see [`MultipleKnapsacks::verify`]), and the total value of the
+/// assigned items otherwise.
+pub struct MultipleKnapsacksCliffScorer {
+    knapsacks: Arc<MultipleKnapsacks>,
+}
+
+impl MultipleKnapsacksCliffScorer {
+    #[must_use]
+    pub fn new(knapsacks: impl Into<Arc<MultipleKnapsacks>>) -> Self {
+        Self {
+            knapsacks: knapsacks.into(),
+        }
+    }
+}
+
+impl Scorer<Vec<usize>> for MultipleKnapsacksCliffScorer {
+    type Score = CliffScore;
+
+    fn score(&self, genome: &Vec<usize>) -> Self::Score {
+        if self.knapsacks.verify(genome).is_ok() {
+            CliffScore::Score(self.knapsacks.value(genome))
+        } else {
+            CliffScore::Overloaded
+        }
+    }
+}
+
+/// The variation we use for multiple knapsack assignments: uniform crossover, followed by
+/// moving each item to a random bin (or out of all of them) with probability
+/// `1 / num_items`. New genomes are [repaired](MultipleKnapsacks::repair), so every
+/// individual in the population is a legal assignment.
+pub struct AssignmentVariation {
+    knapsacks: Arc<MultipleKnapsacks>,
+}
+
+impl AssignmentVariation {
+    #[must_use]
+    pub fn new(knapsacks: impl Into<Arc<MultipleKnapsacks>>) -> Self {
+        Self {
+            knapsacks: knapsacks.into(),
+        }
+    }
+}
+
+impl Variation<Vec<usize>> for AssignmentVariation {
+    fn random<R: Rng + ?Sized>(&self, rng: &mut R) -> Vec<usize> {
+        let num_bins = self.knapsacks.num_bins();
+        let assignment = (0..self.knapsacks.num_items())
+            .map(|_| rng.random_range(0..=num_bins))
+            .collect::<Vec<_>>();
+        self.knapsacks.repair(&assignment)
+    }
+
+    fn make_child<R: Rng + ?Sized>(
+        &self,
+        first_parent: &Vec<usize>,
+        second_parent: &Vec<usize>,
+        rng: &mut R,
+    ) -> Vec<usize> {
+        let num_bins = self.knapsacks.num_bins();
+        let mutation_rate = 1.0 / self.knapsacks.num_items().max(1) as f64;
+        let child = first_parent
+            .iter()
+            .zip(second_parent)
+            .map(|(&first, &second)| {
+                if rng.random_bool(mutation_rate) {
+                    rng.random_range(0..=num_bins)
+                } else if rng.random_bool(0.5) {
+                    first
+                } else {
+                    second
+                }
+            })
+            .collect::<Vec<_>>();
+        self.knapsacks.repair(&child)
+    }
+}
+
+#[expect(clippy::unwrap_used, reason = ".unwrap() is reasonable in tests")]
+#[cfg(test)]
+mod tests {
+    use ec_core::individual::scorer::Scorer;
+    use ec_linear::genome::bitstring::Bitstring;
+    use rand::{Rng, SeedableRng};
+    use rand_pcg::Pcg64;
+    use test_case::test_case;
+
+    use super::{AssignmentVariation, MultipleKnapsacks, MultipleKnapsacksCliffScorer};
+    use crate::{
+        cliff_score::CliffScore, cliff_scorer::CliffScorer, evolution::Variation, item::Item,
+        knapsack::Knapsack,
+    };
+
+    fn knapsacks() -> MultipleKnapsacks {
+        MultipleKnapsacks::new(
+            vec![
+                Item::new(1, 5, 8),
+                Item::new(2, 9, 6),
+                Item::new(3, 2, 7),
+                Item::new(4, 4, 3),
+            ],
+            vec![10, 8],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn knapsack_is_one_bin() {
+        let knapsack = Knapsack::from_file_path("knapsacks/tiny.txt").unwrap();
+        let knapsacks = MultipleKnapsacks::from_file_path("knapsacks/tiny.txt").unwrap();
+        assert_eq!(knapsacks, MultipleKnapsacks::from(&knapsack));
+        assert_eq!(knapsacks.num_bins(), 1);
+
+        // Assignments to the single bin score the same as the equivalent choices.
+        let scorer = CliffScorer::new(knapsack);
+        let knapsacks_scorer = MultipleKnapsacksCliffScorer::new(knapsacks);
+        for bits in 0..8_usize {
+            let assignment = (0..3).map(|i| (bits >> i) & 1).collect::<Vec<_>>();
+            let choices = assignment
+                .iter()
+                .map(|&bin| bin == 1)
+                .collect::<Bitstring>();
+            assert_eq!(knapsacks_scorer.score(&assignment), scorer.score(&choices));
+        }
+    }
+
+    #[test]
+    fn converted_knapsacks_score_the_same() {
+        let knapsack = Knapsack::from_file_path("knapsacks/SmallProblem3.txt").unwrap();
+        let knapsacks_scorer =
+            MultipleKnapsacksCliffScorer::new(MultipleKnapsacks::from(&knapsack));
+        let num_items = knapsack.num_items();
+        let scorer = CliffScorer::new(knapsack);
+        let mut rng = Pcg64::seed_from_u64(3);
+        // Choosing each item with a range of probabilities gives both legal and overloaded
+        // sets of choices.
+        for n in 0..=50 {
+            let probability = f64::from(n) / 5_000.0;
+            let choices = (0..num_items)
+                .map(|_| rng.random_bool(probability))
+                .collect::<Bitstring>();
+            let assignment = choices.iter().map(|&chosen| usize::from(chosen)).collect();
+            assert_eq!(knapsacks_scorer.score(&assignment), scorer.score(&choices));
+        }
+    }
+
+    #[test]
+    fn values_and_loads() {
+        let knapsacks = knapsacks();
+        let assignment = vec![1, 2, 0, 2];
+        assert_eq!(knapsacks.value(&assignment), 18);
+        assert_eq!(knapsacks.loads(&assignment), vec![8, 9]);
+    }
+
+    #[test_case(vec![1, 2, 0, 0], CliffScore::Score(14); "legal")]
+    #[test_case(vec![1, 2, 0, 2], CliffScore::Overloaded; "second bin overloaded")]
+    #[test_case(vec![0, 0, 3, 0], CliffScore::Overloaded; "no such bin")]
+    #[test_case(vec![1, 2], CliffScore::Overloaded; "too short")]
+    fn scores(assignment: Vec<usize>, expected: CliffScore) {
+        let scorer = MultipleKnapsacksCliffScorer::new(knapsacks());
+        assert_eq!(scorer.score(&assignment), expected);
+    }
+
+    #[test]
+    fn repair_removes_the_least_valuable_for_their_weight() {
+        let knapsacks = knapsacks();
+        // Bin 1 holds items 1, 3 and 4 (weight 18, capacity 10); item 3 has the
+        // worst ratio, then item 1. Bin 3 doesn't exist.
+        let repaired = knapsacks.repair(&[1, 3, 1, 1]);
+        assert_eq!(repaired, vec![0, 0, 0, 1]);
+        knapsacks.verify(&repaired).unwrap();
+    }
+
+    #[test]
+    fn variation_makes_legal_assignments() {
+        let knapsacks = std::sync::Arc::new(knapsacks());
+        let variation = AssignmentVariation::new(std::sync::Arc::clone(&knapsacks));
+        let mut rng = Pcg64::seed_from_u64(0);
+        for _ in 0..100 {
+            let first = variation.random(&mut rng);
+            let second = variation.random(&mut rng);
+            let child = variation.make_child(&first, &second, &mut rng);
+            for assignment in [first, second, child] {
+                knapsacks.verify(&assignment).unwrap();
+            }
+        }
+    }
+}
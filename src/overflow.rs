@@ -0,0 +1,52 @@
+use std::fmt::Display;
+
+/// Add up `amounts` and check that the total fits in a `u64`.
+///
+/// Values and weights are stored as `u64`s, and the totals for a set of choices are
+/// added up as `u64`s too, which would silently wrap around in release builds if a
+/// total got too big. The biggest total any set of choices can have is the total over
+/// _all_ the items, though, so checking that once when an instance is built guarantees
+/// none of the sums done while scoring can overflow.
+///
+/// The amounts are added up as `u128`s, which is big enough for the product of two
+/// `u64`s (e.g., a weight times a number of copies). `what` describes what's being added
+/// up (e.g., "weight") for the error message.
+///
+/// # Errors
+///
+/// This fails if the total is more than `u64::MAX`.
+pub fn checked_total(
+    amounts: impl IntoIterator<Item = u128>,
+    what: impl Display,
+) -> anyhow::Result<u64> {
+    let total = amounts.into_iter().fold(0_u128, u128::saturating_add);
+    u64::try_from(total).map_err(|_| {
+        anyhow::anyhow!(
+            "The total {what} of all the items is {total}, which is more than the largest total we can represent ({}), so some choices would get the wrong score",
+            u64::MAX
+        )
+    })
+}
+
+#[expect(clippy::unwrap_used, reason = ".unwrap() is reasonable in tests")]
+#[cfg(test)]
+mod tests {
+    use super::checked_total;
+
+    #[test]
+    fn small_totals_are_fine() {
+        assert_eq!(checked_total([1, 2, 3], "value").ok(), Some(6));
+        assert_eq!(
+            checked_total([u128::from(u64::MAX)], "value").ok(),
+            Some(u64::MAX)
+        );
+    }
+
+    #[test]
+    fn totals_too_big_for_u64_are_errors() {
+        let error = checked_total([u128::from(u64::MAX), 1], "weight").unwrap_err();
+        assert!(error.to_string().contains("total weight"));
+        // Even totals too big for a `u128` are caught.
+        assert!(checked_total([u128::MAX, u128::MAX], "weight").is_err());
+    }
+}
@@ -0,0 +1,156 @@
+use ec_linear::genome::bitstring::Bitstring;
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// A fixed-length sequence of bits packed 64 to a `u64` word.
+///
+/// A `Bitstring` stores each bit as a whole `bool` (a byte), so this takes an eighth of the
+/// memory. More importantly for scoring, the chosen items can be found a word at a time,
+/// skipping over 64 unchosen items at once, instead of checking every item.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PackedBits {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl PackedBits {
+    /// All zeros, with the given length.
+    #[must_use]
+    pub fn zeros(len: usize) -> Self {
+        Self {
+            words: vec![0; len.div_ceil(WORD_BITS)],
+            len,
+        }
+    }
+
+    /// The number of bits.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Are there no bits at all?
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the bit at `index`.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `index` is out of range.
+    #[must_use]
+    pub fn get(&self, index: usize) -> bool {
+        assert!(index < self.len, "Bit {index} is out of range");
+        self.words[index / WORD_BITS] & (1 << (index % WORD_BITS)) != 0
+    }
+
+    /// Flip the bit at `index`.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `index` is out of range.
+    pub fn flip(&mut self, index: usize) {
+        assert!(index < self.len, "Bit {index} is out of range");
+        self.words[index / WORD_BITS] ^= 1 << (index % WORD_BITS);
+    }
+
+    /// The indices of the bits that are set, in increasing order.
+    pub fn ones(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words
+            .iter()
+            .enumerate()
+            .flat_map(|(word_index, &word)| {
+                let mut remaining = word;
+                std::iter::from_fn(move || {
+                    (remaining != 0).then(|| {
+                        let bit = remaining.trailing_zeros() as usize;
+                        // Clear the lowest set bit.
+                        remaining &= remaining - 1;
+                        word_index * WORD_BITS + bit
+                    })
+                })
+            })
+    }
+
+    /// All the bits, in order.
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.len).map(|index| self.get(index))
+    }
+}
+
+impl FromIterator<bool> for PackedBits {
+    fn from_iter<T: IntoIterator<Item = bool>>(iter: T) -> Self {
+        let mut words = Vec::new();
+        let mut len = 0_usize;
+        let mut word = 0;
+        for bit in iter {
+            // This is branch free, which makes a big difference since the bits are
+            // often essentially random.
+            word |= u64::from(bit) << (len % WORD_BITS);
+            len += 1;
+            if len.is_multiple_of(WORD_BITS) {
+                words.push(word);
+                word = 0;
+            }
+        }
+        if !len.is_multiple_of(WORD_BITS) {
+            words.push(word);
+        }
+        Self { words, len }
+    }
+}
+
+impl From<&Bitstring> for PackedBits {
+    fn from(bits: &Bitstring) -> Self {
+        bits.iter().copied().collect()
+    }
+}
+
+impl From<&PackedBits> for Bitstring {
+    fn from(bits: &PackedBits) -> Self {
+        bits.iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ec_linear::genome::bitstring::Bitstring;
+
+    use super::PackedBits;
+
+    fn pattern(len: usize) -> Vec<bool> {
+        (0..len).map(|i| i % 3 == 0 || i % 7 == 5).collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        for len in [0, 1, 63, 64, 65, 200] {
+            let bitstring = Bitstring::from_iter(pattern(len));
+            let packed = PackedBits::from(&bitstring);
+            assert_eq!(packed.len(), len);
+            assert_eq!(Bitstring::from(&packed), bitstring);
+        }
+    }
+
+    #[test]
+    fn ones_are_the_set_bits() {
+        let bits = pattern(150);
+        let packed = bits.iter().copied().collect::<PackedBits>();
+        let expected = (0..150).filter(|&i| bits[i]).collect::<Vec<_>>();
+        assert_eq!(packed.ones().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn flip_and_get() {
+        let mut packed = PackedBits::zeros(130);
+        packed.flip(0);
+        packed.flip(64);
+        packed.flip(129);
+        packed.flip(64);
+        assert!(packed.get(0) && packed.get(129));
+        assert!(!packed.get(64) && !packed.get(1));
+        assert_eq!(packed.ones().collect::<Vec<_>>(), vec![0, 129]);
+    }
+}
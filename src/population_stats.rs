@@ -0,0 +1,165 @@
+use ec_core::individual::ec::EcIndividual;
+use ec_linear::genome::bitstring::Bitstring;
+use serde::{Deserialize, Serialize};
+
+use crate::{cliff_score::CliffScore, knapsack::Knapsack};
+
+/// Summary statistics of the knapsack values and weights over a whole population,
+/// not just the best individual.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PopulationStats {
+    /// The fraction of the population whose score is `CliffScore::Overloaded`.
+    pub overloaded_fraction: f64,
+    /// The total value of the chosen items, whether or not they fit in the knapsack.
+    pub value: Summary,
+    /// The total weight of the chosen items.
+    pub weight: Summary,
+    /// The mean of `weight / capacity` over the population. Values above 1
+    /// come from individuals that are overloaded. This is `None` if the capacity is 0.
+    pub mean_capacity_utilization: Option<f64>,
+}
+
+/// The mean, median, minimum, and maximum of a collection of numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Summary {
+    pub mean: f64,
+    pub median: f64,
+    pub min: u64,
+    pub max: u64,
+}
+
+impl Summary {
+    /// Summarize the given numbers, returning `None` if there aren't any.
+    #[must_use]
+    pub fn of(mut numbers: Vec<u64>) -> Option<Self> {
+        numbers.sort_unstable();
+        let min = *numbers.first()?;
+        let max = *numbers.last()?;
+        let len = numbers.len();
+        let mean = numbers.iter().map(|&n| n as f64).sum::<f64>() / len as f64;
+        let median = if len % 2 == 1 {
+            numbers[len / 2] as f64
+        } else {
+            (numbers[len / 2 - 1] as f64 + numbers[len / 2] as f64) / 2.0
+        };
+        Some(Self {
+            mean,
+            median,
+            min,
+            max,
+        })
+    }
+}
+
+impl PopulationStats {
+    /// Compute the statistics for the given population on the given knapsack, returning
+    /// `None` if the population is empty.
+    #[must_use]
+    pub fn of_population(
+        knapsack: &Knapsack,
+        population: &[EcIndividual<Bitstring, CliffScore>],
+    ) -> Option<Self> {
+        let num_overloaded = population
+            .iter()
+            .filter(|individual| individual.test_results == CliffScore::Overloaded)
+            .count();
+        let values = population
+            .iter()
+            .map(|individual| knapsack.value(&individual.genome))
+            .collect::<Vec<_>>();
+        let weights = population
+            .iter()
+            .map(|individual| knapsack.weight(&individual.genome))
+            .collect::<Vec<_>>();
+        let capacity = knapsack.capacity() as f64;
+        let mean_capacity_utilization = (knapsack.capacity() > 0).then(|| {
+            weights
+                .iter()
+                .map(|&weight| weight as f64 / capacity)
+                .sum::<f64>()
+                / population.len() as f64
+        });
+
+        Some(Self {
+            overloaded_fraction: num_overloaded as f64 / population.len() as f64,
+            value: Summary::of(values)?,
+            weight: Summary::of(weights)?,
+            mean_capacity_utilization,
+        })
+    }
+}
+
+#[expect(clippy::unwrap_used, reason = ".unwrap() is reasonable in tests")]
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use ec_core::individual::{ec::EcIndividual, scorer::Scorer};
+    use ec_linear::genome::bitstring::Bitstring;
+
+    use super::{PopulationStats, Summary};
+    use crate::{
+        cliff_score::CliffScore, cliff_scorer::CliffScorer, item::Item, knapsack::Knapsack,
+    };
+
+    #[test]
+    fn summary_of_odd_count() {
+        let summary = Summary::of(vec![5, 1, 3]).unwrap();
+        assert_eq!(summary.min, 1);
+        assert_eq!(summary.max, 5);
+        assert!((summary.mean - 3.0).abs() < f64::EPSILON);
+        assert!((summary.median - 3.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn summary_of_even_count() {
+        let summary = Summary::of(vec![4, 1, 2, 10]).unwrap();
+        assert!((summary.median - 3.0).abs() < f64::EPSILON);
+        assert!((summary.mean - 4.25).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn summary_of_nothing() {
+        assert_eq!(Summary::of(Vec::new()), None);
+    }
+
+    #[test]
+    fn population_stats() {
+        let knapsack = Arc::new(Knapsack::new(
+            vec![Item::new(1, 5, 8), Item::new(2, 9, 6), Item::new(3, 2, 7)],
+            10,
+        ));
+        let scorer = CliffScorer::new(Arc::clone(&knapsack));
+        let population = [[false, true, false], [true, false, true]]
+            .into_iter()
+            .map(|choices| {
+                let genome = Bitstring::from_iter(choices);
+                let score = scorer.score(&genome);
+                EcIndividual::new(genome, score)
+            })
+            .collect::<Vec<_>>();
+
+        let stats = PopulationStats::of_population(&knapsack, &population).unwrap();
+        assert!((stats.overloaded_fraction - 0.5).abs() < f64::EPSILON);
+        assert_eq!((stats.value.min, stats.value.max), (7, 9));
+        assert_eq!((stats.weight.min, stats.weight.max), (6, 15));
+        assert!((stats.mean_capacity_utilization.unwrap() - 1.05).abs() < 1e-12);
+    }
+
+    #[test]
+    fn no_utilization_without_capacity() {
+        let knapsack = Knapsack::new(vec![Item::new(1, 5, 8)], 0);
+        let population = vec![EcIndividual::new(
+            Bitstring::from_iter([true]),
+            CliffScore::Overloaded,
+        )];
+        let stats = PopulationStats::of_population(&knapsack, &population).unwrap();
+        assert_eq!(stats.mean_capacity_utilization, None);
+        // This has to survive being written to a results file and read back.
+        let json = serde_json::to_string(&stats).unwrap();
+        assert_eq!(
+            serde_json::from_str::<PopulationStats>(&json).unwrap(),
+            stats
+        );
+    }
+}
@@ -0,0 +1,46 @@
+use ec_core::individual::scorer::Scorer;
+use ec_linear::genome::bitstring::Bitstring;
+
+use crate::{
+    cliff_score::CliffScore, cliff_scorer::CliffScorer,
+    multidimensional::MultidimensionalCliffScorer, multiple_choice::MultipleChoiceCliffScorer,
+    quadratic::QuadraticCliffScorer,
+};
+
+/// The scorers for all the kinds of problems we can evolve `Bitstring` solutions to.
+///
+/// `Run` (and our own `Evolution`) are built around a single scorer type, so this lets
+/// `main` choose which kind of problem to run on when the program is run, rather than
+/// having to be edited and recompiled.
+pub enum BitstringScorer {
+    Knapsack(CliffScorer),
+    Multidimensional(MultidimensionalCliffScorer),
+    MultipleChoice(MultipleChoiceCliffScorer),
+    Quadratic(QuadraticCliffScorer),
+}
+
+impl BitstringScorer {
+    /// The number of items in the problem, which is the number of bits in a genome.
+    #[must_use]
+    pub fn num_items(&self) -> usize {
+        match self {
+            Self::Knapsack(scorer) => scorer.knapsack().num_items(),
+            Self::Multidimensional(scorer) => scorer.knapsack().num_items(),
+            Self::MultipleChoice(scorer) => scorer.knapsack().num_items(),
+            Self::Quadratic(scorer) => scorer.knapsack().num_items(),
+        }
+    }
+}
+
+impl Scorer<Bitstring> for BitstringScorer {
+    type Score = CliffScore;
+
+    fn score(&self, genome: &Bitstring) -> Self::Score {
+        match self {
+            Self::Knapsack(scorer) => scorer.score(genome),
+            Self::Multidimensional(scorer) => scorer.score(genome),
+            Self::MultipleChoice(scorer) => scorer.score(genome),
+            Self::Quadratic(scorer) => scorer.score(genome),
+        }
+    }
+}
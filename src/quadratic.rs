@@ -0,0 +1,471 @@
+use std::{path::Path, str::FromStr, sync::Arc};
+
+use anyhow::{anyhow, Context};
+use ec_core::individual::scorer::Scorer;
+use ec_linear::genome::bitstring::Bitstring;
+
+use crate::{cliff_score::CliffScore, instance_lines::read_instance_file, overflow::checked_total};
+
+/// Representation of a quadratic knapsack problem.
+///
+/// This is like [`Knapsack`](crate::knapsack::Knapsack), except that as well as each item
+/// having a value of its own, each _pair_ of items has a value that we only get if both
+/// items in the pair are chosen. The total value of a set of choices is the sum of the
+/// values of the chosen items plus the sum of the values of all the chosen pairs.
+#[derive(Debug)]
+pub struct QuadraticKnapsack {
+    /// The name of the instance from the first line of the instance file.
+    name: String,
+    /// The value of each item on its own.
+    values: Vec<u64>,
+    /// `pair_values[i][j]` is the value we get if both items `i` and `j` are chosen.
+    /// This is symmetric, with zeros on the diagonal.
+    pair_values: Vec<Vec<u64>>,
+    weights: Vec<u64>,
+    capacity: u64,
+}
+
+impl QuadraticKnapsack {
+    /// Construct a quadratic knapsack instance.
+    ///
+    /// `pair_values` is the upper triangle of the pairwise values: `pair_values[i]` has
+    /// the values of the pairs `(i, j)` for each `j > i`, so it has one fewer entry than
+    /// `pair_values[i - 1]`.
+    ///
+    /// # Errors
+    ///
+    /// This fails if the number of values, pair values, and weights don't agree, or if the
+    /// total value (including every pair) or total weight of all the items is too big for
+    /// a `u64`.
+    pub fn new(
+        name: String,
+        values: Vec<u64>,
+        pair_values: &[Vec<u64>],
+        weights: Vec<u64>,
+        capacity: u64,
+    ) -> anyhow::Result<Self> {
+        let num_items = values.len();
+        anyhow::ensure!(
+            weights.len() == num_items,
+            "There are {num_items} item values but {} weights",
+            weights.len()
+        );
+        anyhow::ensure!(
+            pair_values.len() == num_items.saturating_sub(1),
+            "There should be {} rows of pair values but there are {}",
+            num_items.saturating_sub(1),
+            pair_values.len()
+        );
+        let mut full_pair_values = vec![vec![0; num_items]; num_items];
+        for (i, row) in pair_values.iter().enumerate() {
+            anyhow::ensure!(
+                row.len() == num_items - i - 1,
+                "Row {i} of the pair values should have {} entries but has {}",
+                num_items - i - 1,
+                row.len()
+            );
+            for (j, &value) in (i + 1..).zip(row) {
+                full_pair_values[i][j] = value;
+                full_pair_values[j][i] = value;
+            }
+        }
+        checked_total(
+            values
+                .iter()
+                .chain(pair_values.iter().flatten())
+                .map(|&value| u128::from(value)),
+            "value (including every pair)",
+        )?;
+        checked_total(weights.iter().map(|&weight| u128::from(weight)), "weight")?;
+        Ok(Self {
+            name,
+            values,
+            pair_values: full_pair_values,
+            weights,
+            capacity,
+        })
+    }
+
+    /// Get the name of this instance.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the number of items in this knapsack instance.
+    #[must_use]
+    pub fn num_items(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Get the capacity of the knapsack.
+    #[must_use]
+    pub const fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    /// Get the total value of a set of choices, including the values of all the chosen pairs.
+    #[must_use]
+    pub fn value(&self, choices: &Bitstring) -> u64 {
+        let chosen = Self::chosen_indices(choices);
+        chosen
+            .iter()
+            .enumerate()
+            .map(|(n, &i)| {
+                self.values[i]
+                    + chosen[n + 1..]
+                        .iter()
+                        .map(|&j| self.pair_values[i][j])
+                        .sum::<u64>()
+            })
+            .sum()
+    }
+
+    /// Get the total weight of a set of choices.
+    #[must_use]
+    pub fn weight(&self, choices: &Bitstring) -> u64 {
+        self.weights
+            .iter()
+            .zip(choices.iter())
+            .filter_map(|(&weight, &included)| included.then_some(weight))
+            .sum()
+    }
+
+    /// Improve a set of choices by repeatedly flipping single bits, as long as doing so
+    /// increases the value without going over the capacity. This uses a
+    /// [`QuadraticEvaluator`] so each possible flip can be checked in constant time,
+    /// and returns that evaluator so the value and weight of the result are at hand.
+    #[must_use]
+    pub fn hill_climb(&self, choices: &Bitstring) -> QuadraticEvaluator<'_> {
+        let mut evaluator = QuadraticEvaluator::new(self, choices);
+        let mut improved = true;
+        while improved {
+            improved = false;
+            for index in 0..self.num_items() {
+                if evaluator.weight_after_flip(index) <= self.capacity
+                    && evaluator.value_delta(index) > 0
+                {
+                    evaluator.flip(index);
+                    improved = true;
+                }
+            }
+        }
+        evaluator
+    }
+
+    fn chosen_indices(choices: &Bitstring) -> Vec<usize> {
+        choices
+            .iter()
+            .enumerate()
+            .filter_map(|(index, &included)| included.then_some(index))
+            .collect()
+    }
+
+    /// Parse a quadratic knapsack instance from a file in the standard QKP format
+    /// used by Billionnet and Soutif's benchmark instances, which looks like:
+    ///
+    /// ```text
+    /// tiny_qkp
+    /// 3
+    /// 5 9 2
+    /// 1 4
+    /// 7
+    ///
+    /// 0
+    /// 10
+    /// 8 6 1
+    /// ```
+    ///
+    /// - The first line is the name of the instance.
+    /// - The next line is the number of items `n`.
+    /// - Next come the `n` values of the items on their own.
+    /// - Then come the values of the pairs: `n - 1` rows, where row `i` has the values of
+    ///   the pairs `(i, j)` for each `j > i`.
+    /// - Then there's a `0` (indicating a `≤` capacity constraint) and the capacity.
+    /// - Finally come the `n` weights of the items.
+    ///
+    /// Apart from the name, the numbers are separated by whitespace, and can be split over
+    /// lines in any way.
+    ///
+    /// # Errors
+    ///
+    /// This can fail if:
+    ///    - We fail to read the file, or
+    ///    - The file contents have the wrong format
+    pub fn from_file_path(file_path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let (contents, source) = read_instance_file(file_path.as_ref())?;
+        Self::parse_qkp(&contents).with_context(|| format!("Failed to parse the file {source}"))
+    }
+
+    /// Parse a quadratic knapsack instance from a string in the standard QKP format
+    /// (see [`QuadraticKnapsack::from_file_path`]).
+    ///
+    /// # Errors
+    ///
+    /// This fails if the string isn't in the QKP format.
+    pub fn parse_qkp(s: &str) -> anyhow::Result<Self> {
+        let (name, rest) = s.split_once('\n').unwrap_or((s, ""));
+        let mut numbers = rest.split_ascii_whitespace().enumerate().map(|(n, token)| {
+            u64::from_str(token).with_context(|| {
+                format!("Failed to parse number {} ('{token}') as an integer", n + 1)
+            })
+        });
+        let mut next = |what: &str| {
+            numbers
+                .next()
+                .ok_or_else(|| anyhow!("The input ended while looking for {what}"))?
+        };
+
+        let num_items =
+            usize::try_from(next("the number of items")?).context("Count is too large")?;
+        let values = (0..num_items)
+            .map(|_| next("an item value"))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let pair_values = (0..num_items.saturating_sub(1))
+            .map(|i| {
+                (i + 1..num_items)
+                    .map(|_| next("a pair value"))
+                    .collect::<anyhow::Result<Vec<_>>>()
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let constraint_type = next("the constraint type")?;
+        anyhow::ensure!(
+            constraint_type == 0,
+            "Only `≤` capacity constraints (type 0) are supported, not type {constraint_type}"
+        );
+        let capacity = next("the capacity")?;
+        let weights = (0..num_items)
+            .map(|_| next("an item weight"))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        anyhow::ensure!(
+            numbers.next().is_none(),
+            "There was unexpected extra content after the item weights"
+        );
+
+        Self::new(
+            name.trim().to_string(),
+            values,
+            &pair_values,
+            weights,
+            capacity,
+        )
+    }
+}
+
+/// Keeps track of the value and weight of a set of choices for a [`QuadraticKnapsack`]
+/// as single bits are flipped.
+///
+/// Working out the value of a set of choices from scratch takes time proportional to the
+/// square of the number of items. This instead keeps, for every item, how much the value
+/// would change if that item's bit were flipped, so the effect of any flip can be checked
+/// in constant time, and making a flip only takes time proportional to the number of items.
+pub struct QuadraticEvaluator<'a> {
+    knapsack: &'a QuadraticKnapsack,
+    choices: Vec<bool>,
+    value: u64,
+    weight: u64,
+    /// For each item, the value of that item on its own plus the values of the pairs it
+    /// makes with all the _currently chosen_ items. This is how much the total value
+    /// goes up by if the item is added, or down by if it's removed.
+    contributions: Vec<u64>,
+}
+
+impl<'a> QuadraticEvaluator<'a> {
+    /// Start keeping track of the given choices.
+    #[must_use]
+    pub fn new(knapsack: &'a QuadraticKnapsack, choices: &Bitstring) -> Self {
+        let mut evaluator = Self {
+            knapsack,
+            choices: vec![false; knapsack.num_items()],
+            value: 0,
+            weight: 0,
+            contributions: knapsack.values.clone(),
+        };
+        for (index, &included) in choices.iter().enumerate() {
+            if included {
+                evaluator.flip(index);
+            }
+        }
+        evaluator
+    }
+
+    /// The total value of the current choices.
+    #[must_use]
+    pub const fn value(&self) -> u64 {
+        self.value
+    }
+
+    /// The total weight of the current choices.
+    #[must_use]
+    pub const fn weight(&self) -> u64 {
+        self.weight
+    }
+
+    /// The current choices.
+    #[must_use]
+    pub fn choices(&self) -> Bitstring {
+        self.choices.iter().copied().collect()
+    }
+
+    /// How much the value would change by if the bit for the item at `index` were flipped.
+    #[must_use]
+    pub fn value_delta(&self, index: usize) -> i128 {
+        let contribution = i128::from(self.contributions[index]);
+        if self.choices[index] {
+            -contribution
+        } else {
+            contribution
+        }
+    }
+
+    /// What the weight would be if the bit for the item at `index` were flipped.
+    #[must_use]
+    pub fn weight_after_flip(&self, index: usize) -> u64 {
+        let weight = self.knapsack.weights[index];
+        if self.choices[index] {
+            self.weight - weight
+        } else {
+            self.weight + weight
+        }
+    }
+
+    /// Flip the bit for the item at `index`, updating the value and weight.
+    pub fn flip(&mut self, index: usize) {
+        let adding = !self.choices[index];
+        self.weight = self.weight_after_flip(index);
+        if adding {
+            self.value += self.contributions[index];
+        } else {
+            self.value -= self.contributions[index];
+        }
+        self.choices[index] = adding;
+        for (contribution, &pair_value) in self
+            .contributions
+            .iter_mut()
+            .zip(&self.knapsack.pair_values[index])
+        {
+            if adding {
+                *contribution += pair_value;
+            } else {
+                *contribution -= pair_value;
+            }
+        }
+    }
+}
+
+/// A scorer for quadratic knapsacks that returns `CliffScore::Overloaded` if the
+/// choices exceed the capacity, and the total value (including the pair values) otherwise.
+pub struct QuadraticCliffScorer {
+    knapsack: Arc<QuadraticKnapsack>,
+}
+
+impl QuadraticCliffScorer {
+    #[must_use]
+    pub fn new(knapsack: impl Into<Arc<QuadraticKnapsack>>) -> Self {
+        Self {
+            knapsack: knapsack.into(),
+        }
+    }
+
+    /// The knapsack instance this scorer scores choices against.
+    #[must_use]
+    pub fn knapsack(&self) -> &QuadraticKnapsack {
+        &self.knapsack
+    }
+}
+
+impl Scorer<Bitstring> for QuadraticCliffScorer {
+    type Score = CliffScore;
+
+    fn score(&self, genome: &Bitstring) -> Self::Score {
+        if self.knapsack.weight(genome) > self.knapsack.capacity {
+            CliffScore::Overloaded
+        } else {
+            CliffScore::Score(self.knapsack.value(genome))
+        }
+    }
+}
+
+#[expect(clippy::unwrap_used, reason = ".unwrap() is reasonable in tests")]
+#[cfg(test)]
+mod tests {
+    use ec_core::individual::scorer::Scorer;
+    use ec_linear::genome::bitstring::Bitstring;
+    use test_case::test_case;
+
+    use super::{QuadraticCliffScorer, QuadraticEvaluator, QuadraticKnapsack};
+    use crate::cliff_score::CliffScore;
+
+    const TINY: &str = "tiny_qkp\n3\n5 9 2\n1 4\n7\n\n0\n10\n8 6 1\n";
+
+    #[test]
+    fn parse_qkp() {
+        let knapsack = QuadraticKnapsack::parse_qkp(TINY).unwrap();
+        assert_eq!(knapsack.name(), "tiny_qkp");
+        assert_eq!(knapsack.num_items(), 3);
+        assert_eq!(knapsack.values, vec![5, 9, 2]);
+        assert_eq!(
+            knapsack.pair_values,
+            vec![vec![0, 1, 4], vec![1, 0, 7], vec![4, 7, 0]]
+        );
+        assert_eq!(knapsack.weights, vec![8, 6, 1]);
+        assert_eq!(knapsack.capacity(), 10);
+    }
+
+    #[test_case("tiny_qkp\n3\n5 9 2\n1 4\n\n0\n10\n8 6 1\n"; "missing pair value")]
+    #[test_case("tiny_qkp\n3\n5 9 2\n1 4\n7\n\n1\n10\n8 6 1\n"; "unsupported constraint type")]
+    #[test_case("tiny_qkp\n3\n5 9 2\n1 4\n7\n\n0\n10\n8 6 1 3\n"; "extra content")]
+    fn parse_qkp_invalid(contents: &str) {
+        assert!(QuadraticKnapsack::parse_qkp(contents).is_err());
+    }
+
+    #[test_case([false, false, false], 0; "choose no items")]
+    #[test_case([false, true, false], 9; "choose one item")]
+    #[test_case([false, true, true], 18; "choose a pair")]
+    #[test_case([true, true, true], 28; "choose all the items")]
+    fn test_values(choices: [bool; 3], expected_value: u64) {
+        let knapsack = QuadraticKnapsack::parse_qkp(TINY).unwrap();
+        assert_eq!(
+            knapsack.value(&Bitstring::from_iter(choices)),
+            expected_value
+        );
+    }
+
+    #[test_case([false, true, true], CliffScore::Score(18); "fits")]
+    #[test_case([true, true, false], CliffScore::Overloaded; "too heavy")]
+    fn test_scores(choices: [bool; 3], expected_score: CliffScore) {
+        let scorer = QuadraticCliffScorer::new(QuadraticKnapsack::parse_qkp(TINY).unwrap());
+        assert_eq!(scorer.score(&Bitstring::from_iter(choices)), expected_score);
+    }
+
+    #[test]
+    fn evaluator_matches_full_evaluation() {
+        let knapsack = QuadraticKnapsack::parse_qkp(TINY).unwrap();
+        let mut evaluator =
+            QuadraticEvaluator::new(&knapsack, &Bitstring::from_iter([true, false, true]));
+        for index in [1, 0, 2, 1, 1, 0, 2] {
+            let expected_value = i128::from(evaluator.value()) + evaluator.value_delta(index);
+            let expected_weight = evaluator.weight_after_flip(index);
+            evaluator.flip(index);
+            let choices = evaluator.choices();
+            assert_eq!(i128::from(evaluator.value()), expected_value);
+            assert_eq!(evaluator.value(), knapsack.value(&choices));
+            assert_eq!(evaluator.weight(), expected_weight);
+            assert_eq!(evaluator.weight(), knapsack.weight(&choices));
+        }
+    }
+
+    #[test]
+    fn hill_climb_adds_items_that_fit() {
+        let knapsack = QuadraticKnapsack::parse_qkp(TINY).unwrap();
+        let climbed = knapsack
+            .hill_climb(&Bitstring::from_iter([false, true, false]))
+            .choices();
+        assert_eq!(climbed, Bitstring::from_iter([false, true, true]));
+        // Item 0 gets added first here, then item 2, after which item 1 no longer fits.
+        let climbed = knapsack
+            .hill_climb(&Bitstring::from_iter([false, false, false]))
+            .choices();
+        assert_eq!(climbed, Bitstring::from_iter([true, false, true]));
+    }
+}
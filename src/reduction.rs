@@ -0,0 +1,296 @@
+use ec_linear::genome::bitstring::Bitstring;
+
+use crate::{
+    bounds::{by_ratio, greedy},
+    knapsack::Knapsack,
+};
+
+/// A smaller 0/1 knapsack instance made by working out the choice for some of the items
+/// of an original instance ahead of time, along with what's needed to turn a solution to
+/// the smaller instance into one for the original.
+///
+/// The reduction:
+///    - Drops the items that are heavier than the capacity, since they can never fit.
+///    - Takes all the items if they all fit at once (a _trivial_ instance), leaving
+///      nothing to search for.
+///    - Fixes items using the reduced costs from the linear programming relaxation (see
+///      [`crate::bounds::lp_bound`]). If `r` is the value/weight ratio of the critical
+///      item, the reduced cost of item `j` is `d = v_j - r * w_j`, and the best value of
+///      any solution that makes the opposite choice for `j` from the LP (leaving it out
+///      if `d > 0`, taking it if `d < 0`) is at most the LP bound minus `|d|`. If that's
+///      less than the value of the greedy solution, no optimal solution makes that
+///      choice, so we can fix the LP's choice for `j`.
+///    - Drops the items that no longer fit once the fixed items are taken.
+///
+/// The items in the reduced instance keep their ids from the original.
+#[derive(Debug)]
+pub struct Reduction {
+    reduced: Knapsack,
+    /// The index in the original instance of each item in the reduced instance.
+    original_indices: Vec<usize>,
+    /// The indices in the original instance of the items that are always taken.
+    taken: Vec<usize>,
+    /// The ids of the items that are always taken.
+    taken_ids: Vec<u64>,
+    /// The total value of the items that are always taken.
+    taken_value: u64,
+    /// The number of items in the original instance.
+    num_original: usize,
+}
+
+impl Reduction {
+    /// Reduce `knapsack` as described in [`Reduction`].
+    #[must_use]
+    pub fn of(knapsack: &Knapsack) -> Self {
+        let items = knapsack.items();
+        let capacity = knapsack.capacity();
+        let fits = |index: &usize| items[*index].weight() <= capacity;
+        let candidates = (0..items.len()).filter(fits).collect::<Vec<_>>();
+
+        let candidate_weight = candidates.iter().map(|&i| items[i].weight()).sum::<u64>();
+        let (taken, free) = if candidate_weight <= capacity {
+            (candidates, Vec::new())
+        } else {
+            fix_by_reduced_costs(knapsack, candidates)
+        };
+
+        let taken_weight = taken.iter().map(|&i| items[i].weight()).sum::<u64>();
+        let taken_value = taken.iter().map(|&i| items[i].value()).sum();
+        let remaining_capacity = capacity - taken_weight;
+        let original_indices = free
+            .into_iter()
+            .filter(|&i| items[i].weight() <= remaining_capacity)
+            .collect::<Vec<_>>();
+        // These are a subset of the original items, so their totals can't overflow.
+        let reduced = Knapsack::new(
+            original_indices.iter().map(|&i| items[i]).collect(),
+            remaining_capacity,
+        )
+        .with_best_known(
+            knapsack
+                .best_known()
+                .and_then(|best_known| best_known.checked_sub(taken_value)),
+        );
+        Self {
+            reduced,
+            original_indices,
+            taken_ids: taken.iter().map(|&i| items[i].id()).collect(),
+            taken,
+            taken_value,
+            num_original: items.len(),
+        }
+    }
+
+    /// The reduced instance, to search instead of the original.
+    #[must_use]
+    pub const fn reduced(&self) -> &Knapsack {
+        &self.reduced
+    }
+
+    /// Whether every choice was worked out ahead of time, so there's nothing left to search.
+    #[must_use]
+    pub fn is_solved(&self) -> bool {
+        self.reduced.num_items() == 0
+    }
+
+    /// The number of items that are always taken.
+    #[must_use]
+    pub fn num_taken(&self) -> usize {
+        self.taken.len()
+    }
+
+    /// The number of items that are always left out.
+    #[must_use]
+    pub fn num_left_out(&self) -> usize {
+        self.num_original - self.taken.len() - self.original_indices.len()
+    }
+
+    /// The value of a solution to the original instance, given the value of the
+    /// corresponding solution to the reduced instance.
+    #[must_use]
+    pub const fn original_value(&self, reduced_value: u64) -> u64 {
+        self.taken_value + reduced_value
+    }
+
+    /// Turn choices for the items in the reduced instance into choices for the items in
+    /// the original instance.
+    #[must_use]
+    pub fn original_choices(&self, reduced_choices: &Bitstring) -> Bitstring {
+        let mut choices = vec![false; self.num_original];
+        for &index in &self.taken {
+            choices[index] = true;
+        }
+        for (&index, chosen) in self.original_indices.iter().zip(reduced_choices.iter()) {
+            choices[index] = *chosen;
+        }
+        Bitstring::from_iter(choices)
+    }
+
+    /// The ids of the original items chosen by choices for the reduced instance.
+    #[must_use]
+    pub fn original_ids(&self, reduced_choices: &Bitstring) -> Vec<u64> {
+        let mut ids = self.taken_ids.clone();
+        ids.extend(
+            self.reduced
+                .items()
+                .iter()
+                .zip(reduced_choices.iter())
+                .filter_map(|(item, &chosen)| chosen.then_some(item.id())),
+        );
+        ids.sort_unstable();
+        ids
+    }
+}
+
+/// Split the `candidates` (indices of items that fit on their own, which don't all fit
+/// at once) into those that are always taken and those that are still free, leaving
+/// out those that are never taken. See [`Reduction`] for how this works.
+fn fix_by_reduced_costs(knapsack: &Knapsack, candidates: Vec<usize>) -> (Vec<usize>, Vec<usize>) {
+    let items = knapsack.items();
+    let order = by_ratio(knapsack);
+    // Find the critical item, and the value and spare capacity of the whole items before it.
+    let mut lp_value = 0_u64;
+    let mut remaining = knapsack.capacity();
+    let mut critical = None;
+    for item in order.iter().map(|&i| &items[i]) {
+        if item.weight() > remaining {
+            critical = Some(item);
+            break;
+        }
+        remaining -= item.weight();
+        lp_value += item.value();
+    }
+    let Some(critical) = critical else {
+        return (Vec::new(), candidates);
+    };
+    let lower_bound = knapsack.value(&greedy(knapsack));
+
+    // Everything is scaled by the critical item's weight `w_c` so we can use integers:
+    // the gap between the LP bound and the lower bound is
+    // `(lp_value - lower_bound) * w_c + remaining * v_c`, and `|d| * w_c` is
+    // `|v_j * w_c - v_c * w_j|`. If the gap is too big for a `u128`, nothing can be fixed.
+    let (critical_value, critical_weight) =
+        (u128::from(critical.value()), u128::from(critical.weight()));
+    let excess = u128::from(lp_value.saturating_sub(lower_bound)) * critical_weight;
+    let shortfall = u128::from(lower_bound.saturating_sub(lp_value)) * critical_weight;
+    let Some(gap) = excess
+        .checked_add(u128::from(remaining) * critical_value)
+        .and_then(|gap| gap.checked_sub(shortfall))
+    else {
+        return (Vec::new(), candidates);
+    };
+
+    let mut taken = Vec::new();
+    let mut free = Vec::new();
+    for index in candidates {
+        let item = &items[index];
+        let scaled_value = u128::from(item.value()) * critical_weight;
+        let scaled_critical = critical_value * u128::from(item.weight());
+        if scaled_value.abs_diff(scaled_critical) <= gap {
+            free.push(index);
+        } else if scaled_value > scaled_critical {
+            taken.push(index);
+        }
+    }
+    (taken, free)
+}
+
+#[cfg(test)]
+mod tests {
+    use ec_linear::genome::bitstring::Bitstring;
+
+    use super::Reduction;
+    use crate::{item::Item, knapsack::Knapsack};
+
+    /// The best value for `knapsack`, by trying every set of choices.
+    fn brute_force(knapsack: &Knapsack) -> u64 {
+        (0..1_u32 << knapsack.num_items())
+            .map(|bits| Bitstring::from_iter((0..knapsack.num_items()).map(|i| bits >> i & 1 == 1)))
+            .map(|choices| knapsack.value_and_weight(&choices))
+            .filter(|&(_, weight)| weight <= knapsack.capacity())
+            .map(|(value, _)| value)
+            .max()
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn heavy_items_are_dropped() {
+        let items = vec![
+            Item::new(1, 10, 5),
+            Item::new(2, 100, 50),
+            Item::new(3, 4, 4),
+        ];
+        let reduction = Reduction::of(&Knapsack::new(items, 6).with_best_known(Some(10)));
+        assert_eq!(reduction.num_left_out(), 2);
+        assert_eq!(reduction.num_taken(), 1);
+        assert!(reduction.is_solved());
+        assert_eq!(reduction.reduced().best_known(), Some(0));
+        assert_eq!(reduction.original_ids(&Bitstring::from_iter([])), [1]);
+    }
+
+    #[test]
+    fn trivial_instances_take_everything() {
+        let items = vec![Item::new(0, 3, 8), Item::new(1, 2, 8)];
+        let reduction = Reduction::of(&Knapsack::new(items, 10_000_000_000));
+        assert!(reduction.is_solved());
+        assert_eq!(reduction.original_value(0), 5);
+        assert_eq!(
+            reduction.original_choices(&Bitstring::from_iter([])),
+            Bitstring::from_iter([true, true])
+        );
+    }
+
+    #[test]
+    fn reduced_costs_fix_items() {
+        // Item 1 is so good it has to be taken, and item 4 so bad it never is.
+        let items = vec![
+            Item::new(1, 100, 10),
+            Item::new(2, 22, 10),
+            Item::new(3, 21, 10),
+            Item::new(4, 1, 10),
+            Item::new(5, 20, 10),
+        ];
+        let knapsack = Knapsack::new(items, 35);
+        let reduction = Reduction::of(&knapsack);
+        assert_eq!(reduction.num_taken(), 1);
+        assert_eq!(reduction.num_left_out(), 1);
+        let reduced = reduction.reduced();
+        let ids = reduced.items().iter().map(Item::id).collect::<Vec<_>>();
+        assert_eq!(ids, [2, 3, 5]);
+        assert_eq!(reduced.capacity(), 25);
+        assert_eq!(
+            reduction.original_value(brute_force(reduced)),
+            brute_force(&knapsack)
+        );
+
+        let choices = Bitstring::from_iter([true, true, false]);
+        assert_eq!(reduction.original_ids(&choices), [1, 2, 3]);
+        assert_eq!(
+            reduction.original_choices(&choices),
+            Bitstring::from_iter([true, true, true, false, false])
+        );
+    }
+
+    #[test]
+    fn reductions_keep_the_best_value() {
+        // Small instances with lots of ties and near-ties in their ratios.
+        for seed in 0..200_u64 {
+            let items = (1..=10)
+                .map(|id| {
+                    Item::new(
+                        id,
+                        (seed * 7 + id * 13) % 29 + 1,
+                        (seed * 3 + id * 11) % 23 + 1,
+                    )
+                })
+                .collect();
+            let knapsack = Knapsack::new(items, 20 + seed % 60);
+            let reduction = Reduction::of(&knapsack);
+            assert_eq!(
+                reduction.original_value(brute_force(reduction.reduced())),
+                brute_force(&knapsack),
+                "seed {seed}"
+            );
+        }
+    }
+}
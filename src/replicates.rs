@@ -0,0 +1,339 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+};
+
+use anyhow::Context;
+use ec_core::individual::ec::EcIndividual;
+use ec_linear::genome::bitstring::Bitstring;
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64;
+
+use crate::{
+    checkpoint::RunSettings,
+    cliff_score::CliffScore,
+    cliff_scorer::CliffScorer,
+    evolution::{BitstringVariation, Evolution},
+    knapsack::Knapsack,
+    results::{GenerationRecord, ResultsWriter, RunRecord},
+};
+
+/// One of the independent runs (replicates) of a configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replicate {
+    /// Counting from 1, like the `output_N.txt` files from `script.sh`.
+    pub number: usize,
+    pub seed: u64,
+    /// Where to write the structured results of this run.
+    pub results: PathBuf,
+}
+
+impl Replicate {
+    /// Plan `count` replicates, writing their results to `run_<number>.jsonl` in
+    /// `output_dir`. Their seeds are drawn from a generator seeded with `seed`, so the
+    /// whole set of runs can be repeated from that one seed.
+    #[must_use]
+    pub fn plan(count: usize, seed: u64, output_dir: &Path) -> Vec<Self> {
+        let mut rng = Pcg64::seed_from_u64(seed);
+        (1..=count)
+            .map(|number| Self {
+                number,
+                seed: rng.random(),
+                results: output_dir.join(format!("run_{number}.jsonl")),
+            })
+            .collect()
+    }
+}
+
+/// Run the genetic algorithm (our own [`Evolution`], so the run is reproducible from its
+/// seed) with `variation` on `knapsack` for one replicate, writing a record for each
+/// generation and the best individuals at the end to the replicate's results file. Unlike
+/// a run from `main`, this doesn't print anything, since many of these run at once.
+///
+/// This returns the best score in the run.
+///
+/// # Errors
+///
+/// This fails if we can't write the results file.
+pub fn run_replicate(
+    knapsack: &Arc<Knapsack>,
+    variation: BitstringVariation,
+    settings: &RunSettings,
+    replicate: &Replicate,
+) -> anyhow::Result<CliffScore> {
+    let evolution = Evolution::new(
+        CliffScorer::new(Arc::clone(knapsack)),
+        variation,
+        settings.population_size,
+        settings.tournament_size,
+    );
+    let mut rng = Pcg64::seed_from_u64(replicate.seed);
+    let mut results = ResultsWriter::create(&replicate.results)?;
+    let mut population = evolution.initial_population(&mut rng);
+    let mut best_in_run: Option<EcIndividual<Bitstring, CliffScore>> = None;
+    for generation in 0..=settings.max_generations {
+        if generation > 0 {
+            population = evolution.next_generation(&population, &mut rng);
+        }
+        let record =
+            GenerationRecord::of_population(generation, &population, Some(knapsack), false);
+        results.write(&RunRecord::Generation(record))?;
+        let best = best_of(&population);
+        if best_in_run
+            .as_ref()
+            .is_none_or(|best_in_run| best.test_results > best_in_run.test_results)
+        {
+            best_in_run = Some(best.clone());
+        }
+    }
+    let best = best_of(&population);
+    results.write_best(best, best_in_run.as_ref())?;
+    results.finish()?;
+    Ok(best_in_run.map_or(best.test_results, |best| best.test_results))
+}
+
+/// The first of the best individuals in the (non-empty) `population`.
+fn best_of(
+    population: &[EcIndividual<Bitstring, CliffScore>],
+) -> &EcIndividual<Bitstring, CliffScore> {
+    population
+        .iter()
+        .reduce(|best, individual| {
+            if individual.test_results > best.test_results {
+                individual
+            } else {
+                best
+            }
+        })
+        .unwrap_or_else(|| unreachable!("Populations always have at least one individual"))
+}
+
+/// Run all the `replicates` with a pool of `threads` workers, each of which repeatedly
+/// takes the next replicate that hasn't been started and calls `run` on it. The
+/// evaluation within each run (which is also parallel) shares a pool of the same number
+/// of threads, so `threads` is about how many cores this uses in total.
+///
+/// `progress` is called (on this thread) each time a replicate finishes, with the
+/// replicate, its outcome, and how many replicates have finished so far. The outcomes
+/// are returned in the same order as `replicates`.
+///
+/// # Errors
+///
+/// This fails if we can't create the thread pool, or if any of the runs fail; once one
+/// has failed, no more are started, and the error is for the first one that failed.
+pub fn run_all<T: Send>(
+    replicates: &[Replicate],
+    threads: usize,
+    run: impl Fn(&Replicate) -> anyhow::Result<T> + Sync,
+    mut progress: impl FnMut(&Replicate, &T, usize),
+) -> anyhow::Result<Vec<T>> {
+    let threads = threads.max(1);
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .context("Failed to create the thread pool for evaluation")?;
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let (sender, receiver) = mpsc::channel();
+
+    thread::scope(|scope| {
+        for _ in 0..threads.min(replicates.len()) {
+            let sender = sender.clone();
+            let (pool, next, failed, run) = (&pool, &next, &failed, &run);
+            scope.spawn(move || {
+                while !failed.load(Ordering::Relaxed) {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(replicate) = replicates.get(index) else {
+                        break;
+                    };
+                    let outcome = pool.install(|| run(replicate));
+                    if outcome.is_err() {
+                        failed.store(true, Ordering::Relaxed);
+                    }
+                    if sender.send((index, outcome)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        // Only the workers' senders are left, so the loop below ends once they've all
+        // finished.
+        drop(sender);
+
+        let mut outcomes = replicates.iter().map(|_| None).collect::<Vec<_>>();
+        let mut first_error = None;
+        let mut finished = 0;
+        for (index, outcome) in receiver {
+            let replicate = &replicates[index];
+            match outcome {
+                Ok(outcome) => {
+                    finished += 1;
+                    progress(replicate, &outcome, finished);
+                    outcomes[index] = Some(outcome);
+                }
+                Err(error) => {
+                    first_error.get_or_insert_with(|| {
+                        error.context(format!(
+                            "Run {} (with seed {}) failed",
+                            replicate.number, replicate.seed
+                        ))
+                    });
+                }
+            }
+        }
+        first_error.map_or_else(|| Ok(outcomes.into_iter().flatten().collect()), Err)
+    })
+}
+
+/// Write a CSV file listing each replicate's seed, best score, and results file, so any
+/// one run can be found again, or repeated on its own from its seed.
+///
+/// # Errors
+///
+/// This fails if we can't create or write to `path`.
+pub fn write_summary(
+    path: &Path,
+    replicates: &[Replicate],
+    bests: &[CliffScore],
+) -> anyhow::Result<()> {
+    let file = File::create(path).with_context(|| format!("Failed to create the file {path:?}"))?;
+    let mut writer = BufWriter::new(file);
+    writeln!(writer, "run,seed,best,results")?;
+    for (replicate, best) in replicates.iter().zip(bests) {
+        let best = match best {
+            CliffScore::Score(value) => value.to_string(),
+            CliffScore::Overloaded => "overloaded".to_string(),
+        };
+        writeln!(
+            writer,
+            "{},{},{best},{}",
+            replicate.number,
+            replicate.seed,
+            replicate.results.display()
+        )?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[expect(clippy::unwrap_used, reason = ".unwrap() is reasonable in tests")]
+#[cfg(test)]
+mod tests {
+    use std::{
+        env, fs,
+        path::Path,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use super::{run_all, run_replicate, Replicate};
+    use crate::{
+        checkpoint::RunSettings,
+        cli::ProblemKind,
+        evolution::BitstringVariation,
+        item::Item,
+        knapsack::Knapsack,
+        results::{read_records, RunRecord},
+    };
+
+    #[test]
+    fn plans_are_reproducible() {
+        let plan = Replicate::plan(5, 42, Path::new("out"));
+        assert_eq!(plan, Replicate::plan(5, 42, Path::new("out")));
+        assert_eq!(plan[0].number, 1);
+        assert_eq!(plan[4].results, Path::new("out/run_5.jsonl"));
+        assert_ne!(plan[0].seed, plan[1].seed);
+        assert_ne!(
+            plan[0].seed,
+            Replicate::plan(1, 43, Path::new("out"))[0].seed
+        );
+    }
+
+    #[test]
+    fn runs_every_replicate_once_in_order() {
+        let replicates = Replicate::plan(20, 0, Path::new("out"));
+        let calls = AtomicUsize::new(0);
+        let mut finished = Vec::new();
+        let outcomes = run_all(
+            &replicates,
+            4,
+            |replicate| {
+                calls.fetch_add(1, Ordering::Relaxed);
+                Ok(replicate.number * 10)
+            },
+            |_, _, count| finished.push(count),
+        )
+        .unwrap();
+        assert_eq!(calls.load(Ordering::Relaxed), 20);
+        assert_eq!(outcomes, (1..=20).map(|n| n * 10).collect::<Vec<_>>());
+        assert_eq!(finished, (1..=20).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn failures_stop_the_pool() {
+        let replicates = Replicate::plan(50, 0, Path::new("out"));
+        let error = run_all(
+            &replicates,
+            1,
+            |replicate| {
+                anyhow::ensure!(replicate.number != 3, "three is unlucky");
+                Ok(())
+            },
+            |_, (), _| {},
+        )
+        .unwrap_err();
+        assert!(error.to_string().starts_with("Run 3 "), "{error}");
+    }
+
+    #[test]
+    fn replicates_are_reproducible() {
+        let knapsack = Arc::new(Knapsack::new(
+            vec![
+                Item::new(1, 5, 8),
+                Item::new(2, 9, 6),
+                Item::new(3, 2, 7),
+                Item::new(4, 4, 3),
+            ],
+            15,
+        ));
+        let settings = RunSettings {
+            knapsack: "test".into(),
+            problem: ProblemKind::Knapsack,
+            instance: 0,
+            format: None,
+            reduce: false,
+            tournament_size: 2,
+            population_size: 20,
+            max_generations: 5,
+        };
+        let directory = env::temp_dir().join(format!("knapsack-replicates-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let first = Replicate::plan(1, 7, &directory).remove(0);
+        let second = Replicate {
+            results: directory.join("again.jsonl"),
+            ..first.clone()
+        };
+        let variation = BitstringVariation::new(4);
+        let best = run_replicate(&knapsack, variation, &settings, &first).unwrap();
+        let again = run_replicate(&knapsack, variation, &settings, &second).unwrap();
+        let records = read_records(&first.results).unwrap();
+        let records_again = read_records(&second.results).unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(again, best);
+        assert_eq!(records, records_again);
+        let generations = records
+            .iter()
+            .filter(|record| matches!(record, RunRecord::Generation(_)))
+            .count();
+        assert_eq!(generations, 6);
+    }
+}
@@ -0,0 +1,288 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+use crate::{
+    bounds::lp_bound,
+    cliff_score::CliffScore,
+    comparison::{best_in_run, RunGroup},
+    knapsack::Knapsack,
+    statistics::Description,
+};
+
+/// The groups of runs in an experiment directory, by instance and then configuration.
+///
+/// Each subdirectory of the experiment is either
+///    - named `<configuration>_pID_<instance>` and holds the results files for that
+///      configuration on that instance (like `ts_2_pID_Sm3`, as imported from `Outputs/`),
+///      or
+///    - named after an instance, with a subdirectory of results files for each
+///      configuration (like `SmallProblem3/ts_2`).
+///
+/// # Errors
+///
+/// This fails if we can't read the experiment directory or its subdirectories.
+pub fn find_groups(experiment: &Path) -> anyhow::Result<BTreeMap<String, Vec<RunGroup>>> {
+    let mut instances = BTreeMap::<String, Vec<RunGroup>>::new();
+    for directory in subdirectories(experiment)? {
+        let name = file_name(&directory);
+        if let Some((configuration, instance)) = name.split_once("_pID_") {
+            instances
+                .entry(instance.to_string())
+                .or_default()
+                .push(RunGroup {
+                    name: configuration.to_string(),
+                    path: directory,
+                });
+        } else {
+            for configuration in subdirectories(&directory)? {
+                instances.entry(name.clone()).or_default().push(RunGroup {
+                    name: file_name(&configuration),
+                    path: configuration,
+                });
+            }
+        }
+    }
+    Ok(instances)
+}
+
+fn subdirectories(directory: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut subdirectories = fs::read_dir(directory)
+        .with_context(|| format!("Failed to read the directory {directory:?}"))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    subdirectories.retain(|path| path.is_dir());
+    subdirectories.sort();
+    Ok(subdirectories)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// The bound we measure the gap to for an instance: its best known value if the
+/// instance file has one, and otherwise its linear programming bound (see [`lp_bound`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    BestKnown(u64),
+    Lp(u64),
+}
+
+impl Bound {
+    #[must_use]
+    pub fn of(knapsack: &Knapsack) -> Self {
+        knapsack
+            .best_known()
+            .map_or_else(|| Self::Lp(lp_bound(knapsack)), Self::BestKnown)
+    }
+
+    #[must_use]
+    pub const fn value(self) -> u64 {
+        match self {
+            Self::BestKnown(value) | Self::Lp(value) => value,
+        }
+    }
+}
+
+/// One row of a results table: how a configuration did over all its runs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultsRow {
+    pub configuration: String,
+    /// The best value of each run that found a legal solution.
+    pub feasible: Vec<u64>,
+    pub runs: usize,
+}
+
+impl ResultsRow {
+    /// Read the best score of each run in `group`.
+    ///
+    /// # Errors
+    ///
+    /// This fails if any of the group's results files can't be read.
+    pub fn read(group: &RunGroup) -> anyhow::Result<Self> {
+        let files = group.results_files()?;
+        let mut feasible = Vec::new();
+        for path in &files {
+            if let CliffScore::Score(value) = best_in_run(path)? {
+                feasible.push(value);
+            }
+        }
+        Ok(Self {
+            configuration: group.name.clone(),
+            feasible,
+            runs: files.len(),
+        })
+    }
+}
+
+/// Write a Markdown table for an instance, in the same layout as the sack table in the
+/// README. The best, mean, and median are over the runs that found a legal solution,
+/// and the gap is how far the best is below `bound`, as a percentage of the bound.
+///
+/// # Errors
+///
+/// This fails if we can't write to `writer`.
+pub fn write_table(
+    instance: &str,
+    rows: &[ResultsRow],
+    bound: Option<Bound>,
+    mut writer: impl Write,
+) -> anyhow::Result<()> {
+    writeln!(writer, "### {instance}")?;
+    writeln!(writer)?;
+    match bound {
+        Some(Bound::BestKnown(value)) => {
+            writeln!(
+                writer,
+                "Best known value: {}",
+                with_commas(&value.to_string())
+            )?;
+            writeln!(writer)?;
+        }
+        Some(Bound::Lp(value)) => {
+            writeln!(writer, "LP bound: {}", with_commas(&value.to_string()))?;
+            writeln!(writer)?;
+        }
+        None => {}
+    }
+    writeln!(
+        writer,
+        "Configuration | Best | Mean | Median | Feasible runs | Gap to bound |"
+    )?;
+    writeln!(
+        writer,
+        "--------------|------|------|--------|---------------|--------------|"
+    )?;
+    for row in rows {
+        let values = row
+            .feasible
+            .iter()
+            .map(|&value| value as f64)
+            .collect::<Vec<_>>();
+        let best = row.feasible.iter().max().copied();
+        let (mean, median) = Description::of(&values).map_or_else(
+            || ("-".to_string(), "-".to_string()),
+            |description| {
+                (
+                    with_commas(&format!("{:.1}", description.mean)),
+                    with_commas(&format!("{:.1}", description.median)),
+                )
+            },
+        );
+        let gap = match (best, bound) {
+            (Some(best), Some(bound)) if bound.value() > 0 => {
+                let gap = bound.value().saturating_sub(best) as f64 / bound.value() as f64;
+                format!("{:.2}%", gap * 100.0)
+            }
+            _ => "-".to_string(),
+        };
+        writeln!(
+            writer,
+            "{} | {} | {mean} | {median} | {}/{} | {gap} |",
+            row.configuration,
+            best.map_or_else(|| "-".to_string(), |best| with_commas(&best.to_string())),
+            row.feasible.len(),
+            row.runs
+        )?;
+    }
+    writeln!(writer)?;
+    Ok(())
+}
+
+/// Write a Markdown report with a table (see [`write_table`]) for each instance in the
+/// `experiment` directory (see [`find_groups`]). `instances` gives the instance file
+/// for some (or all) of the instance names, to work out the bound for the gap column.
+///
+/// # Errors
+///
+/// This fails if we can't read the experiment, or write to `writer`.
+pub fn write_report(
+    experiment: &Path,
+    instances: &HashMap<String, Knapsack>,
+    mut writer: impl Write,
+) -> anyhow::Result<()> {
+    let groups = find_groups(experiment)?;
+    anyhow::ensure!(
+        !groups.is_empty(),
+        "There aren't any groups of runs in {experiment:?}"
+    );
+    for (instance, groups) in groups {
+        let rows = groups
+            .iter()
+            .map(ResultsRow::read)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let bound = instances.get(&instance).map(Bound::of);
+        write_table(&instance, &rows, bound, &mut writer)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Put commas between groups of three digits in the whole part of `number`, like
+/// `25,000` or `4,909,737.2`.
+fn with_commas(number: &str) -> String {
+    let (whole, fraction) = number
+        .find('.')
+        .map_or((number, ""), |point| number.split_at(point));
+    let mut result = String::new();
+    for (index, digit) in whole.chars().enumerate() {
+        if index > 0 && (whole.len() - index).is_multiple_of(3) {
+            result.push(',');
+        }
+        result.push(digit);
+    }
+    result + fraction
+}
+
+#[expect(clippy::unwrap_used, reason = ".unwrap() is reasonable in tests")]
+#[cfg(test)]
+mod tests {
+    use super::{with_commas, write_table, Bound, ResultsRow};
+
+    #[test]
+    fn commas() {
+        assert_eq!(with_commas("0"), "0");
+        assert_eq!(with_commas("999"), "999");
+        assert_eq!(with_commas("25000"), "25,000");
+        assert_eq!(with_commas("10000000000"), "10,000,000,000");
+        assert_eq!(with_commas("4909737.25"), "4,909,737.25");
+    }
+
+    #[test]
+    fn table_layout() {
+        let rows = vec![
+            ResultsRow {
+                configuration: "ts_2".to_string(),
+                feasible: vec![1_900, 1_950, 2_000],
+                runs: 4,
+            },
+            ResultsRow {
+                configuration: "ts_8".to_string(),
+                feasible: Vec::new(),
+                runs: 2,
+            },
+        ];
+        let mut table = Vec::new();
+        write_table("Sm3", &rows, Some(Bound::Lp(4_000)), &mut table).unwrap();
+        let table = String::from_utf8(table).unwrap();
+        let lines = table.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "### Sm3");
+        assert_eq!(lines[2], "LP bound: 4,000");
+        assert_eq!(
+            lines[4],
+            "Configuration | Best | Mean | Median | Feasible runs | Gap to bound |"
+        );
+        assert_eq!(
+            lines[6],
+            "ts_2 | 2,000 | 1,950.0 | 1,950.0 | 3/4 | 50.00% |"
+        );
+        assert_eq!(lines[7], "ts_8 | - | - | - | 0/2 | - |");
+    }
+}
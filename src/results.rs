@@ -0,0 +1,353 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::Context;
+use course_helpers::statistics::entropy;
+use ec_core::individual::ec::EcIndividual;
+use ec_linear::genome::bitstring::Bitstring;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cliff_score::CliffScore, diversity::Diversity, knapsack::Knapsack,
+    population_stats::PopulationStats,
+};
+
+/// One record in the structured (JSON Lines) output of a run.
+///
+/// Each line of a results file is one of these, tagged with a `record` field
+/// saying which kind of record it is, so the file can be read back with
+/// [`serde_json`] without knowing the order of the records in advance.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+pub enum RunRecord {
+    Generation(GenerationRecord),
+    Best(BestRecord),
+}
+
+/// What we know about the population after a single generation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GenerationRecord {
+    pub generation: usize,
+    /// The best score in this generation.
+    pub best: CliffScore,
+    /// The entropy as computed by `course_helpers::statistics::entropy`.
+    pub entropy: f64,
+    /// This is `None` for runs imported from old logs (see [`crate::legacy`]), which
+    /// didn't record it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diversity: Option<Diversity>,
+    /// Statistics over the whole population; this is `None` if the population was empty.
+    pub population: Option<PopulationStats>,
+}
+
+impl GenerationRecord {
+    /// Work out the record for `population`. The population statistics are only available
+    /// for single capacity knapsacks, i.e., when `knapsack` is given.
+    #[must_use]
+    pub fn of_population(
+        generation: usize,
+        population: &Vec<EcIndividual<Bitstring, CliffScore>>,
+        knapsack: Option<&Knapsack>,
+        include_locus_frequencies: bool,
+    ) -> Self {
+        Self {
+            generation,
+            best: population
+                .iter()
+                .map(|individual| individual.test_results)
+                .max()
+                .unwrap_or_default(),
+            entropy: entropy(population),
+            diversity: Some(Diversity::of_population(
+                population,
+                include_locus_frequencies,
+            )),
+            population: knapsack
+                .and_then(|knapsack| PopulationStats::of_population(knapsack, population)),
+        }
+    }
+}
+
+/// The best individual at the end of a run, written after all the generations.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BestRecord {
+    pub scope: BestScope,
+    pub score: CliffScore,
+    #[serde(with = "bitstring_as_string")]
+    pub genome: Bitstring,
+}
+
+/// Which best individual a [`BestRecord`] is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BestScope {
+    /// The best in the final generation.
+    FinalGeneration,
+    /// The best in any generation of the run.
+    Run,
+}
+
+impl BestRecord {
+    #[must_use]
+    pub fn new(scope: BestScope, individual: &EcIndividual<Bitstring, CliffScore>) -> Self {
+        Self {
+            scope,
+            score: individual.test_results,
+            genome: individual.genome.clone(),
+        }
+    }
+}
+
+/// Read all the records in a results file.
+///
+/// # Errors
+///
+/// This fails if the file can't be read, or any line isn't a [`RunRecord`].
+pub fn read_records(path: impl AsRef<Path>) -> anyhow::Result<Vec<RunRecord>> {
+    let path = path.as_ref();
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read the results file {path:?}"))?;
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(line_number, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("Failed to parse line {} of {path:?}", line_number + 1))
+        })
+        .collect()
+}
+
+/// Writes [`RunRecord`]s to a file, one JSON object per line.
+pub struct ResultsWriter {
+    writer: BufWriter<File>,
+}
+
+impl ResultsWriter {
+    /// Create (or truncate) the results file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// This fails if the file can't be created.
+    pub fn create(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let file = File::create(path.as_ref())
+            .with_context(|| format!("Failed to create the results file {:?}", path.as_ref()))?;
+        Ok(Self {
+            writer: BufWriter::new(file),
+        })
+    }
+
+    /// Reopen the results file at `path` to continue a run that was resumed from a
+    /// checkpoint taken after `generation`. Any records for generations after that
+    /// (written before the run was interrupted) are dropped, since they'll be written
+    /// again as the run continues. So is anything that can't be parsed after the record
+    /// for `generation`, like a line that was only half written when the run crashed.
+    ///
+    /// # Errors
+    ///
+    /// This fails if the existing file can't be read, any of the records up to
+    /// `generation` can't be parsed, or the file can't be rewritten.
+    pub fn resume(path: impl AsRef<Path>, generation: usize) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let existing = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read the results file {path:?} to resume it"))?;
+        let mut kept = Vec::new();
+        let mut reached_checkpoint = false;
+        for (line_number, line) in existing.lines().enumerate() {
+            match serde_json::from_str::<RunRecord>(line) {
+                // The best records come at the end of a run, and will be written again.
+                Ok(RunRecord::Best(_)) => break,
+                Ok(RunRecord::Generation(record)) if record.generation > generation => break,
+                Ok(RunRecord::Generation(record)) => {
+                    reached_checkpoint |= record.generation == generation;
+                    kept.push(line);
+                }
+                // Everything after the checkpoint will be written again anyway.
+                Err(_) if reached_checkpoint => break,
+                Err(error) => {
+                    return Err(error).with_context(|| {
+                        format!("Failed to parse line {} of {path:?}", line_number + 1)
+                    })
+                }
+            }
+        }
+        // Only rewrite the file once we know we can, so a failure doesn't lose anything.
+        let mut results = Self::create(path)?;
+        for line in kept {
+            // Copy the line over as is, rather than re-serializing the record we parsed,
+            // since floating point numbers don't always survive a round trip exactly.
+            writeln!(results.writer, "{line}")?;
+        }
+        Ok(results)
+    }
+
+    /// Append a single record to the results file.
+    ///
+    /// # Errors
+    ///
+    /// This fails if the record can't be written to the file.
+    pub fn write(&mut self, record: &RunRecord) -> anyhow::Result<()> {
+        serde_json::to_writer(&mut self.writer, record)?;
+        writeln!(self.writer)?;
+        Ok(())
+    }
+
+    /// Write the [`BestRecord`]s for the end of a run: the best in the final generation,
+    /// and the best in the whole run (if there was one).
+    ///
+    /// # Errors
+    ///
+    /// This fails if the records can't be written to the file.
+    pub fn write_best(
+        &mut self,
+        final_generation: &EcIndividual<Bitstring, CliffScore>,
+        run: Option<&EcIndividual<Bitstring, CliffScore>>,
+    ) -> anyhow::Result<()> {
+        self.write(&RunRecord::Best(BestRecord::new(
+            BestScope::FinalGeneration,
+            final_generation,
+        )))?;
+        if let Some(run) = run {
+            self.write(&RunRecord::Best(BestRecord::new(BestScope::Run, run)))?;
+        }
+        Ok(())
+    }
+
+    /// Flush any buffered records out to the file.
+    ///
+    /// # Errors
+    ///
+    /// This fails if the buffered records can't be written to the file.
+    pub fn flush(&mut self) -> anyhow::Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Flush any buffered records out to the file and close it.
+    ///
+    /// # Errors
+    ///
+    /// This fails if the buffered records can't be written to the file.
+    pub fn finish(mut self) -> anyhow::Result<()> {
+        self.flush()
+    }
+}
+
+/// (De)serialize a `Bitstring` as a string of `0`s and `1`s, which is a lot more compact
+/// (and readable) than a list of `true`s and `false`s. Use this with
+/// `#[serde(with = "bitstring_as_string")]`.
+pub mod bitstring_as_string {
+    use ec_linear::genome::bitstring::Bitstring;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    /// Serialize `bits` as a string of `0`s and `1`s.
+    ///
+    /// # Errors
+    ///
+    /// This fails if the serializer fails.
+    pub fn serialize<S: Serializer>(bits: &Bitstring, serializer: S) -> Result<S::Ok, S::Error> {
+        let string = bits
+            .iter()
+            .map(|&bit| if bit { '1' } else { '0' })
+            .collect::<String>();
+        serializer.serialize_str(&string)
+    }
+
+    /// Deserialize a string of `0`s and `1`s into a `Bitstring`.
+    ///
+    /// # Errors
+    ///
+    /// This fails if the string contains anything other than `0`s and `1`s.
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Bitstring, D::Error> {
+        let string = String::deserialize(deserializer)?;
+        string
+            .chars()
+            .map(|c| match c {
+                '0' => Ok(false),
+                '1' => Ok(true),
+                _ => Err(D::Error::custom(format!(
+                    "Found '{c}' in a bitstring, which should only contain '0's and '1's"
+                ))),
+            })
+            .collect()
+    }
+}
+
+#[expect(clippy::unwrap_used, reason = ".unwrap() is reasonable in tests")]
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use ec_linear::genome::bitstring::Bitstring;
+    use serde::{Deserialize, Serialize};
+
+    use super::{read_records, GenerationRecord, ResultsWriter, RunRecord};
+    use crate::{cliff_score::CliffScore, diversity::Diversity};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Wrapper(#[serde(with = "super::bitstring_as_string")] Bitstring);
+
+    #[test]
+    fn bitstrings_as_strings() {
+        let bits = Wrapper(Bitstring::from_iter([true, false, false, true]));
+        let json = serde_json::to_string(&bits).unwrap();
+        assert_eq!(json, r#""1001""#);
+        assert_eq!(serde_json::from_str::<Wrapper>(&json).unwrap(), bits);
+        assert!(serde_json::from_str::<Wrapper>(r#""10x1""#).is_err());
+    }
+
+    #[test]
+    fn generation_records_round_trip() {
+        let record = RunRecord::Generation(GenerationRecord {
+            generation: 7,
+            best: CliffScore::Score(42),
+            entropy: 1.5,
+            diversity: Some(Diversity {
+                mean_hamming_distance: 3.25,
+                unique_genomes: 10,
+                distinct_fitnesses: 4,
+                locus_frequencies: None,
+            }),
+            population: None,
+        });
+        let json = serde_json::to_string(&record).unwrap();
+        assert!(json.starts_with(r#"{"record":"generation","generation":7"#));
+        assert_eq!(serde_json::from_str::<RunRecord>(&json).unwrap(), record);
+    }
+
+    #[test]
+    fn resuming_drops_a_half_written_last_line() {
+        let record = |generation| {
+            serde_json::to_string(&RunRecord::Generation(GenerationRecord {
+                generation,
+                best: CliffScore::Score(42),
+                entropy: 1.5,
+                diversity: None,
+                population: None,
+            }))
+            .unwrap()
+        };
+        let path = env::temp_dir().join(format!("knapsack-resume-{}.jsonl", std::process::id()));
+        let half_written = &record(3)[..20];
+        fs::write(
+            &path,
+            format!("{}\n{}\n{half_written}", record(1), record(2)),
+        )
+        .unwrap();
+        ResultsWriter::resume(&path, 2).unwrap().finish().unwrap();
+        let records = read_records(&path).unwrap();
+
+        // A bad line before the checkpoint is still an error, and leaves the file alone.
+        let contents = format!("{}\n{half_written}\n{}\n", record(1), record(2));
+        fs::write(&path, &contents).unwrap();
+        assert!(ResultsWriter::resume(&path, 2).is_err());
+        let unchanged = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(unchanged, contents);
+    }
+}
@@ -0,0 +1,257 @@
+use std::f64::consts::SQRT_2;
+
+/// The `q`th quantile of the (non-empty, sorted) `numbers`, interpolating linearly
+/// between the two closest ranks.
+#[must_use]
+pub fn quantile(numbers: &[f64], q: f64) -> f64 {
+    let rank = q * (numbers.len() - 1) as f64;
+    let (below, above) = (rank.floor() as usize, rank.ceil() as usize);
+    numbers[below] + (numbers[above] - numbers[below]) * (rank - below as f64)
+}
+
+/// The mean, median, standard deviation, and 95% confidence interval for the mean of a
+/// sample.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Description {
+    pub count: usize,
+    pub mean: f64,
+    pub median: f64,
+    /// The sample standard deviation (dividing by `count - 1`), which is 0 for a single
+    /// number.
+    pub standard_deviation: f64,
+    /// The 95% confidence interval for the mean, using Student's t-distribution.
+    /// This is `None` for a single number.
+    pub confidence_interval: Option<(f64, f64)>,
+}
+
+impl Description {
+    /// Describe `numbers`, returning `None` if there aren't any.
+    #[must_use]
+    pub fn of(numbers: &[f64]) -> Option<Self> {
+        if numbers.is_empty() {
+            return None;
+        }
+        let mut sorted = numbers.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let count = numbers.len();
+        let mean = numbers.iter().sum::<f64>() / count as f64;
+        let standard_deviation = if count > 1 {
+            let squares = numbers.iter().map(|x| (x - mean).powi(2)).sum::<f64>();
+            (squares / (count - 1) as f64).sqrt()
+        } else {
+            0.0
+        };
+        let confidence_interval = (count > 1).then(|| {
+            let half_width = t_critical_95(count - 1) * standard_deviation / (count as f64).sqrt();
+            (mean - half_width, mean + half_width)
+        });
+        Some(Self {
+            count,
+            mean,
+            median: quantile(&sorted, 0.5),
+            standard_deviation,
+            confidence_interval,
+        })
+    }
+}
+
+/// The two-sided 95% critical value of Student's t-distribution with `degrees_of_freedom`
+/// degrees of freedom. This uses a table up to 30, and the Cornish-Fisher expansion
+/// (which is accurate to about 4 decimal places there) beyond that.
+fn t_critical_95(degrees_of_freedom: usize) -> f64 {
+    const TABLE: [f64; 30] = [
+        12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+        2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+        2.052, 2.048, 2.045, 2.042,
+    ];
+    if let Some(&t) = TABLE.get(degrees_of_freedom.wrapping_sub(1)) {
+        return t;
+    }
+    let z: f64 = 1.959_964;
+    let df = degrees_of_freedom as f64;
+    z + (z.powi(3) + z) / (4.0 * df)
+        + (5.0 * z.powi(5) + 16.0 * z.powi(3) + 3.0 * z) / (96.0 * df.powi(2))
+}
+
+/// The result of comparing two samples with the Mann-Whitney U test (also known as the
+/// Wilcoxon rank-sum test).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RankSumTest {
+    /// The U statistic for the first sample: the number of pairs where the first sample's
+    /// number is bigger, counting ties as a half.
+    pub u: f64,
+    /// The two-sided p-value, from the normal approximation with corrections for ties
+    /// and continuity. This is reasonable for samples of about 10 or more.
+    pub p_value: f64,
+    /// The Vargha-Delaney A12 effect size: the probability that a number from the first
+    /// sample is bigger than one from the second (counting ties as a half). 0.5 means
+    /// there's no difference.
+    pub a12: f64,
+}
+
+impl RankSumTest {
+    /// Compare `first` and `second`, returning `None` if either is empty.
+    #[must_use]
+    pub fn of(first: &[f64], second: &[f64]) -> Option<Self> {
+        if first.is_empty() || second.is_empty() {
+            return None;
+        }
+        let (n1, n2) = (first.len() as f64, second.len() as f64);
+        let mut all = first
+            .iter()
+            .map(|&x| (x, true))
+            .chain(second.iter().map(|&x| (x, false)))
+            .collect::<Vec<_>>();
+        all.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        // Give tied numbers the average of their ranks (which count from 1).
+        let mut first_rank_sum = 0.0;
+        let mut tie_correction = 0.0;
+        let mut start = 0;
+        while start < all.len() {
+            let end = start + all[start..].partition_point(|&(x, _)| x == all[start].0);
+            let ties = (end - start) as f64;
+            let rank = (start + end + 1) as f64 / 2.0;
+            let in_first = all[start..end].iter().filter(|&&(_, first)| first).count();
+            first_rank_sum += rank * in_first as f64;
+            tie_correction += ties.powi(3) - ties;
+            start = end;
+        }
+
+        let u = first_rank_sum - n1 * (n1 + 1.0) / 2.0;
+        let n = n1 + n2;
+        let variance = n1 * n2 / 12.0 * ((n + 1.0) - tie_correction / (n * (n - 1.0)));
+        let p_value = if variance > 0.0 {
+            let difference = (u - n1 * n2 / 2.0).abs();
+            let z = (difference - 0.5).max(0.0) / variance.sqrt();
+            (2.0 * (1.0 - normal_cdf(z))).min(1.0)
+        } else {
+            // Every number is the same.
+            1.0
+        };
+        Some(Self {
+            u,
+            p_value,
+            a12: u / (n1 * n2),
+        })
+    }
+
+    /// How big the effect is, using Vargha and Delaney's thresholds.
+    #[must_use]
+    pub fn magnitude(&self) -> &'static str {
+        match (self.a12 - 0.5).abs() {
+            d if d < 0.06 => "negligible",
+            d if d < 0.14 => "small",
+            d if d < 0.21 => "medium",
+            _ => "large",
+        }
+    }
+}
+
+/// The cumulative distribution function of the standard normal distribution.
+fn normal_cdf(z: f64) -> f64 {
+    0.5 * erfc(-z / SQRT_2)
+}
+
+/// The complementary error function, using the Chebyshev approximation from Numerical
+/// Recipes, which has a relative error of less than 1.2e-7 everywhere.
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let polynomial = [
+        -1.265_512_23,
+        1.000_023_68,
+        0.374_091_96,
+        0.096_784_18,
+        -0.186_288_06,
+        0.278_868_07,
+        -1.135_203_98,
+        1.488_515_87,
+        -0.822_152_23,
+        0.170_872_77,
+    ]
+    .iter()
+    .rev()
+    .fold(0.0, |sum, coefficient| sum * t + coefficient);
+    let result = t * (-z * z + polynomial).exp();
+    if x >= 0.0 {
+        result
+    } else {
+        2.0 - result
+    }
+}
+
+#[expect(clippy::unwrap_used, reason = ".unwrap() is reasonable in tests")]
+#[cfg(test)]
+mod tests {
+    use super::{normal_cdf, quantile, t_critical_95, Description, RankSumTest};
+
+    fn close(a: f64, b: f64, tolerance: f64) -> bool {
+        (a - b).abs() < tolerance
+    }
+
+    #[test]
+    fn quantiles_interpolate() {
+        let numbers = [1.0, 2.0, 3.0, 4.0];
+        assert!(close(quantile(&numbers, 0.0), 1.0, 1e-12));
+        assert!(close(quantile(&numbers, 0.5), 2.5, 1e-12));
+        assert!(close(quantile(&numbers, 1.0), 4.0, 1e-12));
+    }
+
+    #[test]
+    fn describe_a_sample() {
+        let description = Description::of(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]).unwrap();
+        assert_eq!(description.count, 8);
+        assert!(close(description.mean, 5.0, 1e-12));
+        assert!(close(description.median, 4.5, 1e-12));
+        assert!(close(description.standard_deviation, 2.138_090, 1e-6));
+        let (low, high) = description.confidence_interval.unwrap();
+        assert!(close(low, 3.212_5, 1e-3) && close(high, 6.787_5, 1e-3));
+
+        let single = Description::of(&[3.0]).unwrap();
+        assert!(single.confidence_interval.is_none());
+        assert!(Description::of(&[]).is_none());
+    }
+
+    #[test]
+    fn t_critical_values() {
+        assert!(close(t_critical_95(1), 12.706, 1e-9));
+        assert!(close(t_critical_95(30), 2.042, 1e-9));
+        assert!(close(t_critical_95(40), 2.021, 1e-3));
+        assert!(close(t_critical_95(120), 1.980, 1e-3));
+    }
+
+    #[test]
+    fn normal_distribution() {
+        assert!(close(normal_cdf(0.0), 0.5, 1e-7));
+        assert!(close(normal_cdf(1.959_964), 0.975, 1e-6));
+        assert!(close(normal_cdf(-1.0), 0.158_655, 1e-6));
+    }
+
+    #[test]
+    fn rank_sum_test() {
+        // Every number in the first sample is bigger.
+        let first = (11..=20).map(f64::from).collect::<Vec<_>>();
+        let second = (1..=10).map(f64::from).collect::<Vec<_>>();
+        let test = RankSumTest::of(&first, &second).unwrap();
+        assert!(close(test.u, 100.0, 1e-12));
+        assert!(close(test.a12, 1.0, 1e-12));
+        assert_eq!(test.magnitude(), "large");
+        // U is 50 more than its mean, so z = (50 - 0.5) / sqrt(10 * 10 * 21 / 12).
+        assert!(close(test.p_value, 0.000_182_7, 1e-7));
+
+        let same = RankSumTest::of(&[5.0, 5.0], &[5.0, 5.0, 5.0]).unwrap();
+        assert!(close(same.a12, 0.5, 1e-12));
+        assert!(close(same.p_value, 1.0, 1e-12));
+        assert_eq!(same.magnitude(), "negligible");
+    }
+
+    #[test]
+    fn rank_sum_test_with_ties() {
+        let test = RankSumTest::of(&[1.0, 2.0, 2.0, 3.0], &[2.0, 3.0, 3.0, 4.0]).unwrap();
+        // The 3 beats a 2, and ties with two 3s; the 2s each tie with a 2.
+        assert!(close(test.u, 3.0, 1e-12));
+        assert!(close(test.a12, 3.0 / 16.0, 1e-12));
+        assert!(test.p_value > 0.05 && test.p_value < 1.0);
+    }
+}
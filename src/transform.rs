@@ -0,0 +1,206 @@
+use clap::Args;
+use rand::Rng;
+
+use crate::knapsack::Knapsack;
+
+/// Ways to derive a new 0/1 instance from an existing one. They're applied in the order
+/// they're listed here (subsample, scale, set the capacity, shuffle), so, e.g., the
+/// capacity ratio is of the total weight of the items that were kept.
+#[derive(Debug, Clone, Default, Args)]
+pub struct Transformation {
+    /// Keep this many of the items, chosen at random.
+    #[arg(long)]
+    pub items: Option<usize>,
+
+    /// Multiply every value by this (rounding to the nearest integer).
+    #[arg(long)]
+    pub scale_values: Option<f64>,
+
+    /// Multiply every weight, and the capacity, by this (rounding to the nearest integer).
+    #[arg(long)]
+    pub scale_weights: Option<f64>,
+
+    /// Set the capacity to this fraction of the total weight of the items.
+    #[arg(long)]
+    pub capacity_ratio: Option<f64>,
+
+    /// Put the items in a random order.
+    #[arg(long)]
+    pub shuffle: bool,
+}
+
+impl Transformation {
+    /// Apply this transformation to `knapsack`.
+    ///
+    /// # Errors
+    ///
+    /// This fails if any of the settings don't make sense for `knapsack`, e.g., keeping
+    /// more items than it has; see the methods on [`Knapsack`] for the details.
+    pub fn apply<R: Rng + ?Sized>(
+        &self,
+        mut knapsack: Knapsack,
+        rng: &mut R,
+    ) -> anyhow::Result<Knapsack> {
+        if let Some(num_items) = self.items {
+            knapsack = knapsack.subsample(num_items, rng)?;
+        }
+        if self.scale_values.is_some() || self.scale_weights.is_some() {
+            knapsack = knapsack.scale(
+                self.scale_values.unwrap_or(1.0),
+                self.scale_weights.unwrap_or(1.0),
+            )?;
+        }
+        if let Some(ratio) = self.capacity_ratio {
+            knapsack = knapsack.with_capacity_ratio(ratio)?;
+        }
+        if self.shuffle {
+            knapsack = knapsack.shuffle(rng);
+        }
+        Ok(knapsack)
+    }
+
+    /// Describe what this transformation does, one step per line, for the header of
+    /// the new instance file.
+    #[must_use]
+    pub fn describe(&self) -> Vec<String> {
+        let mut steps = Vec::new();
+        if let Some(num_items) = self.items {
+            steps.push(format!("kept {num_items} items chosen at random"));
+        }
+        if let Some(factor) = self.scale_values {
+            steps.push(format!("multiplied the values by {factor}"));
+        }
+        if let Some(factor) = self.scale_weights {
+            steps.push(format!("multiplied the weights and capacity by {factor}"));
+        }
+        if let Some(ratio) = self.capacity_ratio {
+            steps.push(format!("set the capacity to {ratio} of the total weight"));
+        }
+        if self.shuffle {
+            steps.push("shuffled the items".to_string());
+        }
+        steps
+    }
+}
+
+#[expect(clippy::unwrap_used, reason = ".unwrap() is reasonable in tests")]
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_pcg::Pcg64;
+
+    use super::Transformation;
+    use crate::{item::Item, knapsack::Knapsack};
+
+    fn knapsack() -> Knapsack {
+        let items = (0..10).map(|id| Item::new(id, 10 + id, 20 + id)).collect();
+        Knapsack::new(items, 100).with_best_known(Some(60))
+    }
+
+    #[test]
+    fn capacity_ratio_is_of_the_remaining_items() {
+        let transformation = Transformation {
+            items: Some(4),
+            capacity_ratio: Some(0.5),
+            ..Transformation::default()
+        };
+        let knapsack = transformation
+            .apply(knapsack(), &mut Pcg64::seed_from_u64(0))
+            .unwrap();
+        assert_eq!(knapsack.num_items(), 4);
+        assert_eq!(knapsack.capacity(), knapsack.total_weight() / 2);
+        assert_eq!(knapsack.best_known(), None);
+        let ids = knapsack.items().iter().map(Item::id).collect::<Vec<_>>();
+        assert_eq!(ids, [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn whole_number_scaling_keeps_the_best_known_value() {
+        let transformation = Transformation {
+            scale_values: Some(3.0),
+            scale_weights: Some(2.0),
+            ..Transformation::default()
+        };
+        let knapsack = transformation
+            .apply(knapsack(), &mut Pcg64::seed_from_u64(0))
+            .unwrap();
+        assert_eq!(knapsack.items()[1], Item::new(1, 33, 42));
+        assert_eq!(knapsack.capacity(), 200);
+        assert_eq!(knapsack.best_known(), Some(180));
+
+        let halved = Transformation {
+            scale_weights: Some(0.5),
+            ..Transformation::default()
+        }
+        .apply(knapsack, &mut Pcg64::seed_from_u64(0))
+        .unwrap();
+        assert_eq!(halved.capacity(), 100);
+        assert_eq!(halved.best_known(), None);
+    }
+
+    #[test]
+    fn raising_amounts_to_one_drops_the_best_known_value() {
+        let knapsack =
+            Knapsack::new(vec![Item::new(1, 0, 5), Item::new(2, 7, 5)], 5).with_best_known(Some(7));
+        let scaled = Transformation {
+            scale_values: Some(2.0),
+            ..Transformation::default()
+        }
+        .apply(knapsack, &mut Pcg64::seed_from_u64(0))
+        .unwrap();
+        assert_eq!(scaled.items()[0], Item::new(1, 1, 5));
+        assert_eq!(scaled.best_known(), None);
+    }
+
+    #[test]
+    fn shuffling_keeps_the_items_and_renumbers_them() {
+        let transformation = Transformation {
+            shuffle: true,
+            ..Transformation::default()
+        };
+        let original = knapsack();
+        let shuffled = transformation
+            .apply(knapsack(), &mut Pcg64::seed_from_u64(0))
+            .unwrap();
+        let ids = shuffled.items().iter().map(Item::id).collect::<Vec<_>>();
+        assert_eq!(ids, (0..10).collect::<Vec<_>>());
+        let pairs = |knapsack: &Knapsack| {
+            let mut pairs = knapsack
+                .items()
+                .iter()
+                .map(|item| (item.value(), item.weight()))
+                .collect::<Vec<_>>();
+            pairs.sort_unstable();
+            pairs
+        };
+        assert_eq!(pairs(&original), pairs(&shuffled));
+        assert_ne!(original.items(), shuffled.items());
+        assert_eq!(shuffled.best_known(), Some(60));
+    }
+
+    #[test]
+    fn invalid_settings_are_errors() {
+        let rng = &mut Pcg64::seed_from_u64(0);
+        for transformation in [
+            Transformation {
+                items: Some(11),
+                ..Transformation::default()
+            },
+            Transformation {
+                scale_values: Some(-1.0),
+                ..Transformation::default()
+            },
+            // Too big to scale exactly.
+            Transformation {
+                scale_weights: Some(1e15),
+                ..Transformation::default()
+            },
+            Transformation {
+                capacity_ratio: Some(0.0),
+                ..Transformation::default()
+            },
+        ] {
+            assert!(transformation.apply(knapsack(), rng).is_err());
+        }
+    }
+}